@@ -1,5 +1,20 @@
-use crate::trace::{AccessKind, TraceAccess};
+use crate::{
+    error::Error,
+    index::{self, IndexFunction, SetIndexer},
+    measurement::{Measurement, MeasurementError},
+    observer::{self, CacheObserver},
+    series::{IntervalStats, TimeSeries},
+    trace::{AccessKind, TraceAccess},
+    translation::{PageMapping, PageTable},
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{BuildHasherDefault, Hasher},
+    ops::Range,
+};
 
+/// Way-prediction scheme used to pick the first way probed on a lookup.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PredictionStrategy {
     None,
@@ -7,13 +22,86 @@ pub enum PredictionStrategy {
     MultiColumn,
 }
 
+/// How blocks are mapped onto the ways of the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOrganization {
+    /// Every way is indexed by the same set index.
+    SetAssociative,
+    /// Each way uses its own hash of the block address; a block competes
+    /// with a different group of blocks in every way.
+    SkewedAssociative,
+    /// Direct-mapped with a second probe at the set whose top index bit is
+    /// flipped; a second-probe hit swaps the two lines (Agarwal's hash-rehash).
+    HashRehash,
+    /// Hash-rehash plus a per-line rehash bit: a miss on a line that sits in
+    /// its rehash location replaces it without a second probe.
+    ColumnAssociative,
+}
+
+impl CacheOrganization {
+    /// Direct-mapped organisations that probe a secondary (rehash) location.
+    pub fn is_rehash(self) -> bool {
+        matches!(
+            self,
+            CacheOrganization::HashRehash | CacheOrganization::ColumnAssociative
+        )
+    }
+}
+
+/// Which victim-buffer entry is discarded when the buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VictimPolicy {
+    /// Oldest by last use; copy-on-hit refreshes an entry.
+    Lru,
+    /// Oldest by insertion.
+    Fifo,
+}
+
+/// Which lines evicted from the main array are kept in the victim buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VictimFilter {
+    All,
+    DirtyOnly,
+    /// Lines that were never hit while in the main array.
+    UnreferencedOnly,
+}
+
+/// What a victim-buffer hit does to the buffer entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VictimHitAction {
+    /// Move the line back into the main array; the displaced line takes its place.
+    Swap,
+    /// Copy the line into the main array and keep the buffer entry.
+    Copy,
+}
+
+/// Geometry and policy knobs for a [`Cache`].
 #[derive(Debug, Clone)]
 pub struct CacheConfig {
     pub cache_size: usize,    // Bytes
     pub block_size: usize,    // Bytes
     pub associativity: usize, // set to 1 for Direct-Mapped
+    pub organization: CacheOrganization,
+    /// Sectors per block: 1 for whole-block lines, otherwise a tag covers
+    /// `block_size` bytes and each sector is valid (and fetched) on its own.
+    pub sectors_per_block: usize,
+    /// Set-index function for set-associative caches.
+    pub indexing: IndexFunction,
     pub victim_cache_entries: usize,
+    pub victim_policy: VictimPolicy,
+    pub victim_filter: VictimFilter,
+    pub victim_on_hit: VictimHitAction,
     pub prediction: PredictionStrategy,
+    /// Multi-column bit-vectors per set; `None` picks 1/2/4/8 from the associativity.
+    pub predictor_columns: Option<usize>,
+    /// On a demand miss to block `B`, also fetch block `B + 1` if it is not cached.
+    pub prefetch_next_line: bool,
+    /// Map trace addresses onto physical frames before indexing, as a
+    /// physically indexed cache behind an OS page allocator sees them.
+    pub page_mapping: Option<PageMapping>,
+    /// Ways each source may fill per set, indexed by source id (see
+    /// [`Cache::access_from`]). `None` leaves the whole set shared.
+    pub way_quotas: Option<Vec<usize>>,
 }
 
 impl Default for CacheConfig {
@@ -22,22 +110,448 @@ impl Default for CacheConfig {
             cache_size: 256 * 1024,
             block_size: 32,
             associativity: 4,
+            organization: CacheOrganization::SetAssociative,
+            sectors_per_block: 1,
+            indexing: IndexFunction::Modulo,
             victim_cache_entries: 0,
+            victim_policy: VictimPolicy::Lru,
+            victim_filter: VictimFilter::All,
+            victim_on_hit: VictimHitAction::Swap,
             prediction: PredictionStrategy::None,
+            predictor_columns: None,
+            prefetch_next_line: false,
+            page_mapping: None,
+            way_quotas: None,
         }
     }
 }
 
 impl CacheConfig {
+    /// Starts a builder seeded with the default configuration.
+    pub fn builder() -> CacheConfigBuilder {
+        CacheConfigBuilder::default()
+    }
+
     pub fn num_sets(&self) -> usize {
         let blocks = (self.cache_size / self.block_size).max(1);
         let ways = self.associativity.max(1);
         (blocks / ways).max(1)
     }
+
+    /// Checks that the geometry divides evenly and fits the chosen predictor.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.cache_size == 0 {
+            return Err(ConfigError::ZeroCacheSize);
+        }
+        if !self.block_size.is_power_of_two() {
+            return Err(ConfigError::BlockSizeNotPowerOfTwo(self.block_size));
+        }
+        if self.associativity == 0 {
+            return Err(ConfigError::ZeroAssociativity);
+        }
+        let sectors = self.sectors_per_block;
+        if !sectors.is_power_of_two() || sectors > 64 || sectors > self.block_size {
+            return Err(ConfigError::InvalidSectorCount {
+                sectors,
+                block_size: self.block_size,
+            });
+        }
+        let set_bytes = self.block_size.saturating_mul(self.associativity);
+        if self.cache_size < set_bytes {
+            return Err(ConfigError::CacheSmallerThanSet {
+                cache_size: self.cache_size,
+                set_bytes,
+            });
+        }
+        if !self.cache_size.is_multiple_of(set_bytes) {
+            return Err(ConfigError::UnevenSets {
+                cache_size: self.cache_size,
+                set_bytes,
+            });
+        }
+        let sets = self.cache_size / set_bytes;
+        if !sets.is_power_of_two() && self.indexing != IndexFunction::PrimeModulo {
+            return Err(ConfigError::SetCountNotPowerOfTwo(sets));
+        }
+        if self.organization != CacheOrganization::SetAssociative
+            && self.indexing != IndexFunction::Modulo
+        {
+            return Err(ConfigError::IndexingNeedsSetAssociative(
+                self.indexing.clone(),
+            ));
+        }
+        if let IndexFunction::BitSelect(bits) = &self.indexing {
+            let needed = sets.trailing_zeros() as usize;
+            let distinct = bits
+                .iter()
+                .enumerate()
+                .all(|(idx, bit)| *bit < 64 && !bits[..idx].contains(bit));
+            if bits.len() != needed || !distinct {
+                return Err(ConfigError::InvalidIndexBits {
+                    bits: bits.clone(),
+                    needed,
+                });
+            }
+        }
+        if self.organization != CacheOrganization::SetAssociative
+            && self.prediction != PredictionStrategy::None
+        {
+            return Err(ConfigError::PredictionNeedsSharedSet(self.organization));
+        }
+        if self.organization.is_rehash() {
+            if self.associativity != 1 || sets < 2 {
+                return Err(ConfigError::RehashNeedsDirectMapped {
+                    ways: self.associativity,
+                    sets,
+                });
+            }
+            if self.victim_cache_entries > 0 {
+                return Err(ConfigError::RehashWithVictimBuffer);
+            }
+            if self.prefetch_next_line {
+                return Err(ConfigError::RehashWithPrefetch);
+            }
+        }
+        if let Some(columns) = self.predictor_columns
+            && (columns == 0 || columns > self.associativity)
+        {
+            return Err(ConfigError::InvalidPredictorColumns {
+                columns,
+                ways: self.associativity,
+            });
+        }
+        if let Some(mapping) = &self.page_mapping {
+            mapping.validate()?;
+        }
+        if let Some(quotas) = &self.way_quotas {
+            self.validate_way_quotas(quotas)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that `quotas` give every source at least one way and split the
+    /// whole set, and that replacement is plain LRU.
+    pub fn validate_way_quotas(&self, quotas: &[usize]) -> Result<(), ConfigError> {
+        if self.organization != CacheOrganization::SetAssociative
+            || self.prediction != PredictionStrategy::None
+        {
+            return Err(ConfigError::PartitionNeedsLru);
+        }
+        if quotas.is_empty()
+            || quotas.contains(&0)
+            || quotas.iter().sum::<usize>() != self.associativity
+        {
+            return Err(ConfigError::InvalidWayQuotas {
+                quotas: quotas.to_vec(),
+                ways: self.associativity,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reasons a [`CacheConfig`] is rejected by [`CacheConfig::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    ZeroCacheSize,
+    ZeroAssociativity,
+    BlockSizeNotPowerOfTwo(usize),
+    /// Sectors per block must be a power of two, at most 64 and at most `block_size`.
+    InvalidSectorCount {
+        sectors: usize,
+        block_size: usize,
+    },
+    /// A single set (`block_size * associativity`) does not fit in the cache.
+    CacheSmallerThanSet {
+        cache_size: usize,
+        set_bytes: usize,
+    },
+    /// The cache size is not a whole number of sets.
+    UnevenSets {
+        cache_size: usize,
+        set_bytes: usize,
+    },
+    /// Only prime-modulo indexing can use a set count that is not a power of two.
+    SetCountNotPowerOfTwo(usize),
+    /// Skewed caches hash each way themselves and ignore the index function.
+    IndexingNeedsSetAssociative(IndexFunction),
+    /// Bit selection needs `needed` distinct address bits below 64.
+    InvalidIndexBits {
+        bits: Vec<u32>,
+        needed: usize,
+    },
+    /// Hash-rehash and column-associative caches are direct-mapped with at least two sets.
+    RehashNeedsDirectMapped {
+        ways: usize,
+        sets: usize,
+    },
+    /// The rehash location already plays the victim buffer's role.
+    RehashWithVictimBuffer,
+    /// Prefetched blocks would need their own probe sequence in a rehash cache.
+    RehashWithPrefetch,
+    /// Way predictors track per-set state, so every way must share a set index.
+    PredictionNeedsSharedSet(CacheOrganization),
+    /// Multi-column predictors need between 1 and `associativity` columns.
+    InvalidPredictorColumns {
+        columns: usize,
+        ways: usize,
+    },
+    /// A multi-core system needs at least one core.
+    ZeroCores,
+    /// Private caches cannot keep a victim buffer: lines cast out of it
+    /// would leave the core without the coherence protocol seeing them.
+    VictimBufferInMultiCore,
+    /// Directory mode tracks whole blocks, so private and shared caches must agree.
+    DirectoryBlockSizeMismatch {
+        private: usize,
+        shared: usize,
+    },
+    /// The directory cannot see blocks the shared cache casts out of a victim buffer.
+    DirectoryWithVictimBuffer,
+    /// A limited-pointer directory needs room for at least one sharer.
+    ZeroSharerPointers,
+    /// TLBs need a non-zero entry count that is a multiple of their associativity.
+    InvalidTlb {
+        entries: usize,
+        ways: usize,
+    },
+    /// Physical memory must hold at least one page.
+    PhysicalMemoryTooSmall {
+        bytes: u64,
+        page: u64,
+    },
+    /// The share of memory other processes hold must lie in `[0, 1)`.
+    InvalidOccupiedMemory,
+    /// Page coloring needs between 1 and `frames` colors.
+    InvalidPageColors {
+        colors: u64,
+        frames: u64,
+    },
+    /// Coherence is tracked by trace address, so caches in a multi-core
+    /// system cannot each map pages on their own.
+    PageMappingInMultiCore,
+    /// Way quotas must give each source at least one way and sum to the associativity.
+    InvalidWayQuotas {
+        quotas: Vec<usize>,
+        ways: usize,
+    },
+    /// Way partitioning replaces LRU within a set, so it needs a
+    /// set-associative cache without way prediction.
+    PartitionNeedsLru,
+    /// An access came from a source with no way quota or per-source stats.
+    UnknownSource {
+        source: usize,
+        sources: usize,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ZeroCacheSize => write!(f, "cache size must be non-zero"),
+            ConfigError::ZeroAssociativity => write!(f, "associativity must be at least 1"),
+            ConfigError::BlockSizeNotPowerOfTwo(size) => {
+                write!(f, "block size {size}B is not a power of two")
+            }
+            ConfigError::InvalidSectorCount {
+                sectors,
+                block_size,
+            } => write!(
+                f,
+                "{sectors} sectors cannot split a {block_size}B block \
+                 (need a power of two, at most 64 and at most the block size)"
+            ),
+            ConfigError::CacheSmallerThanSet {
+                cache_size,
+                set_bytes,
+            } => write!(
+                f,
+                "cache size {cache_size}B is smaller than one set ({set_bytes}B)"
+            ),
+            ConfigError::UnevenSets {
+                cache_size,
+                set_bytes,
+            } => write!(
+                f,
+                "cache size {cache_size}B is not a multiple of the set size ({set_bytes}B)"
+            ),
+            ConfigError::SetCountNotPowerOfTwo(sets) => {
+                write!(f, "set count {sets} is not a power of two")
+            }
+            ConfigError::IndexingNeedsSetAssociative(indexing) => write!(
+                f,
+                "{indexing} indexing only applies to set-associative caches"
+            ),
+            ConfigError::InvalidIndexBits { bits, needed } => write!(
+                f,
+                "bit-select indexing needs {needed} distinct bits below 64, got {bits:?}"
+            ),
+            ConfigError::RehashNeedsDirectMapped { ways, sets } => write!(
+                f,
+                "rehash organisations need a direct-mapped cache with at least 2 sets, \
+                 got {ways} ways and {sets} sets"
+            ),
+            ConfigError::RehashWithVictimBuffer => write!(
+                f,
+                "rehash organisations cannot be combined with a victim buffer"
+            ),
+            ConfigError::RehashWithPrefetch => {
+                write!(f, "rehash organisations do not support next-line prefetch")
+            }
+            ConfigError::PredictionNeedsSharedSet(organization) => write!(
+                f,
+                "way prediction requires a set-associative cache, not {organization:?}"
+            ),
+            ConfigError::InvalidPredictorColumns { columns, ways } => write!(
+                f,
+                "{columns} predictor columns is outside 1..={ways} for a {ways}-way cache"
+            ),
+            ConfigError::ZeroCores => write!(f, "a multi-core system needs at least one core"),
+            ConfigError::VictimBufferInMultiCore => {
+                write!(
+                    f,
+                    "private caches in a multi-core system cannot have a victim buffer"
+                )
+            }
+            ConfigError::DirectoryBlockSizeMismatch { private, shared } => write!(
+                f,
+                "private block size {private}B differs from the shared cache's {shared}B"
+            ),
+            ConfigError::DirectoryWithVictimBuffer => write!(
+                f,
+                "the shared cache in directory mode cannot have a victim buffer"
+            ),
+            ConfigError::ZeroSharerPointers => {
+                write!(f, "a limited-pointer directory needs at least one pointer")
+            }
+            ConfigError::InvalidTlb { entries, ways } => write!(
+                f,
+                "a TLB with {entries} entries cannot be {ways}-way set-associative"
+            ),
+            ConfigError::PhysicalMemoryTooSmall { bytes, page } => {
+                write!(f, "{bytes}B of physical memory cannot hold a {page}B page")
+            }
+            ConfigError::InvalidOccupiedMemory => write!(
+                f,
+                "the share of memory held by other processes must be at least 0 and below 1"
+            ),
+            ConfigError::InvalidPageColors { colors, frames } => write!(
+                f,
+                "{colors} page colors is outside 1..={frames} for the available frames"
+            ),
+            ConfigError::PageMappingInMultiCore => write!(
+                f,
+                "caches in a multi-core system cannot have their own page mapping"
+            ),
+            ConfigError::InvalidWayQuotas { quotas, ways } => write!(
+                f,
+                "way quotas {quotas:?} must each be at least 1 and sum to {ways}"
+            ),
+            ConfigError::PartitionNeedsLru => write!(
+                f,
+                "way partitioning requires a set-associative cache without way prediction"
+            ),
+            ConfigError::UnknownSource { source, sources } => write!(
+                f,
+                "source {source} is outside the {sources} sources the cache was set up for"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Chained setters over [`CacheConfig`], starting from its defaults.
+#[derive(Debug, Clone, Default)]
+pub struct CacheConfigBuilder {
+    config: CacheConfig,
+}
+
+impl CacheConfigBuilder {
+    pub fn cache_size(mut self, bytes: usize) -> Self {
+        self.config.cache_size = bytes;
+        self
+    }
+
+    pub fn block_size(mut self, bytes: usize) -> Self {
+        self.config.block_size = bytes;
+        self
+    }
+
+    pub fn associativity(mut self, ways: usize) -> Self {
+        self.config.associativity = ways;
+        self
+    }
+
+    pub fn organization(mut self, organization: CacheOrganization) -> Self {
+        self.config.organization = organization;
+        self
+    }
+
+    pub fn sectors_per_block(mut self, sectors: usize) -> Self {
+        self.config.sectors_per_block = sectors;
+        self
+    }
+
+    pub fn indexing(mut self, indexing: IndexFunction) -> Self {
+        self.config.indexing = indexing;
+        self
+    }
+
+    pub fn victim_cache_entries(mut self, entries: usize) -> Self {
+        self.config.victim_cache_entries = entries;
+        self
+    }
+
+    pub fn victim_policy(mut self, policy: VictimPolicy) -> Self {
+        self.config.victim_policy = policy;
+        self
+    }
+
+    pub fn victim_filter(mut self, filter: VictimFilter) -> Self {
+        self.config.victim_filter = filter;
+        self
+    }
+
+    pub fn victim_on_hit(mut self, action: VictimHitAction) -> Self {
+        self.config.victim_on_hit = action;
+        self
+    }
+
+    pub fn prediction(mut self, strategy: PredictionStrategy) -> Self {
+        self.config.prediction = strategy;
+        self
+    }
+
+    pub fn predictor_columns(mut self, columns: usize) -> Self {
+        self.config.predictor_columns = Some(columns);
+        self
+    }
+
+    pub fn prefetch_next_line(mut self, enabled: bool) -> Self {
+        self.config.prefetch_next_line = enabled;
+        self
+    }
+
+    pub fn page_mapping(mut self, mapping: PageMapping) -> Self {
+        self.config.page_mapping = Some(mapping);
+        self
+    }
+
+    pub fn way_quotas(mut self, quotas: Vec<usize>) -> Self {
+        self.config.way_quotas = Some(quotas);
+        self
+    }
+
+    pub fn build(self) -> Result<CacheConfig, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
 }
 
 // ===== Cache Stat Utility =====
 
+/// Aggregate counters collected while a [`Cache`] services accesses.
 #[derive(Debug, Clone)]
 pub struct CacheStats {
     pub accesses: u64,
@@ -46,11 +560,19 @@ pub struct CacheStats {
     pub hits: u64,
     pub misses: u64,
     pub victim_hits: u64,
+    /// Lines placed into the victim buffer.
+    pub victim_fills: u64,
+    /// Victim hits that exchanged the hit line with the displaced main-array line.
+    pub victim_swaps: u64,
     pub prediction: Option<PredictionStats>,
+    pub rehash: Option<RehashStats>,
+    pub sectors: Option<SectorStats>,
+    pub traffic: TrafficStats,
 }
 
 impl CacheStats {
-    pub fn new(prediction: PredictionStrategy) -> Self {
+    /// Empty counters, with prediction and rehash sections matching `config`.
+    pub fn new(config: &CacheConfig) -> Self {
         Self {
             accesses: 0,
             reads: 0,
@@ -58,11 +580,60 @@ impl CacheStats {
             hits: 0,
             misses: 0,
             victim_hits: 0,
-            prediction: match prediction {
+            victim_fills: 0,
+            victim_swaps: 0,
+            prediction: match config.prediction {
                 PredictionStrategy::None => None,
                 mode => Some(PredictionStats::new(mode)),
             },
+            rehash: config.organization.is_rehash().then(RehashStats::default),
+            sectors: (config.sectors_per_block > 1).then(SectorStats::default),
+            traffic: TrafficStats::default(),
+        }
+    }
+
+    /// Folds one access into the counters.
+    pub fn record(&mut self, outcome: &AccessOutcome) {
+        self.accesses += 1;
+        match outcome.kind {
+            AccessKind::Read => self.reads += 1,
+            AccessKind::Write => self.writes += 1,
+        }
+        if outcome.hit {
+            self.hits += 1;
+            if outcome.from_victim {
+                self.victim_hits += 1;
+            }
+        } else {
+            self.misses += 1;
+        }
+        if outcome.evicted.is_some_and(|line| line.to_victim) {
+            self.victim_fills += 1;
         }
+        if outcome.victim_swap {
+            self.victim_swaps += 1;
+        }
+        if let (Some(stats), Some(prediction)) = (self.prediction.as_mut(), &outcome.prediction) {
+            stats.record(prediction);
+        }
+        if let (Some(stats), Some(rehash)) = (self.rehash.as_mut(), &outcome.rehash) {
+            stats.record(outcome.hit, rehash);
+        }
+        if let Some(stats) = self.sectors.as_mut() {
+            if outcome.sector_miss {
+                stats.sector_misses += 1;
+            } else if !outcome.hit {
+                stats.tag_misses += 1;
+            }
+        }
+        self.traffic.fill_bytes += outcome.fetched_bytes;
+        self.traffic.writeback_bytes += outcome.writeback_bytes;
+        self.traffic.victim_transfer_bytes += outcome.victim_transfer_bytes;
+        if let Some(prefetch) = &outcome.prefetch {
+            self.traffic.prefetches += 1;
+            self.traffic.prefetch_bytes += prefetch.bytes;
+        }
+        self.traffic.uncached_bytes += outcome.size;
     }
 
     pub fn hit_rate(&self) -> f64 {
@@ -80,16 +651,115 @@ impl CacheStats {
             self.victim_hits as f64 / self.hits as f64
         }
     }
+
+    /// Bytes exchanged with the next level per access.
+    pub fn traffic_per_access(&self) -> f64 {
+        if self.accesses == 0 {
+            0.0
+        } else {
+            self.traffic.next_level_bytes() as f64 / self.accesses as f64
+        }
+    }
+
+    /// Next-level traffic relative to sending every access straight to memory.
+    pub fn traffic_ratio(&self) -> f64 {
+        if self.traffic.uncached_bytes == 0 {
+            0.0
+        } else {
+            self.traffic.next_level_bytes() as f64 / self.traffic.uncached_bytes as f64
+        }
+    }
+
+    /// Share of all accesses that hit only because of the victim buffer.
+    pub fn victim_hit_contribution(&self) -> f64 {
+        if self.accesses == 0 {
+            0.0
+        } else {
+            self.victim_hits as f64 / self.accesses as f64
+        }
+    }
+}
+
+/// Bytes moved between the cache and the next level.
+#[derive(Debug, Clone, Default)]
+pub struct TrafficStats {
+    /// Demand fills: whole blocks, or single sectors when sectored.
+    pub fill_bytes: u64,
+    pub prefetches: u64,
+    pub prefetch_bytes: u64,
+    /// Valid sectors of dirty lines leaving the cache, including victim-buffer castouts.
+    pub writeback_bytes: u64,
+    /// Moves between the main array and the victim buffer. These stay on
+    /// chip and are not part of [`TrafficStats::next_level_bytes`].
+    pub victim_transfer_bytes: u64,
+    /// What an uncached system would move: one access-sized transfer per access.
+    pub uncached_bytes: u64,
+}
+
+impl TrafficStats {
+    pub fn next_level_bytes(&self) -> u64 {
+        self.fill_bytes + self.prefetch_bytes + self.writeback_bytes
+    }
+}
+
+/// Miss breakdown for sectored caches.
+#[derive(Debug, Clone, Default)]
+pub struct SectorStats {
+    /// No line held the block's tag.
+    pub tag_misses: u64,
+    /// The tag was resident but the requested sector was not.
+    pub sector_misses: u64,
+}
+
+/// Probe counters for hash-rehash and column-associative caches.
+#[derive(Debug, Clone, Default)]
+pub struct RehashStats {
+    pub first_probe_hits: u64,
+    pub second_probe_hits: u64,
+    /// Accesses that exchanged the primary and rehash lines.
+    pub swaps: u64,
+    /// Misses that skipped the second probe because of the rehash bit.
+    pub rehash_bit_replacements: u64,
+}
+
+impl RehashStats {
+    fn record(&mut self, hit: bool, outcome: &RehashOutcome) {
+        match (hit, outcome.second_probe) {
+            (true, false) => self.first_probe_hits += 1,
+            (true, true) => self.second_probe_hits += 1,
+            (false, _) => {}
+        }
+        if outcome.swapped {
+            self.swaps += 1;
+        }
+        if outcome.rehash_bit_replacement {
+            self.rehash_bit_replacements += 1;
+        }
+    }
+
+    /// Fraction of hits that needed the second probe.
+    pub fn second_probe_share(&self) -> f64 {
+        let hits = self.first_probe_hits + self.second_probe_hits;
+        if hits == 0 {
+            0.0
+        } else {
+            self.second_probe_hits as f64 / hits as f64
+        }
+    }
 }
 
+/// Way-prediction counters, present only when a predictor is configured.
 #[derive(Debug, Clone)]
 pub struct PredictionStats {
     pub mode: PredictionStrategy,
     pub first_hits: u64,
     pub non_first_hits: u64,
     pub total_hits_observed: u64,
+    pub correct_predictions: u64,
     pub bit_vector_search_total: u64,
     pub bit_vector_observations: u64,
+    /// Ways read by the predicted lookup, summed over main-array hits.
+    pub ways_probed_total: u64,
 }
 
 impl PredictionStats {
@@ -99,8 +769,27 @@ impl PredictionStats {
             first_hits: 0,
             non_first_hits: 0,
             total_hits_observed: 0,
+            correct_predictions: 0,
             bit_vector_search_total: 0,
             bit_vector_observations: 0,
+            ways_probed_total: 0,
+        }
+    }
+
+    fn record(&mut self, outcome: &PredictionOutcome) {
+        self.total_hits_observed += 1;
+        if outcome.first_hit {
+            self.first_hits += 1;
+        } else {
+            self.non_first_hits += 1;
+        }
+        if outcome.correct {
+            self.correct_predictions += 1;
+        }
+        self.ways_probed_total += outcome.ways_probed as u64;
+        if let Some(length) = outcome.search_length {
+            self.bit_vector_search_total += length as u64;
+            self.bit_vector_observations += 1;
         }
     }
 
@@ -120,6 +809,15 @@ impl PredictionStats {
         }
     }
 
+    /// Fraction of main-array hits found in the predicted way.
+    pub fn accuracy(&self) -> f64 {
+        if self.total_hits_observed == 0 {
+            0.0
+        } else {
+            self.correct_predictions as f64 / self.total_hits_observed as f64
+        }
+    }
+
     pub fn avg_bit_vector_search(&self) -> f64 {
         if self.bit_vector_observations == 0 {
             0.0
@@ -129,231 +827,853 @@ impl PredictionStats {
     }
 }
 
+// ===== Per-access outcome =====
+
+/// What a single [`Cache::access`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessOutcome {
+    pub kind: AccessKind,
+    /// Bytes the access itself read or wrote.
+    pub size: u64,
+    pub block_address: u64,
+    pub set_index: usize,
+    pub hit: bool,
+    /// Way now holding the block: the hit way, or the way it was filled into.
+    pub way: usize,
+    /// The block was recovered from the victim buffer; a hit unless its sector was missing.
+    pub from_victim: bool,
+    /// The tag was present but the requested sector had to be fetched.
+    pub sector_miss: bool,
+    /// Bytes read from memory to service the access.
+    pub fetched_bytes: u64,
+    /// Bytes written to memory by dirty lines leaving the cache.
+    pub writeback_bytes: u64,
+    /// Bytes moved between the main array and the victim buffer.
+    pub victim_transfer_bytes: u64,
+    /// Next-line prefetch issued by this access.
+    pub prefetch: Option<PrefetchOutcome>,
+    /// Line displaced from the main array.
+    pub evicted: Option<EvictedLine>,
+    /// A victim hit swapped the displaced line into the buffer entry it came from.
+    pub victim_swap: bool,
+    /// Predictor verdict, reported for main-array hits when prediction is enabled.
+    pub prediction: Option<PredictionOutcome>,
+    /// Probe details for hash-rehash and column-associative caches.
+    pub rehash: Option<RehashOutcome>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvictedLine {
+    pub block_address: u64,
+    pub set_index: usize,
+    pub way: usize,
+    pub dirty: bool,
+    /// The line was kept in the victim buffer rather than leaving the cache.
+    pub to_victim: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefetchOutcome {
+    pub block_address: u64,
+    pub set_index: usize,
+    pub way: usize,
+    pub bytes: u64,
+    /// Line displaced to make room for the prefetched block.
+    pub evicted: Option<EvictedLine>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RehashOutcome {
+    /// The rehash location was probed.
+    pub second_probe: bool,
+    /// The primary and rehash lines were exchanged.
+    pub swapped: bool,
+    /// The primary line carried the rehash bit and was replaced without a second probe.
+    pub rehash_bit_replacement: bool,
+    /// Rehash location paired with the block's primary set.
+    pub rehash_set: usize,
+    /// Block a swap moved out of the primary set into the rehash location.
+    pub moved_block: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PredictionOutcome {
+    /// First way the predictor would probe, if it had an opinion.
+    pub predicted_way: Option<usize>,
+    pub correct: bool,
+    /// First hit on the line since it was filled.
+    pub first_hit: bool,
+    /// Ways probed in bit-vector order (multi-column only).
+    pub search_length: Option<u32>,
+    /// Ways whose tag and data were read before the line was found: the
+    /// predicted probe, then whatever fallback search the strategy uses.
+    pub ways_probed: u32,
+}
+
+/// A single-level LRU cache that can be stepped one access at a time.
 pub struct Cache {
     config: CacheConfig,
+    stats: CacheStats,
     sets: Vec<Vec<Option<CacheLine>>>,
     victim: Option<VictimBuffer>,
     prediction_mode: PredictionStrategy,
     multi_predictor: Option<MultiColumnPredictor>,
     next_stamp: u64,
     num_sets: usize,
+    sector_size: u64,
+    indexer: SetIndexer,
+    page_table: Option<PageTable>,
+    /// Source of the access being serviced; new lines are owned by it.
+    source: u32,
+    /// Cleared while warming, so accesses update state but not `stats`.
+    measuring: bool,
+    observers: Vec<Box<dyn CacheObserver>>,
 }
 
 impl Cache {
-    pub fn new(config: CacheConfig) -> Self {
+    /// Builds an empty cache, rejecting configurations that fail [`CacheConfig::validate`].
+    pub fn new(config: CacheConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
         let num_sets = config.num_sets();
         let ways = config.associativity.max(1);
-        let sets = (0..num_sets)
-            .map(|_| vec![None; ways])
-            .collect::<Vec<_>>();
+        let sets = (0..num_sets).map(|_| vec![None; ways]).collect::<Vec<_>>();
         let victim = if config.victim_cache_entries > 0 {
-            Some(VictimBuffer::new(config.victim_cache_entries))
+            Some(VictimBuffer::new(
+                config.victim_cache_entries,
+                config.victim_policy,
+                config.victim_filter,
+                config.victim_on_hit,
+            ))
         } else {
             None
         };
         let prediction_mode = config.prediction;
         let multi_predictor = match prediction_mode {
             PredictionStrategy::MultiColumn => {
-                Some(MultiColumnPredictor::new(num_sets, ways))
+                let columns = config
+                    .predictor_columns
+                    .unwrap_or_else(|| MultiColumnPredictor::default_columns(ways));
+                Some(MultiColumnPredictor::new(num_sets, ways, columns))
             }
             _ => None,
         };
-        Self {
+        let indexer = SetIndexer::new(&config.indexing, num_sets);
+        let sector_size = (config.block_size / config.sectors_per_block) as u64;
+        let page_table = config.page_mapping.as_ref().map(PageTable::new);
+        Ok(Self {
+            stats: CacheStats::new(&config),
             config,
             sets,
             victim,
             prediction_mode,
             multi_predictor,
             next_stamp: 1,
+            indexer,
+            sector_size,
             num_sets,
-        }
+            page_table,
+            source: 0,
+            measuring: true,
+            observers: Vec::new(),
+        })
     }
 
-    pub fn run_trace(&mut self, trace: &[TraceAccess]) -> CacheStats {
-        let mut stats = CacheStats::new(self.prediction_mode);
-        for access in trace {
-            self.process_access(access, &mut stats);
-        }
-        stats
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
     }
 
-    fn process_access(&mut self, access: &TraceAccess, stats: &mut CacheStats) {
-        stats.accesses += 1;
-        match access.kind {
-            AccessKind::Read => stats.reads += 1,
-            AccessKind::Write => stats.writes += 1,
-        }
-
-        let block_address = access.address / self.config.block_size as u64;
-        let set_index = (block_address % self.num_sets as u64) as usize;
-        let tag = block_address / self.num_sets as u64;
+    /// Counters accumulated since construction or the last [`Cache::reset_stats`].
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
 
-        // Capture what the predictor believes before mutate the state.
-        let observation = self.observe_prediction(set_index, block_address);
+    /// Clears the counters while keeping the cache contents warm.
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::new(&self.config);
+    }
 
-        if let Some((way, is_first_hit)) = self.touch_if_hit(set_index, tag) {
-            stats.hits += 1;
-            self.update_multi_column_on_hit(set_index, block_address, way);
-            self.record_prediction(&observation, Some((way, is_first_hit)), stats);
-            self.next_stamp += 1;
-            return;
-        }
+    /// Adds an observer that is notified of every subsequent access.
+    pub fn attach_observer(&mut self, observer: impl CacheObserver + 'static) {
+        self.observers.push(Box::new(observer));
+    }
 
-        let mut victim_line = self
-            .victim
-            .as_mut()
-            .and_then(|victim| victim.take(block_address));
-        if let Some(line) = victim_line.as_mut() {
-            line.stamp = self.next_stamp;
-        }
+    /// Removes and returns all attached observers.
+    pub fn detach_observers(&mut self) -> Vec<Box<dyn CacheObserver>> {
+        std::mem::take(&mut self.observers)
+    }
 
-        if let Some(line) = victim_line {
-            let (way, evicted) = self.install_line(set_index, line);
-            if let Some((evicted_line, evicted_way)) = evicted {
-                self.multi_column_on_evict(set_index, &evicted_line, evicted_way);
-                if let Some(victim) = self.victim.as_mut() {
-                    victim.insert(evicted_line, self.next_stamp);
-                }
-            }
-            if let Some(line) = self.sets[set_index]
-                .get_mut(way)
-                .and_then(|slot| slot.as_mut())
-            {
-                line.mark_hit();
-            }
-            self.update_multi_column_on_hit(set_index, block_address, way);
-            stats.hits += 1;
-            stats.victim_hits += 1;
-        } else {
-            let line = CacheLine::new(tag, block_address, self.next_stamp);
-            let (way, evicted) = self.install_line(set_index, line);
-            if let Some((evicted_line, evicted_way)) = evicted {
-                self.multi_column_on_evict(set_index, &evicted_line, evicted_way);
-                if let Some(victim) = self.victim.as_mut() {
-                    victim.insert(evicted_line, self.next_stamp);
-                }
-            }
-            stats.misses += 1;
-        }
+    /// Replays `trace` and returns the stats for this run only.
+    pub fn run_trace(&mut self, trace: &[TraceAccess]) -> CacheStats {
+        self.replay(trace, &Measurement::default())
+    }
 
-        self.next_stamp += 1;
+    /// Replays `trace`, counting only the accesses `measurement` selects, and
+    /// returns the stats for this run only.
+    pub fn run_measured(
+        &mut self,
+        trace: &[TraceAccess],
+        measurement: &Measurement,
+    ) -> Result<CacheStats, MeasurementError> {
+        measurement.validate()?;
+        Ok(self.replay(trace, measurement))
     }
 
-    fn observe_prediction(
-        &self,
-        set_index: usize,
-        block_address: u64,
-    ) -> PredictionObservation {
-        match self.prediction_mode {
-            PredictionStrategy::None => PredictionObservation::None,
-            PredictionStrategy::Mru => PredictionObservation::Mru {
-                predicted: self.mru_way(set_index),
-            },
-            PredictionStrategy::MultiColumn => {
-                let bits = self
-                    .multi_predictor
-                    .as_ref()
-                    .map(|mc| mc.observe(set_index, block_address))
-                    .unwrap_or(0);
-                PredictionObservation::MultiColumn { bits }
+    fn replay(&mut self, trace: &[TraceAccess], measurement: &Measurement) -> CacheStats {
+        self.reset_stats();
+        for (position, access) in trace.iter().enumerate() {
+            if measurement.is_measured(position, trace.len()) {
+                self.access(access);
+            } else {
+                self.warm(access);
             }
         }
+        self.stats.clone()
     }
 
-    fn record_prediction(
-        &self,
-        observation: &PredictionObservation,
-        actual: Option<(usize, bool)>,
-        stats: &mut CacheStats,
-    ) {
-        let pred_stats = match stats.prediction.as_mut() {
-            Some(stats) => stats,
-            None => return,
-        };
-        let Some((actual_way, is_first_hit)) = actual else {
-            return;
-        };
-        pred_stats.total_hits_observed += 1;
-        if is_first_hit {
-            pred_stats.first_hits += 1;
-        } else {
-            pred_stats.non_first_hits += 1;
+    /// [`Cache::run_measured`], also splitting the counted accesses into
+    /// `interval`-access slices of the trace. [`Cache::stats`] holds the
+    /// whole run afterwards.
+    pub fn run_series(
+        &mut self,
+        trace: &[TraceAccess],
+        measurement: &Measurement,
+        interval: usize,
+    ) -> Result<TimeSeries, Error> {
+        if interval == 0 {
+            return Err(Error::ZeroInterval);
         }
-        match observation {
-            PredictionObservation::None => {}
-            PredictionObservation::Mru { predicted } => {
-                let _ = predicted;
-            }
-            PredictionObservation::MultiColumn { bits } => {
-                if *bits == 0 {
-                    pred_stats.bit_vector_observations += 1;
-                    return;
-                }
-                let mask = 1u32 << actual_way;
-                let mut rank = None;
-                if bits & mask != 0 {
-                    let before = bits & (mask - 1);
-                    rank = Some(before.count_ones() + 1);
-                }
-                if let Some(rank) = rank {
-                    pred_stats.bit_vector_search_total += rank as u64;
+        measurement.validate()?;
+        self.reset_stats();
+        let mut intervals = Vec::new();
+        for (chunk, accesses) in trace.chunks(interval).enumerate() {
+            let start = chunk * interval;
+            let mut stats = CacheStats::new(&self.config);
+            for (offset, access) in accesses.iter().enumerate() {
+                if measurement.is_measured(start + offset, trace.len()) {
+                    stats.record(&self.access(access));
                 } else {
-                    pred_stats.bit_vector_search_total += bits.count_ones() as u64;
+                    self.warm(access);
                 }
-                pred_stats.bit_vector_observations += 1;
             }
+            intervals.push(IntervalStats { start, stats });
         }
+        Ok(TimeSeries {
+            interval,
+            intervals,
+        })
     }
 
-    fn touch_if_hit(&mut self, set_index: usize, tag: u64) -> Option<(usize, bool)> {
-        let set = &mut self.sets[set_index];
-        for (way, slot) in set.iter_mut().enumerate() {
-            if let Some(line) = slot {
-                if line.tag == tag {
-                    // Refresh the LRU stamp when see a hit.
-                    let is_first_hit = line.mark_hit();
-                    line.stamp = self.next_stamp;
-                    return Some((way, is_first_hit));
-                }
-            }
-        }
-        None
+    /// Replaces the way quotas, keeping the cache contents. Lines over a
+    /// source's new quota are not flushed; they go first when it next misses.
+    pub fn set_way_quotas(&mut self, quotas: Vec<usize>) -> Result<(), ConfigError> {
+        self.config.validate_way_quotas(&quotas)?;
+        self.config.way_quotas = Some(quotas);
+        Ok(())
     }
 
-    fn install_line(
+    /// [`Cache::access`] on behalf of `source`, which owns any line it brings
+    /// in. Under way quotas `source` must have a quota.
+    pub fn access_from(
         &mut self,
-        set_index: usize,
-        mut line: CacheLine,
-    ) -> (usize, Option<(CacheLine, usize)>) {
-        // Check for empty slots first
-        if let Some((idx, slot)) = self.sets[set_index]
-            .iter_mut()
-            .enumerate()
-            .find(|(_, slot)| slot.is_none())
+        source: u32,
+        access: &TraceAccess,
+    ) -> Result<AccessOutcome, ConfigError> {
+        if let Some(quotas) = &self.config.way_quotas
+            && source as usize >= quotas.len()
         {
-            line.stamp = self.next_stamp;
-            *slot = Some(line);
-            return (idx, None);
+            return Err(ConfigError::UnknownSource {
+                source: source as usize,
+                sources: quotas.len(),
+            });
         }
+        self.source = source;
+        let outcome = self.access(access);
+        self.source = 0;
+        Ok(outcome)
+    }
 
-        let idx = self.find_victim_index(set_index);
-        
-        // For MRU strategy, we implement LIP (LRU Insertion Policy).
-        // We reuse the victim's stamp so the new line stays at the LRU position.
-        if self.prediction_mode == PredictionStrategy::Mru {
-            if let Some(victim) = &self.sets[set_index][idx] {
-                line.stamp = victim.stamp;
+    /// Lines each source holds in the main array, indexed by source id.
+    pub fn occupancy(&self) -> Vec<usize> {
+        let mut held = Vec::new();
+        for line in self.sets.iter().flatten().flatten() {
+            let owner = line.owner as usize;
+            if held.len() <= owner {
+                held.resize(owner + 1, 0);
             }
+            held[owner] += 1;
         }
+        held
+    }
 
-        let set = &mut self.sets[set_index];
-        let evicted = set[idx].replace(line).unwrap();
-        (idx, Some((evicted, idx)))
+    /// Services a single access, updating the cache state and [`Cache::stats`].
+    pub fn access(&mut self, access: &TraceAccess) -> AccessOutcome {
+        let tick = self.next_stamp;
+        let outcome = self.process_access(access);
+        if self.measuring {
+            self.stats.record(&outcome);
+        }
+        if !self.observers.is_empty() {
+            observer::notify(&mut self.observers, tick, &outcome);
+        }
+        outcome
     }
 
-    fn find_victim_index(&self, set_index: usize) -> usize {
-        let set = &self.sets[set_index];
-        match self.prediction_mode {
+    /// Services an access that updates the cache state but not
+    /// [`Cache::stats`], as during warm-up. Observers still see it.
+    pub fn warm(&mut self, access: &TraceAccess) -> AccessOutcome {
+        let measuring = std::mem::replace(&mut self.measuring, false);
+        let outcome = self.access(access);
+        self.measuring = measuring;
+        outcome
+    }
+
+    pub(crate) fn set_measuring(&mut self, measuring: bool) {
+        self.measuring = measuring;
+    }
+
+    /// Whether `block_address` is held in the main array or the victim buffer.
+    pub fn contains(&self, block_address: u64) -> bool {
+        self.locate(block_address).is_some()
+            || self
+                .victim
+                .as_ref()
+                .is_some_and(|victim| victim.contains(block_address))
+    }
+
+    /// Drops every copy of `block_address`, writing dirty copies back to the
+    /// next level and counting them in [`TrafficStats::writeback_bytes`] unless
+    /// warming up. Returns whether a copy was held. Coherence invalidations
+    /// that hand the data to another cache [`Cache::clean`] the line first.
+    ///
+    /// Observers see the main-array copy leave through
+    /// [`CacheObserver::on_invalidation`].
+    pub fn invalidate(&mut self, block_address: u64) -> bool {
+        let buffered = self
+            .victim
+            .as_mut()
+            .and_then(|victim| victim.take(block_address));
+        if let Some(line) = &buffered
+            && self.measuring
+        {
+            self.stats.traffic.writeback_bytes += self.writeback_bytes(line);
+        }
+        let Some((set_index, way)) = self.locate(block_address) else {
+            return buffered.is_some();
+        };
+        if let Some(line) = self.sets[set_index][way].take() {
+            if self.measuring {
+                self.stats.traffic.writeback_bytes += self.writeback_bytes(&line);
+            }
+            self.multi_column_on_evict(set_index, &line, way);
+            let evicted = line.evicted_from(set_index, way);
+            let tick = self.next_stamp;
+            for observer in self.observers.iter_mut() {
+                observer.on_invalidation(tick, set_index, &evicted);
+            }
+        }
+        true
+    }
+
+    /// [`Cache::invalidate`] for every block overlapping `addresses`. Returns
+    /// how many blocks had a copy.
+    pub fn invalidate_range(&mut self, addresses: Range<u64>) -> usize {
+        let block_size = self.config.block_size as u64;
+        let first = addresses.start / block_size;
+        let end = addresses.end.div_ceil(block_size);
+        (first..end)
+            .filter(|&block_address| self.invalidate(block_address))
+            .count()
+    }
+
+    /// Clears the dirty bit on every copy of `block_address`, for when its
+    /// data has been written back by other means. Returns whether it was dirty.
+    pub fn clean(&mut self, block_address: u64) -> bool {
+        let mut was_dirty = self
+            .victim
+            .as_mut()
+            .is_some_and(|victim| victim.clean(block_address));
+        if let Some((set_index, way)) = self.locate(block_address)
+            && let Some(line) = self.sets[set_index][way].as_mut()
+        {
+            was_dirty |= std::mem::replace(&mut line.dirty, false);
+        }
+        was_dirty
+    }
+
+    /// Main-array slot holding `block_address`, as `(set, way)`.
+    fn locate(&self, block_address: u64) -> Option<(usize, usize)> {
+        let holds = |set_index: usize, way: usize| {
+            self.sets[set_index][way]
+                .as_ref()
+                .is_some_and(|line| line.block_address == block_address)
+        };
+        if self.config.organization.is_rehash() {
+            let primary = self.set_for_way(block_address, 0);
+            return [primary, primary ^ (self.num_sets >> 1)]
+                .into_iter()
+                .find(|&set_index| holds(set_index, 0))
+                .map(|set_index| (set_index, 0));
+        }
+        (0..self.sets[0].len())
+            .map(|way| (self.set_for_way(block_address, way), way))
+            .find(|&(set_index, way)| holds(set_index, way))
+    }
+
+    fn process_access(&mut self, access: &TraceAccess) -> AccessOutcome {
+        // Everything below, outcomes included, works on physical addresses.
+        let physical;
+        let access = match self.page_table.as_mut() {
+            Some(page_table) => {
+                physical = TraceAccess {
+                    address: page_table.translate(access.address),
+                    ..*access
+                };
+                let page_bytes = page_table.page_bytes();
+                // A frame taken from another page still holds that page's lines.
+                if let Some((_, frame)) = page_table.take_reclaimed() {
+                    self.invalidate_range(frame * page_bytes..(frame + 1) * page_bytes);
+                }
+                &physical
+            }
+            None => access,
+        };
+        if self.config.organization.is_rehash() {
+            return self.process_rehash_access(access);
+        }
+        let block_address = access.address / self.config.block_size as u64;
+        let sector = self.sector_of(access.address);
+        let is_write = access.kind == AccessKind::Write;
+
+        // Capture what the predictor believes before mutate the state.
+        let primary_set = self.set_for_way(block_address, 0);
+        let observation = self.observe_prediction(primary_set, block_address);
+
+        if let Some((way, first_hit)) = self.touch_if_hit(block_address, sector, is_write) {
+            let set_index = self.set_for_way(block_address, way);
+            self.update_multi_column_on_hit(set_index, block_address, way);
+            let prediction = first_hit
+                .and_then(|first_hit| self.resolve_prediction(&observation, way, first_hit));
+            let hit = first_hit.is_some();
+            self.next_stamp += 1;
+            return AccessOutcome {
+                kind: access.kind,
+                size: access.size,
+                block_address,
+                set_index,
+                hit,
+                way,
+                from_victim: false,
+                sector_miss: !hit,
+                fetched_bytes: if hit { 0 } else { self.sector_size },
+                writeback_bytes: 0,
+                victim_transfer_bytes: 0,
+                prefetch: None,
+                evicted: None,
+                victim_swap: false,
+                prediction,
+                rehash: None,
+            };
+        }
+
+        let stamp = self.next_stamp;
+        let mut victim_line = self
+            .victim
+            .as_mut()
+            .and_then(|victim| victim.lookup(block_address, stamp));
+        let mut sector_miss = false;
+        let mut victim_transfer_bytes = 0;
+        if let Some(line) = victim_line.as_mut() {
+            line.stamp = self.next_stamp;
+            line.dirty |= is_write;
+            victim_transfer_bytes += self.line_bytes(line);
+            sector_miss = !line.fill_sector(sector);
+        }
+        let from_victim = victim_line.is_some();
+        let line = victim_line.unwrap_or_else(|| {
+            CacheLine::new(
+                block_address,
+                sector,
+                self.next_stamp,
+                is_write,
+                self.source,
+            )
+        });
+
+        let (way, evicted) = self.install_line(block_address, line);
+        let set_index = self.set_for_way(block_address, way);
+        let mut writeback_bytes = 0;
+        let evicted = evicted.map(|(evicted_line, evicted_way)| {
+            let retired = self.retire_line(set_index, evicted_way, evicted_line);
+            writeback_bytes += retired.writeback_bytes;
+            victim_transfer_bytes += retired.victim_transfer_bytes;
+            retired.line
+        });
+        let victim_swap = from_victim
+            && self.config.victim_on_hit == VictimHitAction::Swap
+            && evicted.is_some_and(|line| line.to_victim);
+        if from_victim {
+            if !sector_miss
+                && let Some(line) = self.sets[set_index]
+                    .get_mut(way)
+                    .and_then(|slot| slot.as_mut())
+            {
+                line.mark_hit();
+            }
+            self.update_multi_column_on_hit(set_index, block_address, way);
+        }
+        let hit = from_victim && !sector_miss;
+
+        let prefetch = if self.config.prefetch_next_line && !from_victim {
+            self.prefetch(block_address.wrapping_add(1))
+        } else {
+            None
+        };
+        if let Some(retired) = prefetch.as_ref().and_then(|(_, retired)| retired.as_ref()) {
+            writeback_bytes += retired.writeback_bytes;
+            victim_transfer_bytes += retired.victim_transfer_bytes;
+        }
+
+        self.next_stamp += 1;
+        AccessOutcome {
+            kind: access.kind,
+            size: access.size,
+            block_address,
+            set_index,
+            hit,
+            way,
+            from_victim,
+            sector_miss,
+            fetched_bytes: if hit { 0 } else { self.sector_size },
+            writeback_bytes,
+            victim_transfer_bytes,
+            prefetch: prefetch.map(|(prefetch, _)| prefetch),
+            evicted,
+            victim_swap,
+            prediction: None,
+            rehash: None,
+        }
+    }
+
+    /// Direct-mapped lookup with a second probe at the rehash location.
+    ///
+    /// The block always ends up in its primary set: a second-probe hit swaps
+    /// it with the primary line, and a miss fills the primary set after
+    /// moving the old primary line into the rehash location (evicting
+    /// whatever was there). Column-associative caches skip the second probe
+    /// when the primary line is itself a rehashed line.
+    fn process_rehash_access(&mut self, access: &TraceAccess) -> AccessOutcome {
+        let block_address = access.address / self.config.block_size as u64;
+        let sector = self.sector_of(access.address);
+        let is_write = access.kind == AccessKind::Write;
+        let primary = self.set_for_way(block_address, 0);
+        let secondary = primary ^ (self.num_sets >> 1);
+        let stamp = self.next_stamp;
+        self.next_stamp += 1;
+        let mut outcome = AccessOutcome {
+            kind: access.kind,
+            size: access.size,
+            block_address,
+            set_index: primary,
+            hit: false,
+            way: 0,
+            from_victim: false,
+            sector_miss: false,
+            fetched_bytes: self.sector_size,
+            writeback_bytes: 0,
+            victim_transfer_bytes: 0,
+            prefetch: None,
+            evicted: None,
+            victim_swap: false,
+            prediction: None,
+            rehash: None,
+        };
+        let mut rehash = RehashOutcome {
+            second_probe: false,
+            swapped: false,
+            rehash_bit_replacement: false,
+            rehash_set: secondary,
+            moved_block: None,
+        };
+
+        if let Some(line) = self.sets[primary][0].as_mut()
+            && line.block_address == block_address
+        {
+            outcome.hit = line.fill_sector(sector);
+            if outcome.hit {
+                line.mark_hit();
+                outcome.fetched_bytes = 0;
+            }
+            outcome.sector_miss = !outcome.hit;
+            line.stamp = stamp;
+            line.dirty |= is_write;
+            outcome.rehash = Some(rehash);
+            return outcome;
+        }
+
+        let skip_second_probe = self.config.organization == CacheOrganization::ColumnAssociative
+            && self.sets[primary][0]
+                .as_ref()
+                .is_some_and(|line| line.rehashed);
+        if skip_second_probe {
+            let line = CacheLine::new(block_address, sector, stamp, is_write, self.source);
+            let old = self.sets[primary][0].replace(line);
+            outcome.writeback_bytes = old.as_ref().map_or(0, |line| self.writeback_bytes(line));
+            outcome.evicted = old.map(|line| line.evicted_from(primary, 0));
+            rehash.rehash_bit_replacement = true;
+            outcome.rehash = Some(rehash);
+            return outcome;
+        }
+
+        rehash.second_probe = true;
+        let mut incoming = match self.sets[secondary][0].take() {
+            Some(mut line) if line.block_address == block_address => {
+                outcome.hit = line.fill_sector(sector);
+                if outcome.hit {
+                    line.mark_hit();
+                    outcome.fetched_bytes = 0;
+                }
+                outcome.sector_miss = !outcome.hit;
+                line.dirty |= is_write;
+                line
+            }
+            other => {
+                // Put back whatever was in the rehash location; it is evicted
+                // below only if the primary line needs the slot.
+                self.sets[secondary][0] = other;
+                CacheLine::new(block_address, sector, stamp, is_write, self.source)
+            }
+        };
+        incoming.stamp = stamp;
+        incoming.rehashed = false;
+
+        if let Some(mut displaced) = self.sets[primary][0].replace(incoming) {
+            displaced.rehashed = true;
+            rehash.moved_block = Some(displaced.block_address);
+            let old = self.sets[secondary][0].replace(displaced);
+            outcome.writeback_bytes = old.as_ref().map_or(0, |line| self.writeback_bytes(line));
+            outcome.evicted = old.map(|line| line.evicted_from(secondary, 0));
+            rehash.swapped = true;
+        }
+        outcome.rehash = Some(rehash);
+        outcome
+    }
+
+    fn observe_prediction(&self, set_index: usize, block_address: u64) -> PredictionObservation {
+        match self.prediction_mode {
+            PredictionStrategy::None => PredictionObservation::None,
+            PredictionStrategy::Mru => PredictionObservation::Mru {
+                predicted: self.mru_way(set_index),
+            },
+            PredictionStrategy::MultiColumn => {
+                let bits = self
+                    .multi_predictor
+                    .as_ref()
+                    .map(|mc| mc.observe(set_index, block_address))
+                    .unwrap_or(WayMask::Small(0));
+                PredictionObservation::MultiColumn { bits }
+            }
+        }
+    }
+
+    fn resolve_prediction(
+        &self,
+        observation: &PredictionObservation,
+        actual_way: usize,
+        is_first_hit: bool,
+    ) -> Option<PredictionOutcome> {
+        let ways = self.config.associativity as u32;
+        match observation {
+            PredictionObservation::None => None,
+            // A wrong guess is followed by a parallel read of the other ways.
+            PredictionObservation::Mru { predicted } => Some(PredictionOutcome {
+                predicted_way: *predicted,
+                correct: *predicted == Some(actual_way),
+                first_hit: is_first_hit,
+                search_length: None,
+                ways_probed: if *predicted == Some(actual_way) {
+                    1
+                } else {
+                    ways
+                },
+            }),
+            PredictionObservation::MultiColumn { bits } => {
+                let predicted_way = bits.first();
+                let search_length = if bits.is_empty() {
+                    0
+                } else if bits.contains(actual_way) {
+                    bits.count_below(actual_way) + 1
+                } else {
+                    bits.count()
+                };
+                // Ways outside the bit vector are read in parallel once it runs out.
+                let ways_probed = if bits.contains(actual_way) {
+                    search_length
+                } else {
+                    ways
+                };
+                Some(PredictionOutcome {
+                    predicted_way,
+                    correct: predicted_way == Some(actual_way),
+                    first_hit: is_first_hit,
+                    search_length: Some(search_length),
+                    ways_probed,
+                })
+            }
+        }
+    }
+
+    /// Moves a line displaced from the main array into the victim buffer, or
+    /// out of the cache, accounting for any write-back it causes.
+    fn retire_line(&mut self, set_index: usize, way: usize, line: CacheLine) -> RetiredLine {
+        self.multi_column_on_evict(set_index, &line, way);
+        let mut retired = RetiredLine {
+            line: line.evicted_from(set_index, way),
+            writeback_bytes: 0,
+            victim_transfer_bytes: 0,
+        };
+        let line_bytes = self.line_bytes(&line);
+        let stamp = self.next_stamp;
+        match self.victim.as_mut() {
+            Some(victim) if victim.accepts(&line) => {
+                let castout = victim.insert(line, stamp);
+                retired.line.to_victim = true;
+                retired.victim_transfer_bytes = line_bytes;
+                retired.writeback_bytes = castout.map_or(0, |line| self.writeback_bytes(&line));
+            }
+            _ => retired.writeback_bytes = self.writeback_bytes(&line),
+        }
+        retired
+    }
+
+    /// Installs `block_address` with every sector valid unless it is already
+    /// cached in the main array or the victim buffer.
+    fn prefetch(&mut self, block_address: u64) -> Option<(PrefetchOutcome, Option<RetiredLine>)> {
+        if self.contains(block_address) {
+            return None;
+        }
+        let mut line = CacheLine::new(block_address, 0, self.next_stamp, false, self.source);
+        line.valid_sectors = u64::MAX >> (64 - self.config.sectors_per_block);
+        let (way, evicted) = self.install_line(block_address, line);
+        let set_index = self.set_for_way(block_address, way);
+        let retired = evicted.map(|(evicted_line, evicted_way)| {
+            self.retire_line(set_index, evicted_way, evicted_line)
+        });
+        let outcome = PrefetchOutcome {
+            block_address,
+            set_index,
+            way,
+            bytes: self.config.block_size as u64,
+            evicted: retired.as_ref().map(|retired| retired.line),
+        };
+        Some((outcome, retired))
+    }
+
+    /// Bytes of valid data held by `line`.
+    fn line_bytes(&self, line: &CacheLine) -> u64 {
+        line.valid_sectors.count_ones() as u64 * self.sector_size
+    }
+
+    fn writeback_bytes(&self, line: &CacheLine) -> u64 {
+        if line.dirty { self.line_bytes(line) } else { 0 }
+    }
+
+    /// Set holding `way`'s candidate slot for `block_address`.
+    fn set_for_way(&self, block_address: u64, way: usize) -> usize {
+        match self.config.organization {
+            CacheOrganization::SetAssociative
+            | CacheOrganization::HashRehash
+            | CacheOrganization::ColumnAssociative => self.indexer.set(block_address),
+            CacheOrganization::SkewedAssociative => {
+                index::skewed_set(block_address, way, self.num_sets.trailing_zeros())
+            }
+        }
+    }
+
+    /// Sector of the block that `address` falls in; always 0 when unsectored.
+    fn sector_of(&self, address: u64) -> u32 {
+        ((address % self.config.block_size as u64) / self.sector_size) as u32
+    }
+
+    /// Looks for a tag match and refreshes it. Returns the way plus
+    /// `Some(first_hit)` when the sector was present, or `None` when the
+    /// sector had to be fetched into the resident line.
+    fn touch_if_hit(
+        &mut self,
+        block_address: u64,
+        sector: u32,
+        is_write: bool,
+    ) -> Option<(usize, Option<bool>)> {
+        for way in 0..self.sets[0].len() {
+            let set_index = self.set_for_way(block_address, way);
+            if let Some(line) = self.sets[set_index][way].as_mut()
+                && line.block_address == block_address
+            {
+                // Refresh the LRU stamp when see a hit.
+                let first_hit = line.fill_sector(sector).then(|| line.mark_hit());
+                line.stamp = self.next_stamp;
+                line.dirty |= is_write;
+                return Some((way, first_hit));
+            }
+        }
+        None
+    }
+
+    fn install_line(
+        &mut self,
+        block_address: u64,
+        mut line: CacheLine,
+    ) -> (usize, Option<(CacheLine, usize)>) {
+        // Check for empty slots first. Way quotas only pick which line to
+        // replace, so a source may fill any empty way even past its quota:
+        // the others reclaim those ways through `partitioned_victim` once
+        // they need them, and no capacity sits idle in the meantime.
+        let ways = self.sets[0].len();
+        if let Some(way) =
+            (0..ways).find(|&way| self.sets[self.set_for_way(block_address, way)][way].is_none())
+        {
+            line.stamp = self.next_stamp;
+            let set_index = self.set_for_way(block_address, way);
+            self.sets[set_index][way] = Some(line);
+            return (way, None);
+        }
+
+        if self.config.organization == CacheOrganization::SkewedAssociative {
+            // Plain LRU across the differently-indexed candidate slots.
+            let way = (0..ways)
+                .min_by_key(|&way| {
+                    let set_index = self.set_for_way(block_address, way);
+                    self.sets[set_index][way]
+                        .as_ref()
+                        .map_or(u64::MIN, |line| line.stamp)
+                })
+                .unwrap();
+            let set_index = self.set_for_way(block_address, way);
+            let evicted = self.sets[set_index][way].replace(line).unwrap();
+            return (way, Some((evicted, way)));
+        }
+
+        let set_index = self.set_for_way(block_address, 0);
+        let idx = match &self.config.way_quotas {
+            Some(quotas) => self.partitioned_victim(set_index, quotas),
+            None => self.find_victim_index(set_index),
+        };
+
+        // For MRU strategy, we implement LIP (LRU Insertion Policy).
+        // We reuse the victim's stamp so the new line stays at the LRU position.
+        if self.prediction_mode == PredictionStrategy::Mru
+            && let Some(victim) = &self.sets[set_index][idx]
+        {
+            line.stamp = victim.stamp;
+        }
+
+        let set = &mut self.sets[set_index];
+        let evicted = set[idx].replace(line).unwrap();
+        (idx, Some((evicted, idx)))
+    }
+
+    fn find_victim_index(&self, set_index: usize) -> usize {
+        let set = &self.sets[set_index];
+        match self.prediction_mode {
             PredictionStrategy::Mru => set
                 .iter()
                 .enumerate()
@@ -366,8 +1686,7 @@ impl Cache {
                     .enumerate()
                     .map(|(way, slot)| {
                         let line = slot.as_ref().unwrap();
-                        let bits = predictor.observe(set_index, line.block_address);
-                        let is_hot = (bits >> way) & 1;
+                        let is_hot = predictor.is_marked(set_index, line.block_address, way);
                         (is_hot, line.stamp, way)
                     })
                     .min()
@@ -383,18 +1702,52 @@ impl Cache {
         }
     }
 
+    /// LRU way the current source may replace: one of its own lines once it
+    /// holds its quota in the set, otherwise a line of a source over its quota.
+    fn partitioned_victim(&self, set_index: usize, quotas: &[usize]) -> usize {
+        let set = &self.sets[set_index];
+        let mut held = vec![0; quotas.len()];
+        for line in set.iter().flatten() {
+            if let Some(count) = held.get_mut(line.owner as usize) {
+                *count += 1;
+            }
+        }
+        let source = self.source as usize;
+        let own_full = held[source] >= quotas[source];
+        let replaceable = |owner: usize| {
+            if own_full {
+                owner == source
+            } else {
+                owner != source
+                    && held
+                        .get(owner)
+                        .zip(quotas.get(owner))
+                        .is_none_or(|(held, quota)| held > quota)
+            }
+        };
+        let lines = || {
+            set.iter().enumerate().filter_map(|(way, slot)| {
+                slot.as_ref()
+                    .map(|line| (line.stamp, line.owner as usize, way))
+            })
+        };
+        // Lines left over from an earlier partition can leave no line that
+        // fits the rule; plain LRU resolves it.
+        lines()
+            .filter(|&(_, owner, _)| replaceable(owner))
+            .min()
+            .or_else(|| lines().min())
+            .map(|(_, _, way)| way)
+            .unwrap()
+    }
+
     fn update_multi_column_on_hit(&mut self, set_index: usize, block_address: u64, way: usize) {
         if let Some(predictor) = self.multi_predictor.as_mut() {
             predictor.mark(set_index, block_address, way);
         }
     }
 
-    fn multi_column_on_evict(
-        &mut self,
-        set_index: usize,
-        line: &CacheLine,
-        way: usize,
-    ) {
+    fn multi_column_on_evict(&mut self, set_index: usize, line: &CacheLine, way: usize) {
         if let Some(predictor) = self.multi_predictor.as_mut() {
             predictor.clear(set_index, line.block_address, way);
         }
@@ -410,23 +1763,56 @@ impl Cache {
     }
 }
 
+/// A line leaving the main array and the traffic that caused.
+struct RetiredLine {
+    line: EvictedLine,
+    writeback_bytes: u64,
+    victim_transfer_bytes: u64,
+}
+
 // ===== Cache line====
 
 #[derive(Clone)]
 struct CacheLine {
-    tag: u64,
     block_address: u64,
     stamp: u64,
     has_received_hit: bool,
+    dirty: bool,
+    /// Sits in its rehash location (column-associative caches only).
+    rehashed: bool,
+    /// One bit per sector holding valid data.
+    valid_sectors: u64,
+    /// Source that brought the line in.
+    owner: u32,
 }
 
 impl CacheLine {
-    fn new(tag: u64, block_address: u64, stamp: u64) -> Self {
+    fn new(block_address: u64, sector: u32, stamp: u64, dirty: bool, owner: u32) -> Self {
         Self {
-            tag,
             block_address,
             stamp,
             has_received_hit: false,
+            dirty,
+            rehashed: false,
+            valid_sectors: 1 << sector,
+            owner,
+        }
+    }
+
+    /// Marks `sector` valid. Returns true if it already was.
+    fn fill_sector(&mut self, sector: u32) -> bool {
+        let present = self.valid_sectors & (1 << sector) != 0;
+        self.valid_sectors |= 1 << sector;
+        present
+    }
+
+    fn evicted_from(&self, set_index: usize, way: usize) -> EvictedLine {
+        EvictedLine {
+            block_address: self.block_address,
+            set_index,
+            way,
+            dirty: self.dirty,
+            to_victim: false,
         }
     }
 
@@ -441,103 +1827,303 @@ impl CacheLine {
 
 // ===== Victim cache buffer =====
 
+const NIL: usize = usize::MAX;
+
+/// Multiplicative hash for block-address keys; SipHash dominates the cost of
+/// small buffers otherwise.
+#[derive(Default)]
+struct BlockHasher(u64);
+
+impl Hasher for BlockHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+}
+
+type BlockMap<V> = HashMap<u64, V, BuildHasherDefault<BlockHasher>>;
+
+struct VictimNode {
+    line: CacheLine,
+    prev: usize,
+    next: usize,
+}
+
+/// Fully-associative buffer of recently evicted lines.
+///
+/// Entries live in a slab threaded by a doubly-linked list kept in stamp
+/// order (head is the oldest), and a map from block address to slab index
+/// makes lookup, insertion and replacement O(1) regardless of capacity.
 struct VictimBuffer {
-    entries: Vec<CacheLine>,
+    nodes: Vec<VictimNode>,
+    free: Vec<usize>,
+    index: BlockMap<usize>,
+    head: usize,
+    tail: usize,
     capacity: usize,
+    policy: VictimPolicy,
+    filter: VictimFilter,
+    on_hit: VictimHitAction,
 }
 
 impl VictimBuffer {
-    fn new(capacity: usize) -> Self {
+    fn new(
+        capacity: usize,
+        policy: VictimPolicy,
+        filter: VictimFilter,
+        on_hit: VictimHitAction,
+    ) -> Self {
         Self {
-            entries: Vec::with_capacity(capacity),
+            nodes: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            index: BlockMap::with_capacity_and_hasher(capacity, Default::default()),
+            head: NIL,
+            tail: NIL,
             capacity,
+            policy,
+            filter,
+            on_hit,
         }
     }
 
-    fn take(&mut self, block_address: u64) -> Option<CacheLine> {
+    /// Returns the line for `block_address` if buffered. Swap removes the
+    /// entry; copy leaves a clean copy behind, refreshed under LRU.
+    fn lookup(&mut self, block_address: u64, stamp: u64) -> Option<CacheLine> {
         if self.capacity == 0 {
             return None;
         }
-        if let Some(idx) = self
-            .entries
-            .iter()
-            .position(|line| line.block_address == block_address)
-        {
-            return Some(self.entries.remove(idx));
+        let idx = *self.index.get(&block_address)?;
+        match self.on_hit {
+            VictimHitAction::Swap => Some(self.remove(idx)),
+            VictimHitAction::Copy => {
+                let entry = &mut self.nodes[idx].line;
+                let line = entry.clone();
+                // The main-array copy now owns the dirty data.
+                entry.dirty = false;
+                if self.policy == VictimPolicy::Lru {
+                    entry.stamp = stamp;
+                    self.unlink(idx);
+                    self.push_back(idx);
+                }
+                Some(line)
+            }
         }
-        None
     }
 
-    fn insert(&mut self, mut line: CacheLine, stamp: u64) {
+    fn accepts(&self, line: &CacheLine) -> bool {
+        match self.filter {
+            VictimFilter::All => true,
+            VictimFilter::DirtyOnly => line.dirty,
+            VictimFilter::UnreferencedOnly => !line.has_received_hit,
+        }
+    }
+
+    fn contains(&self, block_address: u64) -> bool {
+        self.index.contains_key(&block_address)
+    }
+
+    fn take(&mut self, block_address: u64) -> Option<CacheLine> {
+        let idx = *self.index.get(&block_address)?;
+        Some(self.remove(idx))
+    }
+
+    fn clean(&mut self, block_address: u64) -> bool {
+        match self.index.get(&block_address) {
+            Some(&idx) => std::mem::replace(&mut self.nodes[idx].line.dirty, false),
+            None => false,
+        }
+    }
+
+    /// Buffers `line` and returns the entry it displaced, if any.
+    fn insert(&mut self, mut line: CacheLine, stamp: u64) -> Option<CacheLine> {
         if self.capacity == 0 {
-            return;
+            return None;
         }
         line.stamp = stamp;
-        if self.entries.len() == self.capacity {
-            if let Some(idx) = self
-                .entries
-                .iter()
-                .enumerate()
-                .min_by_key(|(_, line)| line.stamp)
-                .map(|(idx, _)| idx)
-            {
-                self.entries.remove(idx);
+        // Copy-on-hit can leave a stale copy of the same block behind.
+        if let Some(&idx) = self.index.get(&line.block_address) {
+            self.remove(idx);
+        }
+        let mut castout = None;
+        if self.index.len() == self.capacity && self.head != NIL {
+            castout = Some(self.remove(self.head));
+        }
+        let node = VictimNode {
+            line,
+            prev: NIL,
+            next: NIL,
+        };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = node;
+                idx
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
             }
+        };
+        self.index.insert(self.nodes[idx].line.block_address, idx);
+        self.push_back(idx);
+        castout
+    }
+
+    fn remove(&mut self, idx: usize) -> CacheLine {
+        self.unlink(idx);
+        self.index.remove(&self.nodes[idx].line.block_address);
+        self.free.push(idx);
+        self.nodes[idx].line.clone()
+    }
+
+    fn unlink(&mut self, idx: usize) {
+        let (prev, next) = (self.nodes[idx].prev, self.nodes[idx].next);
+        match prev {
+            NIL => self.head = next,
+            prev => self.nodes[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.nodes[next].prev = prev,
+        }
+    }
+
+    fn push_back(&mut self, idx: usize) {
+        self.nodes[idx].prev = self.tail;
+        self.nodes[idx].next = NIL;
+        match self.tail {
+            NIL => self.head = idx,
+            tail => self.nodes[tail].next = idx,
         }
-        self.entries.push(line);
+        self.tail = idx;
     }
 }
 
 // ===== Prediction Utility ====
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 enum PredictionObservation {
     None,
     Mru { predicted: Option<usize> },
-    MultiColumn { bits: u32 },
+    MultiColumn { bits: WayMask },
+}
+
+/// Snapshot of a multi-column bit-vector. Caches up to 64 ways stay on the
+/// `Small` path so the common case never allocates.
+#[derive(Clone)]
+enum WayMask {
+    Small(u64),
+    Wide(Box<[u64]>),
 }
 
-struct MultiColumnPredictor {
-    bits: Vec<u32>,
+impl WayMask {
+    fn words(&self) -> &[u64] {
+        match self {
+            WayMask::Small(word) => std::slice::from_ref(word),
+            WayMask::Wide(words) => words,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            WayMask::Small(word) => *word == 0,
+            WayMask::Wide(words) => words.iter().all(|word| *word == 0),
+        }
+    }
+
+    fn contains(&self, way: usize) -> bool {
+        self.words()
+            .get(way / 64)
+            .is_some_and(|word| (word >> (way % 64)) & 1 != 0)
+    }
+
+    fn first(&self) -> Option<usize> {
+        self.words()
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != 0)
+            .map(|(idx, word)| idx * 64 + word.trailing_zeros() as usize)
+    }
+
+    fn count(&self) -> u32 {
+        self.words().iter().map(|word| word.count_ones()).sum()
+    }
+
+    /// Number of marked ways with an index below `way`.
+    fn count_below(&self, way: usize) -> u32 {
+        let words = self.words();
+        let (full, partial) = (way / 64, way % 64);
+        let below: u32 = words[..full.min(words.len())]
+            .iter()
+            .map(|word| word.count_ones())
+            .sum();
+        let tail = words
+            .get(full)
+            .map_or(0, |word| (word & ((1u64 << partial) - 1)).count_ones());
+        below + tail
+    }
+}
+
+pub(crate) struct MultiColumnPredictor {
+    bits: Vec<u64>,
     sets: usize,
     columns: usize,
+    words: usize,
 }
 
 impl MultiColumnPredictor {
-    fn new(num_sets: usize, ways: usize) -> Self {
-        let columns = match ways {
+    /// Column count used when [`CacheConfig::predictor_columns`] is unset.
+    pub(crate) fn default_columns(ways: usize) -> usize {
+        match ways {
             0..=1 => 1,
             2..=4 => 2,
             5..=8 => 4,
             _ => 8,
         }
-        .clamp(1, ways.max(1));
+        .clamp(1, ways.max(1))
+    }
+
+    fn new(num_sets: usize, ways: usize, columns: usize) -> Self {
+        let words = ways.max(1).div_ceil(64);
         Self {
-            bits: vec![0; num_sets * columns],
+            bits: vec![0; num_sets * columns * words],
             sets: num_sets,
             columns,
+            words,
         }
     }
 
-    fn observe(&self, set_index: usize, block_address: u64) -> u32 {
-        self.bits[self.index(set_index, self.column(block_address))]
+    fn observe(&self, set_index: usize, block_address: u64) -> WayMask {
+        let idx = self.index(set_index, self.column(block_address));
+        if self.words == 1 {
+            WayMask::Small(self.bits[idx])
+        } else {
+            WayMask::Wide(self.bits[idx..idx + self.words].into())
+        }
+    }
+
+    fn is_marked(&self, set_index: usize, block_address: u64, way: usize) -> bool {
+        let idx = self.index(set_index, self.column(block_address));
+        (self.bits[idx + way / 64] >> (way % 64)) & 1 != 0
     }
 
     fn mark(&mut self, set_index: usize, block_address: u64, way: usize) {
-        if way >= 32 {
-            return;
-        }
         let idx = self.index(set_index, self.column(block_address));
-        self.bits[idx] |= 1u32 << way;
+        self.bits[idx + way / 64] |= 1u64 << (way % 64);
     }
 
     fn clear(&mut self, set_index: usize, block_address: u64, way: usize) {
-        if way >= 32 {
-            return;
-        }
         let idx = self.index(set_index, self.column(block_address));
         // Clear bits to avoid predicting stale ways after eviction
-        self.bits[idx] &= !(1u32 << way);
+        self.bits[idx + way / 64] &= !(1u64 << (way % 64));
     }
 
     fn column(&self, block_address: u64) -> usize {
@@ -545,11 +2131,730 @@ impl MultiColumnPredictor {
             0
         } else {
             let tag = block_address / self.sets as u64;
-            (tag as usize) % self.columns
+            (tag % self.columns as u64) as usize
         }
     }
 
+    /// Offset of the first word of a column's bit-vector.
     fn index(&self, set_index: usize, column: usize) -> usize {
-        set_index * self.columns + column
+        (set_index * self.columns + column) * self.words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::{FrameAllocation, PageSize};
+    use std::{cell::RefCell, rc::Rc};
+
+    fn read(block_address: u64) -> TraceAccess {
+        TraceAccess {
+            kind: AccessKind::Read,
+            address: block_address * 32,
+            thread: 0,
+            size: 4,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn builder_overrides_only_the_fields_it_sets() {
+        let config = CacheConfig::builder()
+            .cache_size(8 * 1024)
+            .associativity(2)
+            .build()
+            .unwrap();
+        assert_eq!((config.cache_size, config.associativity), (8 * 1024, 2));
+        assert_eq!(config.block_size, CacheConfig::default().block_size);
+        assert_eq!(config.num_sets(), 128);
+    }
+
+    #[test]
+    fn runs_evict_the_least_recently_used_way_and_keep_contents_warm() {
+        let config = CacheConfig::builder()
+            .cache_size(4 * 32)
+            .associativity(2)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        // Blocks 0, 2 and 4 share set 0; block 4 evicts 2, the LRU way.
+        let stats = cache.run_trace(&[0, 2, 0, 4, 2].map(read));
+        assert_eq!((stats.accesses, stats.hits, stats.misses), (5, 1, 4));
+        // The next run starts with fresh counters but the same contents.
+        let stats = cache.run_trace(&[read(2)]);
+        assert_eq!((stats.accesses, stats.hits), (1, 1));
+    }
+
+    #[test]
+    fn access_outcomes_report_the_way_and_the_evicted_line() {
+        let config = CacheConfig::builder()
+            .cache_size(4 * 32)
+            .associativity(2)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        let write = TraceAccess {
+            kind: AccessKind::Write,
+            ..read(0)
+        };
+        let first = cache.access(&write);
+        assert_eq!((first.hit, first.set_index, first.way), (false, 0, 0));
+        assert_eq!(cache.access(&read(2)).way, 1);
+        let outcome = cache.access(&read(4));
+        assert_eq!(
+            outcome.evicted,
+            Some(EvictedLine {
+                block_address: 0,
+                set_index: 0,
+                way: 0,
+                dirty: true,
+                to_victim: false,
+            })
+        );
+        let again = cache.access(&read(4));
+        assert_eq!((again.hit, again.way, again.evicted), (true, 0, None));
+    }
+
+    /// Direct-mapped cache with two sets, so blocks 0, 2 and 4 conflict.
+    fn victim_cache(entries: usize) -> CacheConfigBuilder {
+        CacheConfig::builder()
+            .cache_size(2 * 32)
+            .associativity(1)
+            .victim_cache_entries(entries)
+    }
+
+    #[test]
+    fn victim_hits_swap_the_line_back_into_the_main_array() {
+        let mut cache = Cache::new(victim_cache(1).build().unwrap()).unwrap();
+        cache.access(&read(0));
+        cache.access(&read(2));
+        let outcome = cache.access(&read(0));
+        assert!(outcome.hit && outcome.from_victim && outcome.victim_swap);
+        assert_eq!(
+            outcome.evicted,
+            Some(EvictedLine {
+                block_address: 2,
+                set_index: 0,
+                way: 0,
+                dirty: false,
+                to_victim: true,
+            })
+        );
+        // Block 2 took block 0's place in the buffer, so it hits there too.
+        assert!(cache.access(&read(2)).from_victim);
+    }
+
+    #[test]
+    fn dirty_only_victim_buffers_drop_clean_lines() {
+        let config = victim_cache(1)
+            .victim_filter(VictimFilter::DirtyOnly)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        cache.access(&TraceAccess {
+            kind: AccessKind::Write,
+            ..read(0)
+        });
+        cache.access(&read(2));
+        let outcome = cache.access(&read(4));
+        assert!(!outcome.evicted.unwrap().to_victim);
+        assert!(!cache.access(&read(2)).hit);
+        assert!(cache.access(&read(0)).from_victim);
+    }
+
+    #[test]
+    fn lru_victim_buffers_refresh_copied_entries_and_fifo_ones_do_not() {
+        let second_hit = |policy| {
+            let config = victim_cache(2)
+                .victim_policy(policy)
+                .victim_on_hit(VictimHitAction::Copy)
+                .build()
+                .unwrap();
+            let mut cache = Cache::new(config).unwrap();
+            // Blocks 0 and 2 enter the buffer; the copy hit on 0 refreshes it
+            // under LRU, so inserting 4 discards 2 instead of 0.
+            let outcome = cache.run_trace(&[0, 2, 4, 0].map(read));
+            assert_eq!(outcome.hits, 1);
+            cache.access(&read(2)).hit
+        };
+        assert!(!second_hit(VictimPolicy::Lru));
+        assert!(second_hit(VictimPolicy::Fifo));
+    }
+
+    /// Buffered blocks from least to most recently inserted or refreshed.
+    fn recency_order(buffer: &VictimBuffer) -> Vec<u64> {
+        let mut order = Vec::new();
+        let mut idx = buffer.head;
+        while idx != NIL {
+            order.push(buffer.nodes[idx].line.block_address);
+            idx = buffer.nodes[idx].next;
+        }
+        order
+    }
+
+    #[test]
+    fn victim_buffers_reuse_slots_and_keep_recency_order() {
+        let mut buffer = VictimBuffer::new(
+            3,
+            VictimPolicy::Lru,
+            VictimFilter::All,
+            VictimHitAction::Copy,
+        );
+        for (stamp, block) in (10..15).enumerate() {
+            buffer.insert(CacheLine::new(block, 0, 0, false, 0), stamp as u64);
+        }
+        assert_eq!(recency_order(&buffer), [12, 13, 14]);
+        assert_eq!((buffer.nodes.len(), buffer.index.len()), (3, 3));
+        // A copy hit under LRU moves the entry to the most recent end.
+        assert!(buffer.lookup(12, 5).is_some());
+        assert_eq!(recency_order(&buffer), [13, 14, 12]);
+        buffer.insert(CacheLine::new(15, 0, 0, false, 0), 6);
+        assert_eq!(recency_order(&buffer), [14, 12, 15]);
+        assert_eq!(buffer.nodes.len(), 3);
+        assert!(buffer.lookup(13, 7).is_none());
+    }
+
+    #[test]
+    fn large_victim_buffers_hold_a_working_set_the_main_array_cannot() {
+        let config = CacheConfig::builder()
+            .cache_size(64 * 32)
+            .associativity(1)
+            .victim_cache_entries(4096)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        let trace = (0..4096).map(read).collect::<Vec<_>>();
+        cache.run_trace(&trace);
+        let stats = cache.run_trace(&trace);
+        assert_eq!((stats.hits, stats.victim_hits), (4096, 4096));
+    }
+
+    #[test]
+    fn skewing_removes_conflicts_between_blocks_sharing_a_set() {
+        let run = |organization| {
+            let config = CacheConfig::builder()
+                .cache_size(16 * 32)
+                .block_size(32)
+                .associativity(2)
+                .organization(organization)
+                .build()
+                .unwrap();
+            let mut cache = Cache::new(config).unwrap();
+            // Three blocks that all index set 0 of the 8-set, 2-way cache.
+            let trace: Vec<TraceAccess> = (0..10).flat_map(|_| [0, 8, 16].map(read)).collect();
+            cache.run_trace(&trace).misses
+        };
+        assert_eq!(run(CacheOrganization::SetAssociative), 30);
+        assert_eq!(run(CacheOrganization::SkewedAssociative), 3);
+    }
+
+    #[test]
+    fn skewed_lookups_find_blocks_in_their_alternate_way() {
+        let config = CacheConfig::builder()
+            .cache_size(16 * 32)
+            .associativity(2)
+            .organization(CacheOrganization::SkewedAssociative)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        // Block 3 takes way 0 of set 3, which is also block 10's way-0 slot.
+        assert_eq!(cache.access(&read(3)).set_index, 3);
+        let fill = cache.access(&read(10));
+        assert_eq!((fill.hit, fill.way, fill.set_index), (false, 1, 0));
+        let hit = cache.access(&read(10));
+        assert_eq!((hit.hit, hit.way, hit.set_index), (true, 1, 0));
+        assert!(cache.access(&read(3)).hit);
+    }
+
+    fn rehash_cache(organization: CacheOrganization) -> Cache {
+        let config = CacheConfig::builder()
+            .cache_size(4 * 32)
+            .block_size(32)
+            .associativity(1)
+            .organization(organization)
+            .build()
+            .unwrap();
+        Cache::new(config).unwrap()
+    }
+
+    #[test]
+    fn second_probe_hits_swap_into_the_primary_slot() {
+        let mut cache = rehash_cache(CacheOrganization::ColumnAssociative);
+        // Blocks 0 and 4 share primary set 0, whose rehash location is set 2.
+        for block in [0, 4, 0, 0] {
+            cache.access(&read(block));
+        }
+        let rehash = cache.stats().rehash.clone().unwrap();
+        assert_eq!(
+            (
+                rehash.first_probe_hits,
+                rehash.second_probe_hits,
+                rehash.swaps
+            ),
+            (1, 1, 2)
+        );
+        let outcome = cache.access(&read(4));
+        assert_eq!(outcome.rehash.map(|rehash| rehash.second_probe), Some(true));
+        assert!(outcome.hit);
+    }
+
+    #[test]
+    fn the_rehash_bit_protects_primary_lines() {
+        // Block 2's primary set 2 holds block 4 as a rehashed line, so the
+        // column-associative cache replaces it and keeps block 0 in set 0.
+        let trace = [0, 4, 0, 2, 0].map(read);
+        let mut column = rehash_cache(CacheOrganization::ColumnAssociative);
+        let stats = column.run_trace(&trace);
+        assert_eq!(stats.misses, 3);
+        assert_eq!(stats.rehash.unwrap().rehash_bit_replacements, 1);
+        // Hash-rehash probes set 0 instead and pushes block 0 out.
+        let mut hash_rehash = rehash_cache(CacheOrganization::HashRehash);
+        assert_eq!(hash_rehash.run_trace(&trace).misses, 4);
+    }
+
+    #[test]
+    fn warmup_keeps_cold_misses_out_of_the_stats() {
+        let trace: Vec<TraceAccess> = (0..12).map(|index| read(index % 4)).collect();
+        let mut cache = Cache::new(CacheConfig::default()).unwrap();
+        let cold = cache.run_trace(&trace);
+        assert_eq!((cold.accesses, cold.misses), (12, 4));
+
+        let mut cache = Cache::new(CacheConfig::default()).unwrap();
+        let warm = cache.run_measured(&trace, &Measurement::warmup(4)).unwrap();
+        assert_eq!((warm.accesses, warm.hits, warm.misses), (8, 8, 0));
+
+        let mut cache = Cache::new(CacheConfig::default()).unwrap();
+        let windowed = Measurement {
+            windows: vec![2..4, 8..10],
+            ..Measurement::default()
+        };
+        let stats = cache.run_measured(&trace, &windowed).unwrap();
+        assert_eq!((stats.accesses, stats.hits, stats.misses), (4, 2, 2));
+    }
+
+    #[test]
+    fn measured_runs_reject_invalid_measurements() {
+        let mut cache = Cache::new(CacheConfig::default()).unwrap();
+        let empty_window = Measurement {
+            windows: vec![0..2, 3..3],
+            ..Measurement::default()
+        };
+        assert_eq!(
+            cache.run_measured(&[read(0)], &empty_window).unwrap_err(),
+            MeasurementError::EmptyWindow
+        );
+        assert_eq!(
+            cache
+                .run_measured(&[read(0)], &Measurement::warmup_fraction(1.5))
+                .unwrap_err(),
+            MeasurementError::WarmupFractionOutOfRange
+        );
+        assert_eq!(cache.stats().accesses, 0);
+    }
+
+    #[test]
+    fn series_intervals_leave_the_warmup_empty() {
+        let trace: Vec<TraceAccess> = (0..12).map(|index| read(index % 4)).collect();
+        let mut cache = Cache::new(CacheConfig::default()).unwrap();
+        let series = cache
+            .run_series(&trace, &Measurement::warmup(4), 4)
+            .unwrap();
+        let counts: Vec<(usize, u64, u64)> = series
+            .intervals
+            .iter()
+            .map(|interval| (interval.start, interval.stats.accesses, interval.stats.hits))
+            .collect();
+        assert_eq!(counts, vec![(0, 0, 0), (4, 4, 4), (8, 4, 4)]);
+        assert_eq!(cache.stats().accesses, 8);
+
+        assert_eq!(
+            cache
+                .run_series(&trace, &Measurement::default(), 0)
+                .unwrap_err(),
+            Error::ZeroInterval
+        );
+    }
+
+    #[derive(Default)]
+    struct FillLog(Vec<(usize, usize, u64)>);
+
+    impl CacheObserver for FillLog {
+        fn on_fill(&mut self, _tick: u64, set_index: usize, way: usize, block_address: u64) {
+            self.0.push((set_index, way, block_address));
+        }
+    }
+
+    #[test]
+    fn rehash_swaps_report_both_moved_lines_to_observers() {
+        let mut cache = rehash_cache(CacheOrganization::ColumnAssociative);
+        let fills = Rc::new(RefCell::new(FillLog::default()));
+        cache.attach_observer(Rc::clone(&fills));
+        // Blocks 0 and 4 share primary set 0, whose rehash location is set 2.
+        cache.access(&read(0));
+        cache.access(&read(4));
+        let outcome = cache.access(&read(0));
+        assert!(outcome.hit);
+        assert_eq!(
+            fills.borrow().0,
+            [(0, 0, 0), (0, 0, 4), (2, 0, 0), (0, 0, 0), (2, 0, 4)]
+        );
+    }
+
+    #[test]
+    fn sectored_lines_fetch_only_the_missing_sector() {
+        let config = CacheConfig::builder()
+            .cache_size(4 * 64)
+            .block_size(64)
+            .associativity(2)
+            .sectors_per_block(4)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        let at = |address| TraceAccess { address, ..read(0) };
+        let first = cache.access(&at(0));
+        assert_eq!(
+            (first.hit, first.sector_miss, first.fetched_bytes),
+            (false, false, 16)
+        );
+        let second = cache.access(&at(40));
+        assert_eq!(
+            (second.hit, second.sector_miss, second.fetched_bytes),
+            (false, true, 16)
+        );
+        assert_eq!(second.way, first.way);
+        let third = cache.access(&at(44));
+        assert_eq!((third.hit, third.fetched_bytes), (true, 0));
+        let sectors = cache.stats().sectors.clone().unwrap();
+        assert_eq!((sectors.tag_misses, sectors.sector_misses), (1, 1));
+    }
+
+    fn write(block_address: u64) -> TraceAccess {
+        TraceAccess {
+            kind: AccessKind::Write,
+            ..read(block_address)
+        }
+    }
+
+    #[test]
+    fn dirty_evictions_write_back_whole_lines() {
+        let mut cache = Cache::new(victim_cache(0).build().unwrap()).unwrap();
+        let stats = cache.run_trace(&[write(0), read(2), read(4)]);
+        let traffic = &stats.traffic;
+        assert_eq!((traffic.fill_bytes, traffic.writeback_bytes), (96, 32));
+        assert_eq!(traffic.uncached_bytes, 3 * 4);
+        assert_eq!(traffic.next_level_bytes(), 128);
+        assert_eq!(stats.traffic_per_access(), 128.0 / 3.0);
+    }
+
+    #[test]
+    fn victim_castouts_write_back_and_transfers_are_counted_separately() {
+        let mut cache = Cache::new(victim_cache(1).build().unwrap()).unwrap();
+        let stats = cache.run_trace(&[write(0), write(2), read(4)]);
+        let traffic = &stats.traffic;
+        // Block 0 moves to the buffer, then block 2 pushes it out dirty.
+        assert_eq!(traffic.victim_transfer_bytes, 64);
+        assert_eq!(traffic.writeback_bytes, 32);
+        assert_eq!(traffic.next_level_bytes(), 3 * 32 + 32);
+    }
+
+    #[test]
+    fn next_line_prefetch_turns_sequential_misses_into_hits() {
+        let config = CacheConfig::builder()
+            .cache_size(16 * 32)
+            .associativity(2)
+            .prefetch_next_line(true)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        let stats = cache.run_trace(&(0..8).map(read).collect::<Vec<_>>());
+        assert_eq!((stats.misses, stats.hits), (4, 4));
+        assert_eq!(
+            (stats.traffic.prefetches, stats.traffic.prefetch_bytes),
+            (4, 4 * 32)
+        );
+    }
+
+    fn rejected(builder: CacheConfigBuilder) -> ConfigError {
+        builder.build().unwrap_err()
+    }
+
+    #[test]
+    fn zero_sized_caches_are_rejected() {
+        assert_eq!(
+            rejected(CacheConfig::builder().cache_size(0)),
+            ConfigError::ZeroCacheSize
+        );
+    }
+
+    #[test]
+    fn zero_ways_are_rejected() {
+        assert_eq!(
+            rejected(CacheConfig::builder().associativity(0)),
+            ConfigError::ZeroAssociativity
+        );
+    }
+
+    #[test]
+    fn block_sizes_must_be_powers_of_two() {
+        assert_eq!(
+            rejected(CacheConfig::builder().block_size(48)),
+            ConfigError::BlockSizeNotPowerOfTwo(48)
+        );
+    }
+
+    #[test]
+    fn caches_must_hold_a_whole_set() {
+        assert_eq!(
+            rejected(CacheConfig::builder().cache_size(64).associativity(4)),
+            ConfigError::CacheSmallerThanSet {
+                cache_size: 64,
+                set_bytes: 128,
+            }
+        );
+    }
+
+    #[test]
+    fn caches_must_hold_a_whole_number_of_sets() {
+        assert_eq!(
+            rejected(CacheConfig::builder().cache_size(200).associativity(4)),
+            ConfigError::UnevenSets {
+                cache_size: 200,
+                set_bytes: 128,
+            }
+        );
+    }
+
+    #[test]
+    fn set_counts_must_be_powers_of_two() {
+        assert_eq!(
+            rejected(CacheConfig::builder().cache_size(3 * 128).associativity(4)),
+            ConfigError::SetCountNotPowerOfTwo(3)
+        );
+    }
+
+    #[test]
+    fn predictor_columns_must_fit_the_associativity() {
+        for columns in [0, 5] {
+            let builder = CacheConfig::builder()
+                .associativity(4)
+                .prediction(PredictionStrategy::MultiColumn)
+                .predictor_columns(columns);
+            assert_eq!(
+                rejected(builder),
+                ConfigError::InvalidPredictorColumns { columns, ways: 4 }
+            );
+        }
+    }
+
+    fn multi_column_search(columns: usize, block_address: u64) -> PredictionOutcome {
+        // One 128-way set, so the bit-vectors span two words.
+        let config = CacheConfig::builder()
+            .cache_size(128 * 32)
+            .associativity(128)
+            .prediction(PredictionStrategy::MultiColumn)
+            .predictor_columns(columns)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        // Ways are marked on hits, so the second pass fills the bit-vectors.
+        let trace = (0..100).map(read).collect::<Vec<_>>();
+        cache.run_trace(&trace);
+        cache.run_trace(&trace);
+        let outcome = cache.access(&read(block_address));
+        assert_eq!((outcome.hit, outcome.way), (true, block_address as usize));
+        outcome.prediction.unwrap()
+    }
+
+    #[test]
+    fn multi_column_searches_cover_ways_past_the_first_word() {
+        let single = multi_column_search(1, 70);
+        assert_eq!(single.predicted_way, Some(0));
+        assert_eq!(single.search_length, Some(71));
+        // With 8 columns, block 70 only shares its bit-vector with tags 6, 14, ...
+        let split = multi_column_search(8, 70);
+        assert_eq!(split.predicted_way, Some(6));
+        assert_eq!(split.search_length, Some(9));
+    }
+
+    #[test]
+    fn way_predictors_need_a_shared_set_index() {
+        let builder = CacheConfig::builder()
+            .organization(CacheOrganization::SkewedAssociative)
+            .prediction(PredictionStrategy::Mru);
+        assert_eq!(
+            rejected(builder),
+            ConfigError::PredictionNeedsSharedSet(CacheOrganization::SkewedAssociative)
+        );
+    }
+
+    #[test]
+    fn prime_modulo_indexing_accepts_any_set_count() {
+        let config = CacheConfig::builder()
+            .cache_size(8 * 32)
+            .associativity(1)
+            .indexing(IndexFunction::PrimeModulo)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        // Blocks 0, 8 and 16 share set 0 under modulo but not modulo 7.
+        let trace: Vec<TraceAccess> = (0..5).flat_map(|_| [0, 8, 16].map(read)).collect();
+        assert_eq!(cache.run_trace(&trace).misses, 3);
+        let builder = CacheConfig::builder()
+            .cache_size(3 * 128)
+            .associativity(4)
+            .indexing(IndexFunction::PrimeModulo);
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn index_functions_only_apply_to_set_associative_caches() {
+        let builder = CacheConfig::builder()
+            .organization(CacheOrganization::SkewedAssociative)
+            .indexing(IndexFunction::XorFold);
+        assert_eq!(
+            rejected(builder),
+            ConfigError::IndexingNeedsSetAssociative(IndexFunction::XorFold)
+        );
+    }
+
+    #[test]
+    fn bit_select_needs_one_distinct_bit_per_index_bit() {
+        // 128 sets need 7 index bits.
+        for bits in [
+            vec![0, 1],
+            vec![0, 1, 2, 3, 4, 5, 5],
+            vec![0, 1, 2, 3, 4, 5, 64],
+        ] {
+            let builder = CacheConfig::builder()
+                .cache_size(128 * 32)
+                .associativity(1)
+                .indexing(IndexFunction::BitSelect(bits.clone()));
+            assert_eq!(
+                rejected(builder),
+                ConfigError::InvalidIndexBits { bits, needed: 7 }
+            );
+        }
+    }
+
+    #[test]
+    fn rehash_organisations_need_a_direct_mapped_cache() {
+        let builder = CacheConfig::builder()
+            .associativity(2)
+            .organization(CacheOrganization::HashRehash);
+        assert_eq!(
+            rejected(builder),
+            ConfigError::RehashNeedsDirectMapped {
+                ways: 2,
+                sets: 4096,
+            }
+        );
+        let builder = CacheConfig::builder()
+            .cache_size(32)
+            .associativity(1)
+            .organization(CacheOrganization::ColumnAssociative);
+        assert_eq!(
+            rejected(builder),
+            ConfigError::RehashNeedsDirectMapped { ways: 1, sets: 1 }
+        );
+    }
+
+    #[test]
+    fn rehash_organisations_reject_victim_buffers() {
+        let builder = CacheConfig::builder()
+            .associativity(1)
+            .victim_cache_entries(4)
+            .organization(CacheOrganization::ColumnAssociative);
+        assert_eq!(rejected(builder), ConfigError::RehashWithVictimBuffer);
+    }
+
+    #[test]
+    fn sector_counts_must_split_the_block_evenly() {
+        for (sectors, block_size) in [(3, 32), (0, 32), (64, 32), (128, 256)] {
+            let builder = CacheConfig::builder()
+                .block_size(block_size)
+                .sectors_per_block(sectors);
+            assert_eq!(
+                rejected(builder),
+                ConfigError::InvalidSectorCount {
+                    sectors,
+                    block_size,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn rehash_organisations_reject_prefetch() {
+        let builder = CacheConfig::builder()
+            .associativity(1)
+            .prefetch_next_line(true)
+            .organization(CacheOrganization::HashRehash);
+        assert_eq!(rejected(builder), ConfigError::RehashWithPrefetch);
+    }
+
+    #[test]
+    fn page_mapped_caches_index_by_frame_and_write_back_reclaimed_pages() {
+        let page = PageSize::Base4K.bytes();
+        let mapping = PageMapping {
+            allocation: FrameAllocation::Sequential,
+            physical_memory: page,
+            ..PageMapping::default()
+        };
+        let config = CacheConfig::builder()
+            .cache_size(8 * 1024)
+            .associativity(1)
+            .page_mapping(mapping)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        cache.access(&write(0));
+        // Page 1 takes the only frame, so it shares page 0's sets and its
+        // arrival writes page 0's dirty line back.
+        let outcome = cache.access(&read(page / 32));
+        assert!(!outcome.hit);
+        assert_eq!(cache.stats().traffic.writeback_bytes, 32);
+        assert!(!cache.access(&read(0)).hit);
+        assert_eq!(cache.stats().traffic.writeback_bytes, 32);
+    }
+
+    #[test]
+    fn page_mappings_are_validated_with_the_cache() {
+        let mapping = PageMapping {
+            physical_memory: 1024,
+            ..PageMapping::default()
+        };
+        assert_eq!(
+            rejected(CacheConfig::builder().page_mapping(mapping)),
+            ConfigError::PhysicalMemoryTooSmall {
+                bytes: 1024,
+                page: 4096,
+            }
+        );
+        let mapping = PageMapping {
+            allocation: FrameAllocation::PageColored { colors: 0 },
+            ..PageMapping::default()
+        };
+        assert!(matches!(
+            rejected(CacheConfig::builder().page_mapping(mapping)),
+            ConfigError::InvalidPageColors { colors: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn way_quotas_must_cover_the_set_of_a_plain_lru_cache() {
+        for quotas in [vec![], vec![4, 0], vec![2, 1]] {
+            assert_eq!(
+                rejected(CacheConfig::builder().way_quotas(quotas.clone())),
+                ConfigError::InvalidWayQuotas { quotas, ways: 4 }
+            );
+        }
+        let predicted = CacheConfig::builder()
+            .prediction(PredictionStrategy::Mru)
+            .way_quotas(vec![2, 2]);
+        assert_eq!(rejected(predicted), ConfigError::PartitionNeedsLru);
+        let skewed = CacheConfig::builder()
+            .organization(CacheOrganization::SkewedAssociative)
+            .way_quotas(vec![2, 2]);
+        assert_eq!(rejected(skewed), ConfigError::PartitionNeedsLru);
     }
 }
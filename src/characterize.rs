@@ -0,0 +1,444 @@
+use crate::trace::{AccessKind, TraceAccess};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// What a trace characterisation measures and at which granularities.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharacterizeConfig {
+    /// Granularities, in bytes, at which unique addresses are counted.
+    pub footprint_granularities: Vec<u64>,
+    /// Block size, in bytes, for reuse distances, regions and windows.
+    pub block_size: u64,
+    /// Accesses per working-set window.
+    pub window: usize,
+    /// Regions are aligned `2^region_bits`-byte ranges of the address space.
+    pub region_bits: u32,
+    /// Most common strides and busiest regions kept in the report.
+    pub top: usize,
+}
+
+impl Default for CharacterizeConfig {
+    fn default() -> Self {
+        Self {
+            footprint_granularities: vec![4, 32, 64, 4096],
+            block_size: 32,
+            window: 10_000,
+            region_bits: 20,
+            top: 8,
+        }
+    }
+}
+
+impl CharacterizeConfig {
+    pub fn validate(&self) -> Result<(), CharacterizeError> {
+        if !self.block_size.is_power_of_two() {
+            return Err(CharacterizeError::BlockSizeNotPowerOfTwo(self.block_size));
+        }
+        if let Some(&granularity) = self
+            .footprint_granularities
+            .iter()
+            .find(|granularity| !granularity.is_power_of_two())
+        {
+            return Err(CharacterizeError::GranularityNotPowerOfTwo(granularity));
+        }
+        if self.window == 0 {
+            return Err(CharacterizeError::ZeroWindow);
+        }
+        if self.region_bits >= 64 {
+            return Err(CharacterizeError::RegionBitsTooLarge(self.region_bits));
+        }
+        Ok(())
+    }
+}
+
+/// Reasons a [`CharacterizeConfig`] is rejected by [`CharacterizeConfig::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterizeError {
+    BlockSizeNotPowerOfTwo(u64),
+    /// Footprints are counted at power-of-two granularities only.
+    GranularityNotPowerOfTwo(u64),
+    /// Working-set windows need at least one access.
+    ZeroWindow,
+    /// Regions must be smaller than the 64-bit address space.
+    RegionBitsTooLarge(u32),
+}
+
+impl fmt::Display for CharacterizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CharacterizeError::BlockSizeNotPowerOfTwo(size) => {
+                write!(f, "block size {size}B is not a power of two")
+            }
+            CharacterizeError::GranularityNotPowerOfTwo(granularity) => {
+                write!(
+                    f,
+                    "footprint granularity {granularity}B is not a power of two"
+                )
+            }
+            CharacterizeError::ZeroWindow => {
+                write!(f, "working-set windows must hold at least one access")
+            }
+            CharacterizeError::RegionBitsTooLarge(bits) => {
+                write!(f, "{bits} region bits leave no room below a 64-bit address")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CharacterizeError {}
+
+/// Distinct addresses touched at one granularity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Footprint {
+    pub granularity: u64,
+    pub unique: u64,
+}
+
+impl Footprint {
+    pub fn bytes(&self) -> u64 {
+        self.unique * self.granularity
+    }
+}
+
+/// LRU stack distances: how many distinct blocks were touched between two
+/// accesses to the same block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReuseHistogram {
+    /// Bucket 0 counts distance 0; bucket `k` counts distances in
+    /// `2^(k-1)..2^k`.
+    pub buckets: Vec<u64>,
+    /// First touches of a block, which have no reuse distance.
+    pub cold: u64,
+}
+
+impl ReuseHistogram {
+    fn record(&mut self, distance: u64) {
+        let bucket = (u64::BITS - distance.leading_zeros()) as usize;
+        if self.buckets.len() <= bucket {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+    }
+
+    /// Distances counted in `bucket`.
+    pub fn bucket_range(bucket: usize) -> std::ops::Range<u64> {
+        match bucket {
+            0 => 0..1,
+            _ => 1 << (bucket - 1)..1 << bucket,
+        }
+    }
+
+    /// Share of all accesses that reuse a block with a stack distance below
+    /// `blocks`: the hit rate of a fully associative LRU cache of that many
+    /// blocks. `blocks` is rounded down to a power of two.
+    pub fn hit_rate_within(&self, blocks: u64) -> f64 {
+        let total = self.cold + self.buckets.iter().sum::<u64>();
+        if total == 0 || blocks == 0 {
+            return 0.0;
+        }
+        let limit = (u64::BITS - blocks.leading_zeros()) as usize;
+        let hits: u64 = self.buckets.iter().take(limit).sum();
+        hits as f64 / total as f64
+    }
+}
+
+/// Accesses that fell in one aligned address region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionReport {
+    pub base_address: u64,
+    pub accesses: u64,
+    pub writes: u64,
+    pub unique_blocks: u64,
+}
+
+/// Everything [`characterize`] measures about one trace.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceReport {
+    pub accesses: u64,
+    pub reads: u64,
+    pub writes: u64,
+    pub footprints: Vec<Footprint>,
+    pub reuse: ReuseHistogram,
+    /// Byte strides between consecutive accesses and how often each occurs,
+    /// most common first.
+    pub top_strides: Vec<(i64, u64)>,
+    pub distinct_strides: usize,
+    /// Busiest regions, most accesses first.
+    pub top_regions: Vec<RegionReport>,
+    pub regions: usize,
+    /// Accesses per window.
+    pub window: usize,
+    /// Distinct blocks touched in each window, in order.
+    pub working_sets: Vec<u64>,
+}
+
+impl TraceReport {
+    pub fn write_ratio(&self) -> f64 {
+        if self.accesses == 0 {
+            0.0
+        } else {
+            self.writes as f64 / self.accesses as f64
+        }
+    }
+
+    /// Mean and largest working set over the windows, in blocks.
+    pub fn working_set_summary(&self) -> (f64, u64) {
+        let largest = self.working_sets.iter().copied().max().unwrap_or(0);
+        let mean = if self.working_sets.is_empty() {
+            0.0
+        } else {
+            self.working_sets.iter().sum::<u64>() as f64 / self.working_sets.len() as f64
+        };
+        (mean, largest)
+    }
+}
+
+/// Binary indexed tree over access times, marking the latest access to
+/// each block so stack distances are prefix-sum differences.
+struct Fenwick(Vec<i64>);
+
+impl Fenwick {
+    fn new(len: usize) -> Self {
+        Self(vec![0; len + 1])
+    }
+
+    fn add(&mut self, index: usize, delta: i64) {
+        let mut i = index + 1;
+        while i < self.0.len() {
+            self.0[i] += delta;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    /// Sum over `0..end`.
+    fn prefix(&self, end: usize) -> i64 {
+        let mut sum = 0;
+        let mut i = end;
+        while i > 0 {
+            sum += self.0[i];
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    }
+}
+
+/// Characterises `trace` without simulating a cache.
+pub fn characterize(
+    trace: &[TraceAccess],
+    config: &CharacterizeConfig,
+) -> Result<TraceReport, CharacterizeError> {
+    config.validate()?;
+    let block_shift = config.block_size.trailing_zeros();
+    let mut report = TraceReport {
+        accesses: trace.len() as u64,
+        window: config.window,
+        ..TraceReport::default()
+    };
+
+    let mut footprints: Vec<HashSet<u64>> =
+        vec![HashSet::new(); config.footprint_granularities.len()];
+    let mut last_touch: HashMap<u64, usize> = HashMap::new();
+    let mut stack = Fenwick::new(trace.len());
+    let mut strides: HashMap<i64, u64> = HashMap::new();
+    let mut regions: HashMap<u64, (RegionReport, HashSet<u64>)> = HashMap::new();
+    let mut window: HashSet<u64> = HashSet::new();
+    let mut previous: Option<u64> = None;
+
+    for (time, access) in trace.iter().enumerate() {
+        match access.kind {
+            AccessKind::Read => report.reads += 1,
+            AccessKind::Write => report.writes += 1,
+        }
+        for (seen, granularity) in footprints.iter_mut().zip(&config.footprint_granularities) {
+            seen.insert(access.address / granularity);
+        }
+
+        let block = access.address >> block_shift;
+        match last_touch.insert(block, time) {
+            Some(last) => {
+                let distance = stack.prefix(time) - stack.prefix(last + 1);
+                report.reuse.record(distance as u64);
+                stack.add(last, -1);
+            }
+            None => report.reuse.cold += 1,
+        }
+        stack.add(time, 1);
+
+        if let Some(previous) = previous {
+            *strides
+                .entry(access.address.wrapping_sub(previous) as i64)
+                .or_default() += 1;
+        }
+        previous = Some(access.address);
+
+        let region_base = access.address >> config.region_bits << config.region_bits;
+        let (region, blocks) = regions.entry(region_base).or_insert_with(|| {
+            (
+                RegionReport {
+                    base_address: region_base,
+                    accesses: 0,
+                    writes: 0,
+                    unique_blocks: 0,
+                },
+                HashSet::new(),
+            )
+        });
+        region.accesses += 1;
+        region.writes += u64::from(access.kind == AccessKind::Write);
+        blocks.insert(block);
+
+        window.insert(block);
+        if (time + 1).is_multiple_of(config.window) || time + 1 == trace.len() {
+            report.working_sets.push(window.len() as u64);
+            window.clear();
+        }
+    }
+
+    report.footprints = config
+        .footprint_granularities
+        .iter()
+        .zip(&footprints)
+        .map(|(&granularity, seen)| Footprint {
+            granularity,
+            unique: seen.len() as u64,
+        })
+        .collect();
+
+    report.distinct_strides = strides.len();
+    let mut strides: Vec<(i64, u64)> = strides.into_iter().collect();
+    strides.sort_by(|(a_stride, a), (b_stride, b)| b.cmp(a).then(a_stride.cmp(b_stride)));
+    strides.truncate(config.top);
+    report.top_strides = strides;
+
+    report.regions = regions.len();
+    let mut regions: Vec<RegionReport> = regions
+        .into_values()
+        .map(|(mut region, blocks)| {
+            region.unique_blocks = blocks.len() as u64;
+            region
+        })
+        .collect();
+    regions.sort_by(|a, b| {
+        b.accesses
+            .cmp(&a.accesses)
+            .then(a.base_address.cmp(&b.base_address))
+    });
+    regions.truncate(config.top);
+    report.top_regions = regions;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(accesses: &[(AccessKind, u64)]) -> Vec<TraceAccess> {
+        accesses
+            .iter()
+            .enumerate()
+            .map(|(time, &(kind, address))| TraceAccess {
+                kind,
+                address,
+                thread: 0,
+                size: 4,
+                timestamp: time as u64,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reuse_distances_count_the_distinct_blocks_in_between() {
+        let blocks = [0, 1, 2, 0, 1, 1];
+        let accesses: Vec<_> = blocks
+            .iter()
+            .map(|block| (AccessKind::Read, block * 32))
+            .collect();
+        let report = characterize(&trace(&accesses), &CharacterizeConfig::default()).unwrap();
+        assert_eq!(report.reuse.cold, 3);
+        assert_eq!(report.reuse.buckets, [1, 0, 2]);
+        assert_eq!(ReuseHistogram::bucket_range(2), 2..4);
+        // The two distance-2 reuses need three blocks of fully associative LRU.
+        assert_eq!(report.reuse.hit_rate_within(2), 1.0 / 6.0);
+        assert_eq!(report.reuse.hit_rate_within(4), 0.5);
+    }
+
+    #[test]
+    fn footprints_strides_regions_and_windows_are_reported() {
+        let accesses = trace(&[
+            (AccessKind::Read, 0x0),
+            (AccessKind::Write, 0x4),
+            (AccessKind::Read, 0x8),
+            (AccessKind::Write, 0x10_0000),
+        ]);
+        let config = CharacterizeConfig {
+            window: 2,
+            ..CharacterizeConfig::default()
+        };
+        let report = characterize(&accesses, &config).unwrap();
+        assert_eq!((report.reads, report.writes), (2, 2));
+        assert_eq!(report.write_ratio(), 0.5);
+        let unique: Vec<_> = report.footprints.iter().map(|f| f.unique).collect();
+        assert_eq!(unique, [4, 2, 2, 2]);
+        assert_eq!(report.footprints[0].bytes(), 16);
+        assert_eq!(report.top_strides, [(4, 2), (0x10_0000 - 8, 1)]);
+        assert_eq!(report.distinct_strides, 2);
+        assert_eq!(report.regions, 2);
+        assert_eq!(
+            report.top_regions[0],
+            RegionReport {
+                base_address: 0,
+                accesses: 3,
+                writes: 1,
+                unique_blocks: 1,
+            }
+        );
+        assert_eq!(report.working_sets, [1, 2]);
+        assert_eq!(report.working_set_summary(), (1.5, 2));
+    }
+
+    #[test]
+    fn configs_need_power_of_two_sizes_and_a_window() {
+        let rejected = |config: CharacterizeConfig| config.validate().unwrap_err();
+        assert_eq!(
+            rejected(CharacterizeConfig {
+                block_size: 48,
+                ..CharacterizeConfig::default()
+            }),
+            CharacterizeError::BlockSizeNotPowerOfTwo(48)
+        );
+        assert_eq!(
+            rejected(CharacterizeConfig {
+                footprint_granularities: vec![4, 6],
+                ..CharacterizeConfig::default()
+            }),
+            CharacterizeError::GranularityNotPowerOfTwo(6)
+        );
+        assert_eq!(
+            rejected(CharacterizeConfig {
+                window: 0,
+                ..CharacterizeConfig::default()
+            }),
+            CharacterizeError::ZeroWindow
+        );
+    }
+
+    #[test]
+    fn region_bits_must_leave_an_address_below_them() {
+        let config = CharacterizeConfig {
+            region_bits: 64,
+            ..CharacterizeConfig::default()
+        };
+        assert_eq!(
+            characterize(&[], &config).unwrap_err(),
+            CharacterizeError::RegionBitsTooLarge(64)
+        );
+        let widest = CharacterizeConfig {
+            region_bits: 63,
+            ..CharacterizeConfig::default()
+        };
+        assert!(widest.validate().is_ok());
+    }
+}
@@ -0,0 +1,591 @@
+use crate::{
+    cache::{AccessOutcome, Cache, CacheConfig, CacheStats, ConfigError},
+    measurement::{Measurement, MeasurementError},
+    trace::{AccessKind, TraceAccess},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// Snooping invalidation protocol run on the shared bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoherenceProtocol {
+    Msi,
+    /// MSI plus an Exclusive state, so a block read by a single core can be
+    /// written without a bus upgrade.
+    Mesi,
+}
+
+impl fmt::Display for CoherenceProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoherenceProtocol::Msi => write!(f, "MSI"),
+            CoherenceProtocol::Mesi => write!(f, "MESI"),
+        }
+    }
+}
+
+/// Coherence state of a block in one core's private cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineState {
+    Modified,
+    /// Dirty and possibly shared; this core answers requests for the block
+    /// instead of memory. Only used by the MOESI directory protocol.
+    Owned,
+    Exclusive,
+    Shared,
+    Invalid,
+}
+
+impl LineState {
+    pub const ALL: [LineState; 5] = [
+        LineState::Modified,
+        LineState::Owned,
+        LineState::Exclusive,
+        LineState::Shared,
+        LineState::Invalid,
+    ];
+
+    fn index(self) -> usize {
+        match self {
+            LineState::Modified => 0,
+            LineState::Owned => 1,
+            LineState::Exclusive => 2,
+            LineState::Shared => 3,
+            LineState::Invalid => 4,
+        }
+    }
+}
+
+impl fmt::Display for LineState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineState::Modified => write!(f, "M"),
+            LineState::Owned => write!(f, "O"),
+            LineState::Exclusive => write!(f, "E"),
+            LineState::Shared => write!(f, "S"),
+            LineState::Invalid => write!(f, "I"),
+        }
+    }
+}
+
+/// Protocol counters for one core.
+///
+/// Requests are named after the snooping bus; in directory mode they count
+/// the matching GetS, GetM and Upgrade messages sent to the home node.
+#[derive(Debug, Clone, Default)]
+pub struct CoherenceStats {
+    /// BusRd requests issued for read misses and prefetches.
+    pub bus_reads: u64,
+    /// BusRdX requests issued for write misses.
+    pub bus_read_exclusives: u64,
+    /// BusUpgr requests issued for writes to Shared (or Owned) lines.
+    pub bus_upgrades: u64,
+    /// Remote copies this core's requests invalidated.
+    pub invalidations_sent: u64,
+    /// Copies in this core's cache invalidated by other cores.
+    pub invalidations_received: u64,
+    /// Blocks this core supplied to another core's request in place of
+    /// memory or the shared cache.
+    pub interventions: u64,
+    /// Bytes of dirty data this core wrote back because of another core's
+    /// request or a directory eviction.
+    pub flush_bytes: u64,
+    /// Modified lines this core wrote back when it evicted them.
+    pub writebacks: u64,
+    /// Misses to blocks that were last lost to an invalidation.
+    pub coherence_misses: u64,
+    /// `transitions[from][to]`, indexed in [`LineState::ALL`] order.
+    pub transitions: [[u64; 5]; 5],
+}
+
+impl CoherenceStats {
+    pub fn transition(&self, from: LineState, to: LineState) -> u64 {
+        self.transitions[from.index()][to.index()]
+    }
+
+    pub fn bus_transactions(&self) -> u64 {
+        self.bus_reads + self.bus_read_exclusives + self.bus_upgrades
+    }
+}
+
+/// Per-core results of a multi-core run.
+#[derive(Debug, Clone)]
+pub struct CoreStats {
+    pub cache: CacheStats,
+    pub coherence: CoherenceStats,
+}
+
+/// Private caches, one per core, kept coherent over a snooping bus.
+///
+/// Each access is steered to core `thread % cores`. Bus requests are
+/// resolved before the requesting cache is updated, and the bus is atomic:
+/// one request completes before the next starts.
+pub struct SnoopingSystem {
+    protocol: CoherenceProtocol,
+    cores: PrivateCaches,
+}
+
+impl SnoopingSystem {
+    /// Builds `cores` private caches with the same `config`, which must not
+    /// have a victim buffer.
+    pub fn new(
+        config: CacheConfig,
+        cores: usize,
+        protocol: CoherenceProtocol,
+    ) -> Result<Self, ConfigError> {
+        Ok(Self {
+            protocol,
+            cores: PrivateCaches::new(config, cores)?,
+        })
+    }
+
+    pub fn protocol(&self) -> CoherenceProtocol {
+        self.protocol
+    }
+
+    pub fn cores(&self) -> usize {
+        self.cores.len()
+    }
+
+    pub fn cache(&self, core: usize) -> &Cache {
+        &self.cores.caches[core]
+    }
+
+    /// Core that services accesses from `thread`.
+    pub fn core_for(&self, thread: u32) -> usize {
+        self.cores.core_for(thread)
+    }
+
+    /// Current state of `block_address` in `core`'s cache.
+    pub fn state(&self, core: usize, block_address: u64) -> LineState {
+        self.cores.state(core, block_address)
+    }
+
+    pub fn core_stats(&self) -> Vec<CoreStats> {
+        self.cores.core_stats()
+    }
+
+    /// Clears every core's counters while keeping cache contents and states.
+    pub fn reset_stats(&mut self) {
+        self.cores.reset_stats();
+    }
+
+    /// Replays `trace` and returns per-core stats for this run only.
+    pub fn run_trace(&mut self, trace: &[TraceAccess]) -> Vec<CoreStats> {
+        self.replay(trace, &Measurement::default())
+    }
+
+    /// Replays `trace`, counting only the accesses `measurement` selects, and
+    /// returns per-core stats for this run only.
+    pub fn run_measured(
+        &mut self,
+        trace: &[TraceAccess],
+        measurement: &Measurement,
+    ) -> Result<Vec<CoreStats>, MeasurementError> {
+        measurement.validate()?;
+        Ok(self.replay(trace, measurement))
+    }
+
+    fn replay(&mut self, trace: &[TraceAccess], measurement: &Measurement) -> Vec<CoreStats> {
+        self.reset_stats();
+        for (position, access) in trace.iter().enumerate() {
+            if measurement.is_measured(position, trace.len()) {
+                self.access(access);
+            } else {
+                self.warm(access);
+            }
+        }
+        self.core_stats()
+    }
+
+    /// Services an access, bus traffic included, without counting it.
+    pub fn warm(&mut self, access: &TraceAccess) -> AccessOutcome {
+        let coherence = self.cores.pause_stats();
+        let outcome = self.access(access);
+        self.cores.resume_stats(coherence);
+        outcome
+    }
+
+    /// Services one access on the core its thread maps to.
+    pub fn access(&mut self, access: &TraceAccess) -> AccessOutcome {
+        let core = self.cores.core_for(access.thread);
+        let block_address = access.address / self.cores.block_size;
+        let state = self.cores.state(core, block_address);
+
+        let next = match (access.kind, state) {
+            (AccessKind::Read, LineState::Invalid) => self.bus_read(core, block_address),
+            (AccessKind::Read, held) => held,
+            (AccessKind::Write, LineState::Modified | LineState::Exclusive) => LineState::Modified,
+            (AccessKind::Write, LineState::Shared | LineState::Owned) => {
+                self.cores.coherence[core].bus_upgrades += 1;
+                self.invalidate_others(core, block_address);
+                LineState::Modified
+            }
+            (AccessKind::Write, LineState::Invalid) => {
+                self.cores.coherence[core].bus_read_exclusives += 1;
+                self.invalidate_others(core, block_address);
+                LineState::Modified
+            }
+        };
+
+        let (outcome, dropped) = self.cores.access(core, access);
+        self.cores.set_state(core, block_address, state, next);
+        for (_, held) in dropped {
+            self.private_eviction(core, held);
+        }
+
+        if let Some(prefetch) = &outcome.prefetch {
+            let next = self.bus_read(core, prefetch.block_address);
+            self.cores
+                .set_state(core, prefetch.block_address, LineState::Invalid, next);
+        }
+        outcome
+    }
+
+    /// Issues a BusRd for `core` and returns the state the block is filled in.
+    fn bus_read(&mut self, core: usize, block_address: u64) -> LineState {
+        self.cores.coherence[core].bus_reads += 1;
+        let mut shared = false;
+        for other in self.cores.others(core) {
+            let state = self.cores.state(other, block_address);
+            match state {
+                LineState::Invalid => continue,
+                LineState::Modified | LineState::Owned => {
+                    self.cores.flush(other, block_address);
+                    self.cores
+                        .set_state(other, block_address, state, LineState::Shared);
+                }
+                LineState::Exclusive => {
+                    self.cores
+                        .set_state(other, block_address, state, LineState::Shared);
+                }
+                LineState::Shared => {}
+            }
+            shared = true;
+        }
+        match self.protocol {
+            CoherenceProtocol::Mesi if !shared => LineState::Exclusive,
+            _ => LineState::Shared,
+        }
+    }
+
+    /// `core` evicted a line it held in `held`; a Modified line is written
+    /// back over the bus, while clean lines are dropped silently.
+    fn private_eviction(&mut self, core: usize, held: LineState) {
+        if held == LineState::Modified {
+            self.cores.coherence[core].writebacks += 1;
+        }
+    }
+
+    /// Invalidates every other core's copy of `block_address`.
+    fn invalidate_others(&mut self, core: usize, block_address: u64) {
+        for other in self.cores.others(core) {
+            let state = self.cores.state(other, block_address);
+            if state == LineState::Invalid {
+                continue;
+            }
+            if matches!(state, LineState::Modified | LineState::Owned) {
+                self.cores.flush(other, block_address);
+            }
+            self.cores.invalidate(other, block_address);
+            self.cores.coherence[core].invalidations_sent += 1;
+        }
+    }
+}
+
+/// The per-core caches and coherence bookkeeping shared by the snooping and
+/// directory systems.
+pub(crate) struct PrivateCaches {
+    pub(crate) block_size: u64,
+    pub(crate) caches: Vec<Cache>,
+    /// Coherence state of each block resident in the matching cache. Blocks
+    /// the cache has since dropped are treated as Invalid.
+    states: Vec<HashMap<u64, LineState>>,
+    /// Blocks each core lost to an invalidation and has not refetched.
+    invalidated: Vec<HashSet<u64>>,
+    pub(crate) coherence: Vec<CoherenceStats>,
+}
+
+impl PrivateCaches {
+    pub(crate) fn new(config: CacheConfig, cores: usize) -> Result<Self, ConfigError> {
+        if cores == 0 {
+            return Err(ConfigError::ZeroCores);
+        }
+        if config.victim_cache_entries > 0 {
+            return Err(ConfigError::VictimBufferInMultiCore);
+        }
+        if config.page_mapping.is_some() {
+            return Err(ConfigError::PageMappingInMultiCore);
+        }
+        let block_size = config.block_size as u64;
+        let caches = (0..cores)
+            .map(|_| Cache::new(config.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            block_size,
+            caches,
+            states: vec![HashMap::new(); cores],
+            invalidated: vec![HashSet::new(); cores],
+            coherence: vec![CoherenceStats::default(); cores],
+        })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.caches.len()
+    }
+
+    pub(crate) fn core_for(&self, thread: u32) -> usize {
+        thread as usize % self.caches.len()
+    }
+
+    /// Every core except `core`.
+    pub(crate) fn others(&self, core: usize) -> impl Iterator<Item = usize> + use<> {
+        (0..self.caches.len()).filter(move |&other| other != core)
+    }
+
+    pub(crate) fn state(&self, core: usize, block_address: u64) -> LineState {
+        if !self.caches[core].contains(block_address) {
+            return LineState::Invalid;
+        }
+        self.states[core]
+            .get(&block_address)
+            .copied()
+            .unwrap_or(LineState::Invalid)
+    }
+
+    pub(crate) fn set_state(
+        &mut self,
+        core: usize,
+        block_address: u64,
+        from: LineState,
+        to: LineState,
+    ) {
+        if from != to {
+            self.coherence[core].transitions[from.index()][to.index()] += 1;
+        }
+        if to == LineState::Invalid {
+            self.states[core].remove(&block_address);
+        } else {
+            self.states[core].insert(block_address, to);
+        }
+    }
+
+    /// Runs `access` on `core`'s cache. Returns the outcome and the blocks
+    /// the access pushed out of the cache, with the state each was held in;
+    /// their move to Invalid is already recorded. The caller sets the state
+    /// of the accessed and prefetched blocks and handles any write-backs.
+    pub(crate) fn access(
+        &mut self,
+        core: usize,
+        access: &TraceAccess,
+    ) -> (AccessOutcome, Vec<(u64, LineState)>) {
+        let block_address = access.address / self.block_size;
+        let outcome = self.caches[core].access(access);
+        if !outcome.hit && self.invalidated[core].remove(&block_address) {
+            self.coherence[core].coherence_misses += 1;
+        }
+        let prefetch_evicted = outcome.prefetch.as_ref().and_then(|prefetch| {
+            self.invalidated[core].remove(&prefetch.block_address);
+            prefetch.evicted
+        });
+        let dropped = [outcome.evicted, prefetch_evicted]
+            .into_iter()
+            .flatten()
+            .filter_map(|line| {
+                let state = self.states[core].remove(&line.block_address)?;
+                self.coherence[core].transitions[state.index()][LineState::Invalid.index()] += 1;
+                Some((line.block_address, state))
+            })
+            .collect();
+        (outcome, dropped)
+    }
+
+    /// Drops `core`'s copy of `block_address` at another core's request.
+    /// Returns the state it was held in. Dirty data goes to the requester or
+    /// was already flushed, so the cache does not write it back again.
+    pub(crate) fn invalidate(&mut self, core: usize, block_address: u64) -> LineState {
+        let state = self.state(core, block_address);
+        self.caches[core].clean(block_address);
+        self.caches[core].invalidate(block_address);
+        self.set_state(core, block_address, state, LineState::Invalid);
+        self.invalidated[core].insert(block_address);
+        self.coherence[core].invalidations_received += 1;
+        state
+    }
+
+    /// `owner` supplies its dirty copy and writes it back.
+    pub(crate) fn flush(&mut self, owner: usize, block_address: u64) {
+        self.caches[owner].clean(block_address);
+        self.coherence[owner].interventions += 1;
+        self.coherence[owner].flush_bytes += self.block_size;
+    }
+
+    pub(crate) fn core_stats(&self) -> Vec<CoreStats> {
+        self.caches
+            .iter()
+            .zip(&self.coherence)
+            .map(|(cache, coherence)| CoreStats {
+                cache: cache.stats().clone(),
+                coherence: coherence.clone(),
+            })
+            .collect()
+    }
+
+    pub(crate) fn reset_stats(&mut self) {
+        for cache in &mut self.caches {
+            cache.reset_stats();
+        }
+        self.coherence.fill(CoherenceStats::default());
+    }
+
+    /// Stops the caches counting and returns the coherence counters to hand
+    /// back to [`PrivateCaches::resume_stats`].
+    pub(crate) fn pause_stats(&mut self) -> Vec<CoherenceStats> {
+        for cache in &mut self.caches {
+            cache.set_measuring(false);
+        }
+        self.coherence.clone()
+    }
+
+    /// Undoes [`PrivateCaches::pause_stats`], dropping whatever was counted since.
+    pub(crate) fn resume_stats(&mut self, coherence: Vec<CoherenceStats>) {
+        for cache in &mut self.caches {
+            cache.set_measuring(true);
+        }
+        self.coherence = coherence;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::PageMapping;
+
+    fn access(kind: AccessKind, thread: u32, block_address: u64) -> TraceAccess {
+        TraceAccess {
+            kind,
+            address: block_address * 32,
+            thread,
+            size: 4,
+            timestamp: 0,
+        }
+    }
+
+    fn direct_mapped() -> CacheConfig {
+        CacheConfig::builder()
+            .cache_size(4 * 32)
+            .block_size(32)
+            .associativity(1)
+            .build()
+            .unwrap()
+    }
+
+    fn system(protocol: CoherenceProtocol) -> SnoopingSystem {
+        SnoopingSystem::new(direct_mapped(), 2, protocol).unwrap()
+    }
+
+    #[test]
+    fn mesi_writes_to_exclusive_lines_need_no_upgrade() {
+        let trace = [
+            access(AccessKind::Read, 0, 1),
+            access(AccessKind::Write, 0, 1),
+        ];
+        let mut mesi = system(CoherenceProtocol::Mesi);
+        let coherence = mesi.run_trace(&trace)[0].coherence.clone();
+        assert_eq!((coherence.bus_reads, coherence.bus_upgrades), (1, 0));
+        assert_eq!(
+            coherence.transition(LineState::Exclusive, LineState::Modified),
+            1
+        );
+        // MSI fills the line Shared, so the write has to upgrade it.
+        let mut msi = system(CoherenceProtocol::Msi);
+        let coherence = msi.run_trace(&trace)[0].coherence.clone();
+        assert_eq!((coherence.bus_reads, coherence.bus_upgrades), (1, 1));
+        assert_eq!(
+            coherence.transition(LineState::Shared, LineState::Modified),
+            1
+        );
+    }
+
+    #[test]
+    fn remote_reads_and_writes_flush_and_invalidate() {
+        let mut system = system(CoherenceProtocol::Mesi);
+        let cores = system.run_trace(&[
+            access(AccessKind::Write, 0, 1),
+            access(AccessKind::Read, 1, 1),
+            access(AccessKind::Write, 1, 1),
+            access(AccessKind::Read, 0, 1),
+        ]);
+        // Core 0 supplies its dirty copy, loses it to core 1's upgrade, and
+        // misses on it again.
+        assert_eq!(cores[0].coherence.interventions, 1);
+        assert_eq!(cores[0].coherence.invalidations_received, 1);
+        assert_eq!(cores[0].coherence.coherence_misses, 1);
+        assert_eq!(cores[1].coherence.bus_upgrades, 1);
+        assert_eq!(cores[1].coherence.interventions, 1);
+        assert_eq!(system.state(0, 1), LineState::Shared);
+        assert_eq!(system.state(1, 1), LineState::Shared);
+    }
+
+    #[test]
+    fn warmup_accesses_leave_no_bus_traffic_in_the_stats() {
+        let trace = [
+            access(AccessKind::Write, 0, 1),
+            access(AccessKind::Read, 1, 1),
+            access(AccessKind::Read, 1, 1),
+        ];
+        let mut system = system(CoherenceProtocol::Mesi);
+        let cores = system
+            .run_measured(&trace, &Measurement::warmup(2))
+            .unwrap();
+        assert_eq!(cores[0].coherence.interventions, 0);
+        assert_eq!(cores[1].coherence.bus_reads, 0);
+        assert_eq!((cores[1].cache.accesses, cores[1].cache.hits), (1, 1));
+        // The warm-up still moved the block into both caches.
+        assert_eq!(system.state(0, 1), LineState::Shared);
+    }
+
+    #[test]
+    fn only_modified_evictions_write_back() {
+        let mut system = system(CoherenceProtocol::Msi);
+        // Blocks 0, 4 and 8 all map to set 0 of core 0's direct-mapped cache.
+        system.access(&access(AccessKind::Write, 0, 0));
+        system.access(&access(AccessKind::Read, 0, 4));
+        system.access(&access(AccessKind::Read, 0, 8));
+        let coherence = &system.core_stats()[0].coherence;
+        assert_eq!(coherence.writebacks, 1);
+        assert_eq!(
+            coherence.transition(LineState::Modified, LineState::Invalid),
+            1
+        );
+        assert_eq!(
+            coherence.transition(LineState::Shared, LineState::Invalid),
+            1
+        );
+        assert_eq!(system.state(0, 8), LineState::Shared);
+    }
+
+    #[test]
+    fn systems_need_a_core_and_plain_private_caches() {
+        let rejected = |config, cores| {
+            SnoopingSystem::new(config, cores, CoherenceProtocol::Msi)
+                .err()
+                .unwrap()
+        };
+        assert_eq!(rejected(direct_mapped(), 0), ConfigError::ZeroCores);
+        let mut with_victims = direct_mapped();
+        with_victims.victim_cache_entries = 4;
+        assert_eq!(
+            rejected(with_victims, 2),
+            ConfigError::VictimBufferInMultiCore
+        );
+        let mut page_mapped = direct_mapped();
+        page_mapped.page_mapping = Some(PageMapping::default());
+        assert_eq!(
+            rejected(page_mapped, 2),
+            ConfigError::PageMappingInMultiCore
+        );
+    }
+}
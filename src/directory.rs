@@ -0,0 +1,635 @@
+use crate::{
+    cache::{AccessOutcome, Cache, CacheConfig, CacheStats, ConfigError},
+    coherence::{CoreStats, LineState, PrivateCaches},
+    measurement::{Measurement, MeasurementError},
+    trace::{AccessKind, TraceAccess},
+};
+use std::{collections::HashMap, fmt};
+
+/// How a directory entry records which cores hold a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SharerTracking {
+    /// One presence bit per core.
+    FullMap,
+    /// Up to this many core ids. An entry that overflows forgets its sharers
+    /// and broadcasts its next invalidation to every core.
+    LimitedPointer(usize),
+}
+
+impl fmt::Display for SharerTracking {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SharerTracking::FullMap => write!(f, "full-map"),
+            SharerTracking::LimitedPointer(pointers) => write!(f, "{pointers}-pointer"),
+        }
+    }
+}
+
+/// Directory and shared-cache counters for a whole run.
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryStats {
+    /// Requests the home node served from the shared cache or memory.
+    pub two_hop: u64,
+    /// Requests forwarded to the owning core, which replied to the requester.
+    pub three_hop: u64,
+    /// Requests that sent at least one invalidation.
+    pub invalidating_requests: u64,
+    /// Invalidation messages sent, including broadcasts and stale sharers.
+    pub invalidations: u64,
+    /// `fan_out[n]`: requests that sent `n` invalidations.
+    pub fan_out: Vec<u64>,
+    /// Invalidating requests that had to broadcast after a pointer overflow.
+    pub broadcasts: u64,
+    /// Sharers added to an entry whose pointers were already full.
+    pub pointer_overflows: u64,
+    /// Invalidations sent to cores that had silently dropped the block.
+    pub stale_invalidations: u64,
+    /// Modified or Owned private lines written back to the home node on eviction.
+    pub writebacks: u64,
+    /// Tracked blocks evicted from the shared cache.
+    pub directory_evictions: u64,
+    /// Private copies invalidated to keep the shared cache inclusive.
+    pub back_invalidations: u64,
+}
+
+impl DirectoryStats {
+    /// Mean invalidations per invalidating request.
+    pub fn avg_fan_out(&self) -> f64 {
+        if self.invalidating_requests == 0 {
+            0.0
+        } else {
+            self.invalidations as f64 / self.invalidating_requests as f64
+        }
+    }
+
+    /// Share of coherence requests that needed the owner's cache.
+    pub fn three_hop_share(&self) -> f64 {
+        let requests = self.two_hop + self.three_hop;
+        if requests == 0 {
+            0.0
+        } else {
+            self.three_hop as f64 / requests as f64
+        }
+    }
+}
+
+/// Everything a directory-mode run produces.
+#[derive(Debug, Clone)]
+pub struct SystemStats {
+    pub cores: Vec<CoreStats>,
+    pub llc: CacheStats,
+    pub directory: DirectoryStats,
+}
+
+#[derive(Debug, Clone, Default)]
+struct DirectoryEntry {
+    /// Core holding the block in M, O or E.
+    owner: Option<usize>,
+    /// Cores that may hold a copy, owner included. Shared lines are evicted
+    /// silently, so this can name cores that no longer do.
+    sharers: Vec<usize>,
+    /// Set when a limited-pointer entry ran out of pointers.
+    overflowed: bool,
+}
+
+impl DirectoryEntry {
+    /// Records `core` as a sharer. Returns whether the pointers overflowed.
+    fn add_sharer(&mut self, core: usize, tracking: SharerTracking) -> bool {
+        if self.overflowed || self.sharers.contains(&core) {
+            return false;
+        }
+        match tracking {
+            SharerTracking::LimitedPointer(pointers) if self.sharers.len() >= pointers => {
+                self.sharers.clear();
+                self.overflowed = true;
+                true
+            }
+            _ => {
+                self.sharers.push(core);
+                false
+            }
+        }
+    }
+
+    fn remove_sharer(&mut self, core: usize) {
+        self.sharers.retain(|&sharer| sharer != core);
+        if self.owner == Some(core) {
+            self.owner = None;
+        }
+    }
+
+    /// Whether any core other than `core` may hold the block.
+    fn shared_beyond(&self, core: usize) -> bool {
+        self.overflowed || self.sharers.iter().any(|&sharer| sharer != core)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.owner.is_none() && self.sharers.is_empty() && !self.overflowed
+    }
+}
+
+/// Private caches kept coherent by a MOESI directory at a shared,
+/// inclusive last-level cache.
+///
+/// Private misses go to the home node. It answers from the shared cache
+/// (two hops) or forwards the request to the core that owns the block
+/// (three hops), and sends invalidations to the recorded sharers. Owned
+/// blocks stay dirty in the owner's cache when read by others, so reads do
+/// not force a write-back. Modified, Owned and Exclusive lines notify the
+/// directory when evicted; Shared lines leave silently.
+///
+/// Each access is steered to core `thread % cores`.
+pub struct DirectorySystem {
+    tracking: SharerTracking,
+    cores: PrivateCaches,
+    llc: Cache,
+    entries: HashMap<u64, DirectoryEntry>,
+    stats: DirectoryStats,
+}
+
+impl DirectorySystem {
+    /// Builds `cores` private caches with `private` behind one shared `llc`.
+    pub fn new(
+        private: CacheConfig,
+        llc: CacheConfig,
+        cores: usize,
+        tracking: SharerTracking,
+    ) -> Result<Self, ConfigError> {
+        Self::validate(&private, &llc, cores, tracking)?;
+        let cores = PrivateCaches::new(private, cores)?;
+        let stats = DirectoryStats {
+            fan_out: vec![0; cores.len() + 1],
+            ..DirectoryStats::default()
+        };
+        Ok(Self {
+            tracking,
+            cores,
+            llc: Cache::new(llc)?,
+            entries: HashMap::new(),
+            stats,
+        })
+    }
+
+    /// Checks both cache configurations, that they can be combined and that
+    /// `tracking` can record a sharer.
+    pub fn validate(
+        private: &CacheConfig,
+        llc: &CacheConfig,
+        cores: usize,
+        tracking: SharerTracking,
+    ) -> Result<(), ConfigError> {
+        private.validate()?;
+        llc.validate()?;
+        if cores == 0 {
+            return Err(ConfigError::ZeroCores);
+        }
+        if tracking == SharerTracking::LimitedPointer(0) {
+            return Err(ConfigError::ZeroSharerPointers);
+        }
+        if private.block_size != llc.block_size {
+            return Err(ConfigError::DirectoryBlockSizeMismatch {
+                private: private.block_size,
+                shared: llc.block_size,
+            });
+        }
+        // Castouts from a victim buffer are not reported, so the directory
+        // could track neither private sharers nor an inclusive shared cache.
+        if private.victim_cache_entries > 0 {
+            return Err(ConfigError::VictimBufferInMultiCore);
+        }
+        if llc.victim_cache_entries > 0 {
+            return Err(ConfigError::DirectoryWithVictimBuffer);
+        }
+        if private.page_mapping.is_some() || llc.page_mapping.is_some() {
+            return Err(ConfigError::PageMappingInMultiCore);
+        }
+        Ok(())
+    }
+
+    pub fn tracking(&self) -> SharerTracking {
+        self.tracking
+    }
+
+    pub fn cores(&self) -> usize {
+        self.cores.len()
+    }
+
+    pub fn cache(&self, core: usize) -> &Cache {
+        &self.cores.caches[core]
+    }
+
+    pub fn llc(&self) -> &Cache {
+        &self.llc
+    }
+
+    /// Core that services accesses from `thread`.
+    pub fn core_for(&self, thread: u32) -> usize {
+        self.cores.core_for(thread)
+    }
+
+    /// Current state of `block_address` in `core`'s cache.
+    pub fn state(&self, core: usize, block_address: u64) -> LineState {
+        self.cores.state(core, block_address)
+    }
+
+    pub fn stats(&self) -> SystemStats {
+        SystemStats {
+            cores: self.cores.core_stats(),
+            llc: self.llc.stats().clone(),
+            directory: self.stats.clone(),
+        }
+    }
+
+    /// Clears all counters while keeping cache contents and directory state.
+    pub fn reset_stats(&mut self) {
+        self.cores.reset_stats();
+        self.llc.reset_stats();
+        self.stats = DirectoryStats {
+            fan_out: vec![0; self.cores.len() + 1],
+            ..DirectoryStats::default()
+        };
+    }
+
+    /// Replays `trace` and returns the stats for this run only.
+    pub fn run_trace(&mut self, trace: &[TraceAccess]) -> SystemStats {
+        self.replay(trace, &Measurement::default())
+    }
+
+    /// Replays `trace`, counting only the accesses `measurement` selects, and
+    /// returns the stats for this run only.
+    pub fn run_measured(
+        &mut self,
+        trace: &[TraceAccess],
+        measurement: &Measurement,
+    ) -> Result<SystemStats, MeasurementError> {
+        measurement.validate()?;
+        Ok(self.replay(trace, measurement))
+    }
+
+    fn replay(&mut self, trace: &[TraceAccess], measurement: &Measurement) -> SystemStats {
+        self.reset_stats();
+        for (position, access) in trace.iter().enumerate() {
+            if measurement.is_measured(position, trace.len()) {
+                self.access(access);
+            } else {
+                self.warm(access);
+            }
+        }
+        self.stats()
+    }
+
+    /// Services an access, directory and shared-cache work included, without
+    /// counting it.
+    pub fn warm(&mut self, access: &TraceAccess) -> AccessOutcome {
+        let coherence = self.cores.pause_stats();
+        let directory = self.stats.clone();
+        self.llc.set_measuring(false);
+        let outcome = self.access(access);
+        self.llc.set_measuring(true);
+        self.stats = directory;
+        self.cores.resume_stats(coherence);
+        outcome
+    }
+
+    /// Services one access on the core its thread maps to.
+    pub fn access(&mut self, access: &TraceAccess) -> AccessOutcome {
+        let core = self.cores.core_for(access.thread);
+        let block_address = access.address / self.cores.block_size;
+        let state = self.cores.state(core, block_address);
+
+        let next = match (access.kind, state) {
+            (AccessKind::Read, LineState::Invalid) => self.get_shared(core, block_address),
+            (AccessKind::Read, held) => held,
+            (AccessKind::Write, LineState::Modified | LineState::Exclusive) => LineState::Modified,
+            (AccessKind::Write, LineState::Shared | LineState::Owned) => {
+                self.cores.coherence[core].bus_upgrades += 1;
+                self.invalidate_sharers(core, block_address);
+                self.claim(core, block_address);
+                LineState::Modified
+            }
+            (AccessKind::Write, LineState::Invalid) => self.get_modified(core, block_address),
+        };
+
+        let (outcome, dropped) = self.cores.access(core, access);
+        self.cores.set_state(core, block_address, state, next);
+        for (evicted, held) in dropped {
+            self.private_eviction(core, evicted, held);
+        }
+        if let Some(prefetch) = &outcome.prefetch {
+            let next = self.get_shared(core, prefetch.block_address);
+            self.cores
+                .set_state(core, prefetch.block_address, LineState::Invalid, next);
+        }
+        outcome
+    }
+
+    /// GetS: returns the state `core` fills the block in.
+    fn get_shared(&mut self, core: usize, block_address: u64) -> LineState {
+        self.cores.coherence[core].bus_reads += 1;
+        match self.owner(block_address) {
+            Some((owner, held)) if owner != core => {
+                self.stats.three_hop += 1;
+                self.cores.coherence[owner].interventions += 1;
+                let demoted = match held {
+                    LineState::Modified | LineState::Owned => LineState::Owned,
+                    _ => LineState::Shared,
+                };
+                self.cores.set_state(owner, block_address, held, demoted);
+                if demoted == LineState::Shared {
+                    self.entry(block_address).owner = None;
+                }
+            }
+            _ => self.fetch_from_home(block_address),
+        }
+        let tracking = self.tracking;
+        let entry = self.entry(block_address);
+        let shared = entry.shared_beyond(core);
+        if entry.add_sharer(core, tracking) {
+            self.stats.pointer_overflows += 1;
+        }
+        if shared {
+            LineState::Shared
+        } else {
+            self.entry(block_address).owner = Some(core);
+            LineState::Exclusive
+        }
+    }
+
+    /// GetM: `core` ends up the only holder, in Modified.
+    fn get_modified(&mut self, core: usize, block_address: u64) -> LineState {
+        self.cores.coherence[core].bus_read_exclusives += 1;
+        match self.owner(block_address) {
+            Some((owner, _)) if owner != core => {
+                self.stats.three_hop += 1;
+                self.cores.coherence[owner].interventions += 1;
+            }
+            _ => self.fetch_from_home(block_address),
+        }
+        self.invalidate_sharers(core, block_address);
+        self.claim(core, block_address);
+        LineState::Modified
+    }
+
+    /// The recorded owner and the state it holds the block in. Owners
+    /// always tell the directory when they drop a line, so the owner still
+    /// holds it.
+    fn owner(&self, block_address: u64) -> Option<(usize, LineState)> {
+        let owner = self.entries.get(&block_address)?.owner?;
+        let held = self.cores.state(owner, block_address);
+        debug_assert_ne!(held, LineState::Invalid, "stale owner {owner}");
+        Some((owner, held))
+    }
+
+    /// Sends invalidations for `block_address` to every sharer but `core`.
+    fn invalidate_sharers(&mut self, core: usize, block_address: u64) {
+        let Some(entry) = self.entries.get(&block_address) else {
+            return;
+        };
+        let targets: Vec<usize> = if entry.overflowed {
+            self.cores.others(core).collect()
+        } else {
+            entry
+                .sharers
+                .iter()
+                .copied()
+                .filter(|&sharer| sharer != core)
+                .collect()
+        };
+        if targets.is_empty() {
+            return;
+        }
+        if entry.overflowed {
+            self.stats.broadcasts += 1;
+        }
+        for &target in &targets {
+            if self.cores.state(target, block_address) == LineState::Invalid {
+                self.stats.stale_invalidations += 1;
+            } else {
+                self.cores.invalidate(target, block_address);
+            }
+        }
+        let sent = targets.len();
+        self.cores.coherence[core].invalidations_sent += sent as u64;
+        self.stats.invalidations += sent as u64;
+        self.stats.invalidating_requests += 1;
+        self.stats.fan_out[sent] += 1;
+    }
+
+    /// Makes `core` the sole recorded holder and owner.
+    fn claim(&mut self, core: usize, block_address: u64) {
+        let entry = self.entry(block_address);
+        entry.owner = Some(core);
+        entry.sharers = vec![core];
+        entry.overflowed = false;
+    }
+
+    /// `core` evicted `block_address`, which it held in `held`.
+    fn private_eviction(&mut self, core: usize, block_address: u64, held: LineState) {
+        if held == LineState::Shared {
+            return;
+        }
+        if let Some(entry) = self.entries.get_mut(&block_address) {
+            entry.remove_sharer(core);
+            if entry.is_empty() {
+                self.entries.remove(&block_address);
+            }
+        }
+        // Exclusive lines are clean: the directory is told, but no data moves.
+        if matches!(held, LineState::Modified | LineState::Owned) {
+            self.stats.writebacks += 1;
+            self.cores.coherence[core].writebacks += 1;
+            self.llc_access(AccessKind::Write, block_address);
+        }
+    }
+
+    fn fetch_from_home(&mut self, block_address: u64) {
+        self.stats.two_hop += 1;
+        self.llc_access(AccessKind::Read, block_address);
+    }
+
+    fn llc_access(&mut self, kind: AccessKind, block_address: u64) {
+        let outcome = self.llc.access(&TraceAccess {
+            kind,
+            address: block_address * self.cores.block_size,
+            thread: 0,
+            size: self.cores.block_size,
+            timestamp: 0,
+        });
+        let prefetch_evicted = outcome.prefetch.and_then(|prefetch| prefetch.evicted);
+        for line in [outcome.evicted, prefetch_evicted].into_iter().flatten() {
+            self.directory_eviction(line.block_address);
+        }
+    }
+
+    /// The shared cache dropped `block_address`; recall every private copy.
+    fn directory_eviction(&mut self, block_address: u64) {
+        let Some(entry) = self.entries.remove(&block_address) else {
+            return;
+        };
+        self.stats.directory_evictions += 1;
+        let targets: Vec<usize> = if entry.overflowed {
+            (0..self.cores.len()).collect()
+        } else {
+            entry.sharers
+        };
+        for target in targets {
+            let held = self.cores.state(target, block_address);
+            if held == LineState::Invalid {
+                continue;
+            }
+            if matches!(held, LineState::Modified | LineState::Owned) {
+                self.cores.coherence[target].flush_bytes += self.cores.block_size;
+            }
+            self.cores.invalidate(target, block_address);
+            self.stats.back_invalidations += 1;
+        }
+    }
+
+    fn entry(&mut self, block_address: u64) -> &mut DirectoryEntry {
+        self.entries.entry(block_address).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::PageMapping;
+
+    fn access(kind: AccessKind, thread: u32, block_address: u64) -> TraceAccess {
+        TraceAccess {
+            kind,
+            address: block_address * 32,
+            thread,
+            size: 4,
+            timestamp: 0,
+        }
+    }
+
+    fn configs() -> (CacheConfig, CacheConfig) {
+        let private = CacheConfig::builder()
+            .cache_size(4 * 32)
+            .block_size(32)
+            .associativity(1)
+            .build()
+            .unwrap();
+        let llc = CacheConfig::builder()
+            .cache_size(64 * 32)
+            .block_size(32)
+            .associativity(4)
+            .build()
+            .unwrap();
+        (private, llc)
+    }
+
+    #[test]
+    fn reads_of_modified_lines_are_forwarded_and_leave_an_owner() {
+        let (private, llc) = configs();
+        let mut system = DirectorySystem::new(private, llc, 3, SharerTracking::FullMap).unwrap();
+        let stats = system.run_trace(&[
+            access(AccessKind::Write, 0, 1),
+            access(AccessKind::Read, 1, 1),
+            access(AccessKind::Read, 2, 1),
+        ]);
+        assert_eq!(system.state(0, 1), LineState::Owned);
+        assert_eq!(system.state(1, 1), LineState::Shared);
+        assert_eq!(system.state(2, 1), LineState::Shared);
+        // The write fetches from home; both reads go through the owner,
+        // which keeps its dirty copy instead of writing it back.
+        assert_eq!((stats.directory.two_hop, stats.directory.three_hop), (1, 2));
+        assert_eq!(stats.cores[0].coherence.interventions, 2);
+        assert_eq!(stats.cores[0].coherence.flush_bytes, 0);
+        assert_eq!(
+            stats.cores[0]
+                .coherence
+                .transition(LineState::Modified, LineState::Owned),
+            1
+        );
+    }
+
+    #[test]
+    fn overflowed_pointers_broadcast_invalidations() {
+        let trace = [
+            access(AccessKind::Read, 0, 1),
+            access(AccessKind::Read, 1, 1),
+            access(AccessKind::Read, 2, 1),
+            access(AccessKind::Write, 3, 1),
+        ];
+        let run = |tracking| {
+            let (private, llc) = configs();
+            let mut system = DirectorySystem::new(private, llc, 4, tracking).unwrap();
+            system.run_trace(&trace).directory
+        };
+        let limited = run(SharerTracking::LimitedPointer(1));
+        assert_eq!(limited.pointer_overflows, 1);
+        assert_eq!(limited.broadcasts, 1);
+        assert_eq!(limited.invalidations, 3);
+        assert_eq!(limited.fan_out[3], 1);
+        let full = run(SharerTracking::FullMap);
+        assert_eq!((full.broadcasts, full.invalidations), (0, 3));
+    }
+
+    #[test]
+    fn clean_exclusive_evictions_do_not_write_back() {
+        let (private, llc) = configs();
+        let mut system = DirectorySystem::new(private, llc, 2, SharerTracking::FullMap).unwrap();
+        // Blocks 0, 4 and 8 all map to set 0 of core 0's direct-mapped cache.
+        system.access(&access(AccessKind::Read, 0, 0));
+        assert_eq!(system.state(0, 0), LineState::Exclusive);
+        system.access(&access(AccessKind::Read, 0, 4));
+        assert_eq!(system.stats().directory.writebacks, 0);
+        system.access(&access(AccessKind::Write, 0, 4));
+        system.access(&access(AccessKind::Read, 0, 8));
+        let stats = system.stats();
+        assert_eq!(stats.directory.writebacks, 1);
+        assert_eq!(stats.cores[0].coherence.writebacks, 1);
+    }
+
+    #[test]
+    fn limited_pointer_directories_need_a_pointer() {
+        let (private, llc) = configs();
+        assert_eq!(
+            DirectorySystem::validate(&private, &llc, 2, SharerTracking::LimitedPointer(0)),
+            Err(ConfigError::ZeroSharerPointers)
+        );
+        assert!(
+            DirectorySystem::validate(&private, &llc, 2, SharerTracking::LimitedPointer(1)).is_ok()
+        );
+    }
+
+    #[test]
+    fn private_and_shared_caches_must_be_compatible() {
+        let rejected = |private, llc| {
+            DirectorySystem::new(private, llc, 2, SharerTracking::FullMap)
+                .err()
+                .unwrap()
+        };
+        let (private, llc) = configs();
+        let mut wide_blocks = llc.clone();
+        wide_blocks.block_size = 64;
+        assert_eq!(
+            rejected(private.clone(), wide_blocks),
+            ConfigError::DirectoryBlockSizeMismatch {
+                private: 32,
+                shared: 64,
+            }
+        );
+        let mut shared_victims = llc.clone();
+        shared_victims.victim_cache_entries = 4;
+        assert_eq!(
+            rejected(private.clone(), shared_victims),
+            ConfigError::DirectoryWithVictimBuffer
+        );
+        let mut private_victims = private.clone();
+        private_victims.victim_cache_entries = 4;
+        assert_eq!(
+            rejected(private_victims, llc.clone()),
+            ConfigError::VictimBufferInMultiCore
+        );
+        let mut page_mapped = llc;
+        page_mapped.page_mapping = Some(PageMapping::default());
+        assert_eq!(
+            rejected(private, page_mapped),
+            ConfigError::PageMappingInMultiCore
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::cache::{CacheConfig, CacheStats, MultiColumnPredictor, PredictionStrategy};
+
+/// Per-event energies for turning [`CacheStats`] into an energy estimate.
+///
+/// All energies are in picojoules. The defaults are in the range CACTI reports
+/// for a 32 KiB SRAM at 45 nm; use them for comparing organisations rather
+/// than as absolute figures.
+#[derive(Debug, Clone)]
+pub struct EnergyModel {
+    /// Reading one way's tag.
+    pub tag_read_pj: f64,
+    /// Reading one way's data block.
+    pub data_read_pj: f64,
+    /// One fully associative search of the victim buffer.
+    pub victim_probe_pj: f64,
+    /// One read of the MRU bits or multi-column bit vector.
+    pub predictor_access_pj: f64,
+    /// Static energy per byte of storage, per cycle.
+    pub leakage_pj_per_byte_cycle: f64,
+    /// Width of the addresses stored in tags.
+    pub address_bits: u32,
+}
+
+impl Default for EnergyModel {
+    fn default() -> Self {
+        Self {
+            tag_read_pj: 1.2,
+            data_read_pj: 8.0,
+            victim_probe_pj: 3.5,
+            predictor_access_pj: 0.4,
+            leakage_pj_per_byte_cycle: 0.0002,
+            address_bits: 48,
+        }
+    }
+}
+
+/// Storage needed by a configuration, in bytes.
+#[derive(Debug, Clone, Default)]
+pub struct AreaEstimate {
+    pub data_bytes: u64,
+    /// Tags plus valid, dirty and rehash bits.
+    pub tag_bytes: u64,
+    /// Victim-buffer data and full block-address tags.
+    pub victim_bytes: u64,
+    pub predictor_bytes: u64,
+}
+
+impl AreaEstimate {
+    pub fn total_bytes(&self) -> u64 {
+        self.data_bytes + self.tag_bytes + self.victim_bytes + self.predictor_bytes
+    }
+}
+
+/// Energy spent over a run, split by source. One access is taken as one cycle
+/// for leakage.
+#[derive(Debug, Clone, Default)]
+pub struct EnergyEstimate {
+    pub accesses: u64,
+    pub tag_pj: f64,
+    pub data_pj: f64,
+    pub victim_pj: f64,
+    pub predictor_pj: f64,
+    pub leakage_pj: f64,
+}
+
+impl EnergyEstimate {
+    pub fn dynamic_pj(&self) -> f64 {
+        self.tag_pj + self.data_pj + self.victim_pj + self.predictor_pj
+    }
+
+    pub fn total_pj(&self) -> f64 {
+        self.dynamic_pj() + self.leakage_pj
+    }
+
+    pub fn dynamic_per_access(&self) -> f64 {
+        if self.accesses == 0 {
+            0.0
+        } else {
+            self.dynamic_pj() / self.accesses as f64
+        }
+    }
+
+    pub fn total_per_access(&self) -> f64 {
+        if self.accesses == 0 {
+            0.0
+        } else {
+            self.total_pj() / self.accesses as f64
+        }
+    }
+}
+
+impl EnergyModel {
+    pub fn area(&self, config: &CacheConfig) -> AreaEstimate {
+        let num_sets = config.num_sets() as u64;
+        let ways = config.associativity as u64;
+        let lines = num_sets * ways;
+        let offset_bits = config.block_size.trailing_zeros();
+        // Hashed and prime-modulo indices cannot drop index bits from the tag.
+        let index_bits = if num_sets.is_power_of_two() {
+            num_sets.trailing_zeros()
+        } else {
+            0
+        };
+        let block_bits = self.address_bits.saturating_sub(offset_bits) as u64;
+        let status_bits =
+            config.sectors_per_block as u64 + 1 + u64::from(config.organization.is_rehash());
+        let tag_bits = block_bits.saturating_sub(index_bits as u64) + status_bits;
+
+        let victim_entries = config.victim_cache_entries as u64;
+        let victim_bits = victim_entries * (block_bits + status_bits);
+
+        let predictor_bits = match config.prediction {
+            PredictionStrategy::None => 0,
+            PredictionStrategy::Mru => {
+                num_sets * (ways.next_power_of_two().trailing_zeros() as u64)
+            }
+            PredictionStrategy::MultiColumn => {
+                let columns = config
+                    .predictor_columns
+                    .unwrap_or_else(|| MultiColumnPredictor::default_columns(config.associativity));
+                num_sets * columns as u64 * ways
+            }
+        };
+
+        AreaEstimate {
+            data_bytes: config.cache_size as u64,
+            tag_bytes: (lines * tag_bits).div_ceil(8),
+            victim_bytes: victim_entries * config.block_size as u64 + victim_bits.div_ceil(8),
+            predictor_bytes: predictor_bits.div_ceil(8),
+        }
+    }
+
+    /// Energy of the run in `stats` on `config`.
+    ///
+    /// With a predictor, main-array hits read only the ways in
+    /// [`crate::cache::PredictionStats::ways_probed_total`] and every access
+    /// reads the predictor once. Everything that misses the main array reads
+    /// all ways before probing the victim buffer.
+    pub fn estimate(&self, config: &CacheConfig, stats: &CacheStats) -> EnergyEstimate {
+        let ways = config.associativity as f64;
+        let main_misses = stats.misses + stats.victim_hits;
+        let (ways_read, predictor_reads) = match &stats.prediction {
+            Some(prediction) => (
+                prediction.ways_probed_total as f64 + main_misses as f64 * ways,
+                stats.accesses,
+            ),
+            None => (stats.accesses as f64 * ways, 0),
+        };
+        self.with_ways_read(config, stats, ways_read, predictor_reads)
+    }
+
+    /// Energy of the same run with a conventional lookup that reads every way
+    /// in parallel and has no predictor.
+    pub fn parallel_baseline(&self, config: &CacheConfig, stats: &CacheStats) -> EnergyEstimate {
+        let ways_read = stats.accesses as f64 * config.associativity as f64;
+        let mut baseline = config.clone();
+        baseline.prediction = PredictionStrategy::None;
+        self.with_ways_read(&baseline, stats, ways_read, 0)
+    }
+
+    fn with_ways_read(
+        &self,
+        config: &CacheConfig,
+        stats: &CacheStats,
+        ways_read: f64,
+        predictor_reads: u64,
+    ) -> EnergyEstimate {
+        let victim_probes = if config.victim_cache_entries > 0 {
+            stats.misses + stats.victim_hits
+        } else {
+            0
+        };
+        EnergyEstimate {
+            accesses: stats.accesses,
+            tag_pj: ways_read * self.tag_read_pj,
+            data_pj: ways_read * self.data_read_pj,
+            victim_pj: victim_probes as f64 * self.victim_probe_pj,
+            predictor_pj: predictor_reads as f64 * self.predictor_access_pj,
+            leakage_pj: self.area(config).total_bytes() as f64
+                * self.leakage_pj_per_byte_cycle
+                * stats.accesses as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::Cache,
+        trace::{AccessKind, TraceAccess},
+    };
+
+    fn four_way(prediction: PredictionStrategy) -> CacheConfig {
+        // 64 sets of four 32-byte lines.
+        CacheConfig::builder()
+            .cache_size(8 * 1024)
+            .associativity(4)
+            .victim_cache_entries(8)
+            .prediction(prediction)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn area_counts_tags_victim_entries_and_predictor_bits() {
+        let model = EnergyModel::default();
+        let area = model.area(&four_way(PredictionStrategy::None));
+        // 48 - 5 offset - 6 index tag bits, plus one valid-sector and one dirty bit.
+        assert_eq!(area.data_bytes, 8 * 1024);
+        assert_eq!(area.tag_bytes, 256 * 39 / 8);
+        assert_eq!(area.victim_bytes, 8 * 32 + (8 * 45_u64).div_ceil(8));
+        assert_eq!(area.predictor_bytes, 0);
+        let mru = model.area(&four_way(PredictionStrategy::Mru));
+        assert_eq!(mru.predictor_bytes, 64 * 2 / 8);
+        // Four ways default to two columns of four bits per set.
+        let multi_column = model.area(&four_way(PredictionStrategy::MultiColumn));
+        assert_eq!(multi_column.predictor_bytes, 64 * 2 * 4 / 8);
+    }
+
+    #[test]
+    fn correct_predictions_read_fewer_ways_than_the_parallel_baseline() {
+        let config = four_way(PredictionStrategy::Mru);
+        let mut cache = Cache::new(config.clone()).unwrap();
+        let trace = [TraceAccess {
+            kind: AccessKind::Read,
+            address: 0,
+            thread: 0,
+            size: 4,
+            timestamp: 0,
+        }; 10];
+        let stats = cache.run_trace(&trace);
+        let model = EnergyModel::default();
+        let predicted = model.estimate(&config, &stats);
+        let baseline = model.parallel_baseline(&config, &stats);
+        assert_eq!(predicted.predictor_pj, 10.0 * model.predictor_access_pj);
+        assert_eq!(baseline.predictor_pj, 0.0);
+        assert!(predicted.data_pj < baseline.data_pj / 2.0);
+        assert!(predicted.dynamic_per_access() < baseline.dynamic_per_access());
+        // Without a predictor the estimate is the parallel lookup itself.
+        let plain = four_way(PredictionStrategy::None);
+        let stats = Cache::new(plain.clone()).unwrap().run_trace(&trace);
+        assert_eq!(
+            model.estimate(&plain, &stats).total_pj(),
+            model.parallel_baseline(&plain, &stats).total_pj()
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    cache::ConfigError, characterize::CharacterizeError, measurement::MeasurementError,
+    mix::MixError, partition::PartitionError, sampling::SamplingError, synthetic::PatternError,
+    trace::TraceError,
+};
+use std::fmt;
+
+/// Any reason a run cannot start: a rejected [`crate::cache::CacheConfig`],
+/// a trace that cannot be loaded, or bad parameters for one of the tools
+/// built around the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Config(ConfigError),
+    Measurement(MeasurementError),
+    Trace(TraceError),
+    Mix(MixError),
+    Partition(PartitionError),
+    Pattern(PatternError),
+    Characterize(CharacterizeError),
+    Sampling(SamplingError),
+    /// Interval statistics need intervals of at least one access.
+    ZeroInterval,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(err) => err.fmt(f),
+            Error::Measurement(err) => err.fmt(f),
+            Error::Trace(err) => err.fmt(f),
+            Error::Mix(err) => err.fmt(f),
+            Error::Partition(err) => err.fmt(f),
+            Error::Pattern(err) => err.fmt(f),
+            Error::Characterize(err) => err.fmt(f),
+            Error::Sampling(err) => err.fmt(f),
+            Error::ZeroInterval => write!(f, "intervals must hold at least one access"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Config(err) => Some(err),
+            Error::Measurement(err) => Some(err),
+            Error::Trace(err) => Some(err),
+            Error::Mix(err) => Some(err),
+            Error::Partition(err) => Some(err),
+            Error::Pattern(err) => Some(err),
+            Error::Characterize(err) => Some(err),
+            Error::Sampling(err) => Some(err),
+            Error::ZeroInterval => None,
+        }
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(err: ConfigError) -> Self {
+        Error::Config(err)
+    }
+}
+
+impl From<MeasurementError> for Error {
+    fn from(err: MeasurementError) -> Self {
+        Error::Measurement(err)
+    }
+}
+
+impl From<TraceError> for Error {
+    fn from(err: TraceError) -> Self {
+        Error::Trace(err)
+    }
+}
+
+impl From<MixError> for Error {
+    fn from(err: MixError) -> Self {
+        Error::Mix(err)
+    }
+}
+
+impl From<PartitionError> for Error {
+    fn from(err: PartitionError) -> Self {
+        Error::Partition(err)
+    }
+}
+
+impl From<PatternError> for Error {
+    fn from(err: PatternError) -> Self {
+        Error::Pattern(err)
+    }
+}
+
+impl From<CharacterizeError> for Error {
+    fn from(err: CharacterizeError) -> Self {
+        Error::Characterize(err)
+    }
+}
+
+impl From<SamplingError> for Error {
+    fn from(err: SamplingError) -> Self {
+        Error::Sampling(err)
+    }
+}
@@ -1,17 +1,38 @@
-use std::fmt;
 use crate::{
-    cache::{Cache, CacheConfig, CacheStats, PredictionStrategy},
+    cache::{
+        Cache, CacheConfig, CacheOrganization, CacheStats, ConfigError, PredictionStrategy,
+        VictimFilter, VictimHitAction, VictimPolicy,
+    },
+    characterize::{CharacterizeConfig, CharacterizeError, TraceReport, characterize},
+    coherence::{CoherenceProtocol, CoreStats, SnoopingSystem},
+    directory::{DirectorySystem, SharerTracking, SystemStats},
+    error::Error,
+    index::IndexFunction,
+    measurement::Measurement,
+    mix::{MixConfig, MixPolicy},
+    partition::{PartitionEpoch, PartitionPolicy, SharedCache},
+    sampling::{SampledEstimate, SamplingMode, estimate},
+    series::TimeSeries,
+    sharing::{SharingAnalysis, SharingReport},
+    synthetic::{Pattern, SyntheticConfig},
     trace::TraceFile,
+    translation::{
+        FrameAllocation, PageMapping, PageSize, TranslationConfig, TranslationStats, Translator,
+    },
 };
+use std::fmt;
 
+/// A labelled cache configuration to run against every trace.
 #[derive(Clone)]
 pub struct ScenarioConfig {
     pub label: String, // Label to be printed for the Result
     pub config: CacheConfig,
 }
 
+/// Per-trace results for one [`ScenarioConfig`].
 pub struct ScenarioResult {
     pub label: String, // Label to be printed for the Result
+    pub config: CacheConfig,
     pub trace_results: Vec<TraceResult>,
 }
 
@@ -20,6 +41,192 @@ pub struct TraceResult {
     pub stats: CacheStats,
 }
 
+/// A labelled multi-core configuration: `cores` private caches built from
+/// `config`, kept coherent with `protocol`.
+#[derive(Clone)]
+pub struct CoherenceScenario {
+    pub label: String,
+    pub config: CacheConfig,
+    pub cores: usize,
+    pub protocol: CoherenceProtocol,
+}
+
+/// Per-trace, per-core results for one [`CoherenceScenario`].
+pub struct CoherenceResult {
+    pub label: String,
+    pub trace_results: Vec<CoherenceTraceResult>,
+}
+
+pub struct CoherenceTraceResult {
+    pub trace_name: String,
+    pub cores: Vec<CoreStats>,
+}
+
+/// A labelled directory-mode configuration: `cores` private caches built from
+/// `private` behind a shared `llc`.
+#[derive(Clone)]
+pub struct DirectoryScenario {
+    pub label: String,
+    pub private: CacheConfig,
+    pub llc: CacheConfig,
+    pub cores: usize,
+    pub tracking: SharerTracking,
+}
+
+/// Per-trace results for one [`DirectoryScenario`].
+pub struct DirectoryResult {
+    pub label: String,
+    pub trace_results: Vec<DirectoryTraceResult>,
+}
+
+pub struct DirectoryTraceResult {
+    pub trace_name: String,
+    pub stats: SystemStats,
+}
+
+/// A labelled cache configuration fed through virtual-to-physical translation.
+#[derive(Clone)]
+pub struct TranslatedScenario {
+    pub label: String,
+    pub cache: CacheConfig,
+    pub translation: TranslationConfig,
+}
+
+/// Per-trace results for one [`TranslatedScenario`].
+pub struct TranslatedResult {
+    pub label: String,
+    pub trace_results: Vec<TranslatedTraceResult>,
+}
+
+pub struct TranslatedTraceResult {
+    pub trace_name: String,
+    pub translation: TranslationStats,
+    /// Stats of the cache on physical addresses.
+    pub cache: CacheStats,
+}
+
+/// Every trace co-running on one shared `config`, with its ways divided by `policy`.
+#[derive(Clone)]
+pub struct PartitionScenario {
+    pub label: String,
+    pub config: CacheConfig,
+    pub policy: PartitionPolicy,
+}
+
+/// Per-source results for one [`PartitionScenario`].
+pub struct PartitionResult {
+    pub label: String,
+    pub sources: Vec<SourceResult>,
+    pub history: Vec<PartitionEpoch>,
+}
+
+impl PartitionResult {
+    /// Sum of the per-source speedups: the number of sources when sharing
+    /// costs nothing.
+    pub fn weighted_speedup(&self, miss_penalty: f64) -> f64 {
+        self.sources
+            .iter()
+            .map(|source| source.speedup(miss_penalty))
+            .sum()
+    }
+}
+
+pub struct SourceResult {
+    pub trace_name: String,
+    /// Stats while sharing the cache.
+    pub shared: CacheStats,
+    /// Stats with the whole cache to itself.
+    pub alone: CacheStats,
+}
+
+impl SourceResult {
+    /// Shared over alone performance, taking each access to cost one cycle
+    /// plus `miss_penalty` cycles on a miss.
+    pub fn speedup(&self, miss_penalty: f64) -> f64 {
+        let cycles = |stats: &CacheStats| 1.0 + (1.0 - stats.hit_rate()) * miss_penalty;
+        cycles(&self.alone) / cycles(&self.shared)
+    }
+}
+
+/// Every trace interleaved by `mix` into one unpartitioned `config`.
+#[derive(Clone)]
+pub struct MixScenario {
+    pub label: String,
+    pub config: CacheConfig,
+    pub mix: MixConfig,
+}
+
+/// Aggregate and per-source results for one [`MixScenario`].
+pub struct MixResult {
+    pub label: String,
+    /// Stats over the whole interleaved stream.
+    pub aggregate: CacheStats,
+    pub sources: Vec<SourceResult>,
+}
+
+impl MixResult {
+    /// See [`PartitionResult::weighted_speedup`].
+    pub fn weighted_speedup(&self, miss_penalty: f64) -> f64 {
+        self.sources
+            .iter()
+            .map(|source| source.speedup(miss_penalty))
+            .sum()
+    }
+}
+
+/// `config` estimated from the slices `mode` picks out of every trace.
+#[derive(Clone)]
+pub struct SamplingScenario {
+    pub label: String,
+    pub config: CacheConfig,
+    pub mode: SamplingMode,
+}
+
+/// Per-trace results for one [`SamplingScenario`].
+pub struct SamplingResult {
+    pub label: String,
+    pub trace_results: Vec<SampledTraceResult>,
+}
+
+pub struct SampledTraceResult {
+    pub trace_name: String,
+    pub estimate: SampledEstimate,
+    /// Stats of a full run, to judge the estimate against.
+    pub full: CacheStats,
+}
+
+impl SampledTraceResult {
+    /// Estimated minus true hit rate.
+    pub fn error(&self) -> f64 {
+        self.estimate.hit_rate - self.full.hit_rate()
+    }
+}
+
+/// Per-trace time series for one [`ScenarioConfig`].
+pub struct SeriesResult {
+    pub label: String,
+    pub trace_results: Vec<SeriesTraceResult>,
+}
+
+pub struct SeriesTraceResult {
+    pub trace_name: String,
+    pub series: TimeSeries,
+    /// Stats of the whole run.
+    pub stats: CacheStats,
+}
+
+/// Characterisation of one trace.
+pub struct CharacterizationResult {
+    pub trace_name: String,
+    pub report: TraceReport,
+}
+
+/// False-sharing report for one trace.
+pub struct SharingResult {
+    pub trace_name: String,
+    pub report: SharingReport,
+}
+
 impl fmt::Display for ScenarioResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "{}", self.label)?;
@@ -35,80 +242,482 @@ impl fmt::Display for ScenarioResult {
     }
 }
 
+/// Runs each scenario against each trace on a fresh cache, counting the
+/// accesses `measurement` selects.
 pub fn run_scenarios(
     traces: &[TraceFile],
     scenarios: &[ScenarioConfig],
-) -> Vec<ScenarioResult> {
+    measurement: &Measurement,
+) -> Result<Vec<ScenarioResult>, Error> {
+    measurement.validate()?;
     let mut results = Vec::new();
     for scenario in scenarios {
         let mut per_trace = Vec::new();
         for trace in traces {
-            let mut cache = Cache::new(scenario.config.clone());
-            let stats = cache.run_trace(&trace.entries);
+            let mut cache = Cache::new(scenario.config.clone())?;
+            let stats = cache.run_measured(&trace.entries, measurement)?;
             per_trace.push(TraceResult {
                 trace_name: trace.name.clone(),
                 stats,
             });
         }
         results.push(ScenarioResult {
+            label: scenario.label.clone(),
+            config: scenario.config.clone(),
+            trace_results: per_trace,
+        });
+    }
+    Ok(results)
+}
+
+/// [`run_scenarios`], also recording stats for every `interval` accesses
+/// of each trace.
+pub fn run_series(
+    traces: &[TraceFile],
+    scenarios: &[ScenarioConfig],
+    measurement: &Measurement,
+    interval: usize,
+) -> Result<Vec<SeriesResult>, Error> {
+    let mut results = Vec::new();
+    for scenario in scenarios {
+        let mut per_trace = Vec::new();
+        for trace in traces {
+            let mut cache = Cache::new(scenario.config.clone())?;
+            let series = cache.run_series(&trace.entries, measurement, interval)?;
+            per_trace.push(SeriesTraceResult {
+                trace_name: trace.name.clone(),
+                series,
+                stats: cache.stats().clone(),
+            });
+        }
+        results.push(SeriesResult {
+            label: scenario.label.clone(),
+            trace_results: per_trace,
+        });
+    }
+    Ok(results)
+}
+
+/// Runs each multi-core scenario against each trace on fresh caches.
+pub fn run_coherence(
+    traces: &[TraceFile],
+    scenarios: &[CoherenceScenario],
+    measurement: &Measurement,
+) -> Result<Vec<CoherenceResult>, Error> {
+    measurement.validate()?;
+    let mut results = Vec::new();
+    for scenario in scenarios {
+        let mut per_trace = Vec::new();
+        for trace in traces {
+            let mut system =
+                SnoopingSystem::new(scenario.config.clone(), scenario.cores, scenario.protocol)?;
+            per_trace.push(CoherenceTraceResult {
+                trace_name: trace.name.clone(),
+                cores: system.run_measured(&trace.entries, measurement)?,
+            });
+        }
+        results.push(CoherenceResult {
+            label: scenario.label.clone(),
+            trace_results: per_trace,
+        });
+    }
+    Ok(results)
+}
+
+/// Runs each directory-mode scenario against each trace on fresh caches.
+pub fn run_directory(
+    traces: &[TraceFile],
+    scenarios: &[DirectoryScenario],
+    measurement: &Measurement,
+) -> Result<Vec<DirectoryResult>, Error> {
+    measurement.validate()?;
+    let mut results = Vec::new();
+    for scenario in scenarios {
+        let mut per_trace = Vec::new();
+        for trace in traces {
+            let mut system = DirectorySystem::new(
+                scenario.private.clone(),
+                scenario.llc.clone(),
+                scenario.cores,
+                scenario.tracking,
+            )?;
+            per_trace.push(DirectoryTraceResult {
+                trace_name: trace.name.clone(),
+                stats: system.run_measured(&trace.entries, measurement)?,
+            });
+        }
+        results.push(DirectoryResult {
+            label: scenario.label.clone(),
+            trace_results: per_trace,
+        });
+    }
+    Ok(results)
+}
+
+/// Runs each translated scenario against each trace with a fresh page table,
+/// TLBs and cache.
+pub fn run_translated(
+    traces: &[TraceFile],
+    scenarios: &[TranslatedScenario],
+    measurement: &Measurement,
+) -> Result<Vec<TranslatedResult>, Error> {
+    measurement.validate()?;
+    let mut results = Vec::new();
+    for scenario in scenarios {
+        let mut per_trace = Vec::new();
+        for trace in traces {
+            let mut translator = Translator::new(scenario.translation.clone())?;
+            let mut cache = Cache::new(scenario.cache.clone())?;
+            for (position, access) in trace.entries.iter().enumerate() {
+                let measured = measurement.is_measured(position, trace.entries.len());
+                let physical = if measured {
+                    translator.translate_access(access)
+                } else {
+                    translator.warm_access(access)
+                };
+                if let Some(frame) = translator.take_reclaimed() {
+                    // Write-backs of reclaimed pages count like the accesses around them.
+                    cache.set_measuring(measured);
+                    cache.invalidate_range(frame);
+                    cache.set_measuring(true);
+                }
+                if measured {
+                    cache.access(&physical);
+                } else {
+                    cache.warm(&physical);
+                }
+            }
+            per_trace.push(TranslatedTraceResult {
+                trace_name: trace.name.clone(),
+                translation: translator.stats().clone(),
+                cache: cache.stats().clone(),
+            });
+        }
+        results.push(TranslatedResult {
+            label: scenario.label.clone(),
+            trace_results: per_trace,
+        });
+    }
+    Ok(results)
+}
+
+/// Co-runs every trace on each scenario's shared cache, and each trace alone
+/// on an unpartitioned copy of the cache for reference.
+pub fn run_partitioned(
+    traces: &[TraceFile],
+    scenarios: &[PartitionScenario],
+    measurement: &Measurement,
+) -> Result<Vec<PartitionResult>, Error> {
+    let mut results = Vec::new();
+    for scenario in scenarios {
+        let (shared, sources) = co_run(
+            traces,
+            &scenario.config,
+            scenario.policy.clone(),
+            &MixConfig::default(),
+            measurement,
+        )?;
+        results.push(PartitionResult {
+            label: scenario.label.clone(),
+            sources,
+            history: shared.history().to_vec(),
+        });
+    }
+    Ok(results)
+}
+
+/// Estimates each scenario on each trace from samples, alongside a full run
+/// on a fresh cache. Slices are warmed by their [`SamplingMode`], and the
+/// full run counts every access, as the estimate targets it.
+pub fn run_sampled(
+    traces: &[TraceFile],
+    scenarios: &[SamplingScenario],
+) -> Result<Vec<SamplingResult>, Error> {
+    let mut results = Vec::new();
+    for scenario in scenarios {
+        let mut per_trace = Vec::new();
+        for trace in traces {
+            per_trace.push(SampledTraceResult {
+                trace_name: trace.name.clone(),
+                estimate: estimate(&trace.entries, &scenario.config, &scenario.mode)?,
+                full: Cache::new(scenario.config.clone())?.run_trace(&trace.entries),
+            });
+        }
+        results.push(SamplingResult {
             label: scenario.label.clone(),
             trace_results: per_trace,
         });
     }
-    results
+    Ok(results)
 }
 
-pub fn direct_mapped(base: &CacheConfig) -> ScenarioConfig {
+/// Interleaves the traces of each mix into one shared cache, and runs each
+/// trace alone on a copy of the cache for reference.
+pub fn run_mixed(
+    traces: &[TraceFile],
+    scenarios: &[MixScenario],
+    measurement: &Measurement,
+) -> Result<Vec<MixResult>, Error> {
+    let mut results = Vec::new();
+    for scenario in scenarios {
+        let (shared, sources) = co_run(
+            traces,
+            &scenario.config,
+            PartitionPolicy::Shared,
+            &scenario.mix,
+            measurement,
+        )?;
+        results.push(MixResult {
+            label: scenario.label.clone(),
+            aggregate: shared.cache().stats().clone(),
+            sources,
+        });
+    }
+    Ok(results)
+}
+
+/// Runs `traces` together on a fresh shared cache, then each alone on a
+/// fresh unpartitioned one. `measurement` applies to the mixed stream and to
+/// each trace run alone.
+fn co_run(
+    traces: &[TraceFile],
+    config: &CacheConfig,
+    policy: PartitionPolicy,
+    mix: &MixConfig,
+    measurement: &Measurement,
+) -> Result<(SharedCache, Vec<SourceResult>), Error> {
+    let entries: Vec<_> = traces
+        .iter()
+        .map(|trace| trace.entries.as_slice())
+        .collect();
+    let mut shared = SharedCache::new(config.clone(), traces.len(), policy)?;
+    shared.run_measured(&entries, mix, measurement)?;
+    let mut alone_config = config.clone();
+    alone_config.way_quotas = None;
+    let sources = traces
+        .iter()
+        .zip(shared.source_stats())
+        .map(|(trace, stats)| {
+            let alone =
+                Cache::new(alone_config.clone())?.run_measured(&trace.entries, measurement)?;
+            Ok(SourceResult {
+                trace_name: trace.name.clone(),
+                shared: stats.clone(),
+                alone,
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok((shared, sources))
+}
+
+/// Classifies each trace's invalidations at `block_size` granularity and keeps
+/// the `top` blocks with the most false sharing. The invalidations come from
+/// [`SharingAnalysis`]'s idealised per-thread caches, not a coherence run.
+pub fn analyze_sharing(traces: &[TraceFile], block_size: usize, top: usize) -> Vec<SharingResult> {
+    traces
+        .iter()
+        .map(|trace| {
+            let mut analysis = SharingAnalysis::new(block_size);
+            analysis.run_trace(&trace.entries);
+            SharingResult {
+                trace_name: trace.name.clone(),
+                report: analysis.report(top),
+            }
+        })
+        .collect()
+}
+
+impl ScenarioConfig {
+    /// Labels `config` after checking it with [`CacheConfig::validate`].
+    pub fn new(label: impl Into<String>, config: CacheConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(Self {
+            label: label.into(),
+            config,
+        })
+    }
+}
+
+pub fn direct_mapped(base: &CacheConfig) -> Result<ScenarioConfig, ConfigError> {
     let mut cfg = base.clone();
     cfg.associativity = 1;
     cfg.prediction = PredictionStrategy::None;
     cfg.victim_cache_entries = 0;
-    ScenarioConfig {
-        label: "Direct-Mapped".to_string(),
-        config: cfg,
-    }
+    ScenarioConfig::new("Direct-Mapped", cfg)
 }
 
-pub fn set_associative(base: &CacheConfig, ways: &[usize]) -> Vec<ScenarioConfig> {
+pub fn set_associative(
+    base: &CacheConfig,
+    ways: &[usize],
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
     ways.iter()
         .map(|&assoc| {
             let mut cfg = base.clone();
             cfg.associativity = assoc;
             cfg.prediction = PredictionStrategy::None;
             cfg.victim_cache_entries = 0;
-            ScenarioConfig {
-                label: format!("{assoc}-way SA"),
-                config: cfg,
-            }
+            ScenarioConfig::new(format!("{assoc}-way SA"), cfg)
+        })
+        .collect()
+}
+
+pub fn skewed_associative(
+    base: &CacheConfig,
+    ways: &[usize],
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
+    ways.iter()
+        .map(|&assoc| {
+            let mut cfg = base.clone();
+            cfg.associativity = assoc;
+            cfg.organization = CacheOrganization::SkewedAssociative;
+            cfg.prediction = PredictionStrategy::None;
+            cfg.victim_cache_entries = 0;
+            ScenarioConfig::new(format!("{assoc}-way Skewed"), cfg)
+        })
+        .collect()
+}
+
+pub fn index_functions(
+    base: &CacheConfig,
+    functions: &[IndexFunction],
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
+    functions
+        .iter()
+        .map(|function| {
+            let mut cfg = base.clone();
+            cfg.indexing = function.clone();
+            ScenarioConfig::new(format!("Index {function}"), cfg)
+        })
+        .collect()
+}
+
+/// Hash-rehash and column-associative variants of the direct-mapped `base`.
+pub fn rehash_configs(base: &CacheConfig) -> Result<Vec<ScenarioConfig>, ConfigError> {
+    [
+        (CacheOrganization::HashRehash, "Hash-Rehash"),
+        (CacheOrganization::ColumnAssociative, "Column-Associative"),
+    ]
+    .into_iter()
+    .map(|(organization, label)| {
+        let mut cfg = base.clone();
+        cfg.associativity = 1;
+        cfg.organization = organization;
+        cfg.prediction = PredictionStrategy::None;
+        cfg.victim_cache_entries = 0;
+        ScenarioConfig::new(label, cfg)
+    })
+    .collect()
+}
+
+/// `base` on physical addresses, with `mapping` placing pages by each allocation policy.
+pub fn page_mapping_configs(
+    base: &CacheConfig,
+    mapping: &PageMapping,
+    allocations: &[FrameAllocation],
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
+    allocations
+        .iter()
+        .map(|&allocation| {
+            let mut cfg = base.clone();
+            cfg.page_mapping = Some(PageMapping {
+                allocation,
+                ..*mapping
+            });
+            ScenarioConfig::new(
+                format!("{} pages, {allocation} frames", mapping.page_size),
+                cfg,
+            )
         })
         .collect()
 }
 
-pub fn block_sizes(base: &CacheConfig, block_sizes: &[usize]) -> Vec<ScenarioConfig> {
+pub fn block_sizes(
+    base: &CacheConfig,
+    block_sizes: &[usize],
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
     block_sizes
         .iter()
         .map(|&block| {
             let mut cfg = base.clone();
             cfg.block_size = block;
-            ScenarioConfig {
-                label: format!("Block {block}B"),
-                config: cfg,
-            }
+            ScenarioConfig::new(format!("Block {block}B"), cfg)
         })
         .collect()
 }
 
-pub fn victim_cache_configs(base: &CacheConfig, entries: &[usize]) -> Vec<ScenarioConfig> {
+/// Block size sweep with next-line prefetch enabled.
+pub fn prefetch_block_sizes(
+    base: &CacheConfig,
+    block_sizes: &[usize],
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
+    block_sizes
+        .iter()
+        .map(|&block| {
+            let mut cfg = base.clone();
+            cfg.block_size = block;
+            cfg.prefetch_next_line = true;
+            ScenarioConfig::new(format!("Block {block}B + next-line prefetch"), cfg)
+        })
+        .collect()
+}
+
+/// Splits each block of `base` into the given sector counts.
+pub fn sector_configs(
+    base: &CacheConfig,
+    sectors: &[usize],
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
+    sectors
+        .iter()
+        .map(|&count| {
+            let mut cfg = base.clone();
+            cfg.sectors_per_block = count;
+            let label = format!(
+                "Block {}B x {count} sectors ({}B)",
+                cfg.block_size,
+                cfg.block_size / count.max(1)
+            );
+            ScenarioConfig::new(label, cfg)
+        })
+        .collect()
+}
+
+/// Adds a victim buffer of each size to `base`, keeping its associativity.
+pub fn victim_cache_configs(
+    base: &CacheConfig,
+    entries: &[usize],
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
+    let prefix = match base.associativity {
+        1 => "DM".to_string(),
+        ways => format!("{ways}-way"),
+    };
     entries
         .iter()
         .map(|&size| {
             let mut cfg = base.clone();
-            cfg.associativity = 1;
             cfg.victim_cache_entries = size;
-            ScenarioConfig {
-                label: format!("DM + Victim({size})"),
-                config: cfg,
-            }
+            ScenarioConfig::new(format!("{prefix} + Victim({size})"), cfg)
+        })
+        .collect()
+}
+
+/// One scenario per (replacement, insertion filter, hit action) combination
+/// on top of `base`, which must already have a victim buffer.
+pub fn victim_policy_configs(
+    base: &CacheConfig,
+    variants: &[(VictimPolicy, VictimFilter, VictimHitAction)],
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
+    variants
+        .iter()
+        .map(|&(policy, filter, on_hit)| {
+            let mut cfg = base.clone();
+            cfg.victim_policy = policy;
+            cfg.victim_filter = filter;
+            cfg.victim_on_hit = on_hit;
+            let label = format!(
+                "Victim({}) {policy:?} {filter:?} {on_hit:?}",
+                cfg.victim_cache_entries
+            );
+            ScenarioConfig::new(label, cfg)
         })
         .collect()
 }
@@ -117,7 +726,7 @@ pub fn predictor_configs(
     base: &CacheConfig,
     ways: &[usize],
     strategy: PredictionStrategy,
-) -> Vec<ScenarioConfig> {
+) -> Result<Vec<ScenarioConfig>, ConfigError> {
     let label_prefix = match strategy {
         PredictionStrategy::None => "No-Predict",
         PredictionStrategy::Mru => "MRU",
@@ -129,10 +738,226 @@ pub fn predictor_configs(
             cfg.associativity = assoc;
             cfg.prediction = strategy;
             cfg.victim_cache_entries = 0;
-            ScenarioConfig {
-                label: format!("{label_prefix} {assoc}-way"),
-                config: cfg,
-            }
+            ScenarioConfig::new(format!("{label_prefix} {assoc}-way"), cfg)
+        })
+        .collect()
+}
+
+/// `base` on `cores` private caches under each protocol.
+pub fn coherence_protocols(
+    base: &CacheConfig,
+    cores: usize,
+    protocols: &[CoherenceProtocol],
+) -> Result<Vec<CoherenceScenario>, ConfigError> {
+    base.validate()?;
+    if cores == 0 {
+        return Err(ConfigError::ZeroCores);
+    }
+    Ok(protocols
+        .iter()
+        .map(|&protocol| CoherenceScenario {
+            label: format!("{protocol} x{cores} cores"),
+            config: base.clone(),
+            cores,
+            protocol,
+        })
+        .collect())
+}
+
+/// `private` caches behind `llc` with each way of tracking sharers.
+pub fn sharer_tracking_configs(
+    private: &CacheConfig,
+    llc: &CacheConfig,
+    cores: usize,
+    trackings: &[SharerTracking],
+) -> Result<Vec<DirectoryScenario>, ConfigError> {
+    for &tracking in trackings {
+        DirectorySystem::validate(private, llc, cores, tracking)?;
+    }
+    Ok(trackings
+        .iter()
+        .map(|&tracking| DirectoryScenario {
+            label: format!("MOESI {tracking} x{cores} cores"),
+            private: private.clone(),
+            llc: llc.clone(),
+            cores,
+            tracking,
+        })
+        .collect())
+}
+
+/// `cache` behind `base` translation with each page size and frame allocation.
+pub fn translation_configs(
+    cache: &CacheConfig,
+    base: &TranslationConfig,
+    variants: &[(PageSize, FrameAllocation)],
+) -> Result<Vec<TranslatedScenario>, ConfigError> {
+    cache.validate()?;
+    variants
+        .iter()
+        .map(|&(page_size, allocation)| {
+            let mut translation = base.clone();
+            translation.page_size = page_size;
+            translation.allocation = allocation;
+            translation.validate()?;
+            Ok(TranslatedScenario {
+                label: format!("{page_size} pages, {allocation} frames"),
+                cache: cache.clone(),
+                translation,
+            })
+        })
+        .collect()
+}
+
+/// `base` shared by `sources` co-running traces under each partitioning policy.
+pub fn partition_policies(
+    base: &CacheConfig,
+    sources: usize,
+    policies: &[PartitionPolicy],
+) -> Result<Vec<PartitionScenario>, Error> {
+    policies
+        .iter()
+        .map(|policy| {
+            SharedCache::new(base.clone(), sources, policy.clone())?;
+            Ok(PartitionScenario {
+                label: format!("{}-way, {policy}", base.associativity),
+                config: base.clone(),
+                policy: policy.clone(),
+            })
+        })
+        .collect()
+}
+
+/// `base` shared by `sources` traces interleaved by each mix policy, every
+/// source in its own address space.
+pub fn mix_policies(
+    base: &CacheConfig,
+    sources: usize,
+    policies: &[MixPolicy],
+) -> Result<Vec<MixScenario>, Error> {
+    base.validate()?;
+    policies
+        .iter()
+        .map(|policy| {
+            let mix = MixConfig {
+                policy: policy.clone(),
+                ..MixConfig::default()
+            };
+            mix.validate(sources)?;
+            Ok(MixScenario {
+                label: format!("{policy} mix"),
+                config: base.clone(),
+                mix,
+            })
+        })
+        .collect()
+}
+
+/// `base` estimated under each sampling mode.
+pub fn sampling_modes(
+    base: &CacheConfig,
+    modes: &[SamplingMode],
+) -> Result<Vec<SamplingScenario>, Error> {
+    base.validate()?;
+    modes
+        .iter()
+        .map(|mode| {
+            mode.validate()?;
+            Ok(SamplingScenario {
+                label: format!("{mode}"),
+                config: base.clone(),
+                mode: *mode,
+            })
+        })
+        .collect()
+}
+
+/// One synthetic trace per pattern, sized against `cache`: a stream, a
+/// stride that maps every access to one set, uniform and Zipf working sets
+/// of twice the cache, a pointer chase over twice the cache, a loop a
+/// quarter larger than it, and a Zipf working set of half the cache mixed
+/// 3:1 with a stream that keeps polluting it.
+pub fn synthetic_suite(
+    cache: &CacheConfig,
+    accesses: usize,
+    seed: u64,
+) -> Result<Vec<TraceFile>, Error> {
+    cache.validate()?;
+    let size = cache.cache_size as u64;
+    let block = cache.block_size as u64;
+    [
+        ("sequential", Pattern::Sequential),
+        (
+            "set-stride",
+            Pattern::Strided {
+                stride: block * cache.num_sets() as u64,
+            },
+        ),
+        (
+            "uniform",
+            Pattern::Uniform {
+                working_set: 2 * size,
+            },
+        ),
+        (
+            "zipf",
+            Pattern::Zipf {
+                working_set: 2 * size,
+                exponent: 0.9,
+            },
+        ),
+        (
+            "chase",
+            Pattern::PointerChase {
+                nodes: 2 * size / block,
+                node_size: block,
+            },
+        ),
+        (
+            "loop",
+            Pattern::Loop {
+                footprint: size + size / 4,
+            },
+        ),
+        (
+            "mixture",
+            Pattern::Mixture(vec![
+                (
+                    3,
+                    Pattern::Zipf {
+                        working_set: size / 2,
+                        exponent: 0.9,
+                    },
+                ),
+                (1, Pattern::Sequential),
+            ]),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, pattern)| {
+        SyntheticConfig {
+            accesses,
+            seed,
+            ..SyntheticConfig::new(pattern)
+        }
+        .generate(format!("{name}.syn"))
+        .map_err(Error::from)
+    })
+    .collect()
+}
+
+/// Characterises each trace under `config`.
+pub fn characterize_traces(
+    traces: &[TraceFile],
+    config: &CharacterizeConfig,
+) -> Result<Vec<CharacterizationResult>, CharacterizeError> {
+    traces
+        .iter()
+        .map(|trace| {
+            Ok(CharacterizationResult {
+                trace_name: trace.name.clone(),
+                report: characterize(&trace.entries, config)?,
+            })
         })
         .collect()
 }
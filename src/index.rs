@@ -0,0 +1,169 @@
+use std::fmt;
+
+/// How a block address is turned into a set index in a set-associative cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexFunction {
+    /// Low bits of the block address (`block % num_sets`).
+    Modulo,
+    /// XOR of every `log2(num_sets)`-bit chunk of the block address, so the
+    /// upper tag bits also disperse blocks across sets.
+    XorFold,
+    /// `block % p` for the largest prime `p <= num_sets`. The set count need
+    /// not be a power of two; sets at or above `p` are left unused.
+    PrimeModulo,
+    /// Index bit `i` is block-address bit `bits[i]`; needs exactly
+    /// `log2(num_sets)` distinct bits.
+    BitSelect(Vec<u32>),
+}
+
+impl fmt::Display for IndexFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexFunction::Modulo => write!(f, "modulo"),
+            IndexFunction::XorFold => write!(f, "xor-fold"),
+            IndexFunction::PrimeModulo => write!(f, "prime-modulo"),
+            IndexFunction::BitSelect(bits) => {
+                let bits: Vec<_> = bits.iter().map(|bit| bit.to_string()).collect();
+                write!(f, "bits[{}]", bits.join(","))
+            }
+        }
+    }
+}
+
+/// [`IndexFunction`] resolved against a concrete set count.
+pub(crate) struct SetIndexer {
+    function: IndexFunction,
+    num_sets: u64,
+    index_bits: u32,
+    prime: u64,
+}
+
+impl SetIndexer {
+    pub(crate) fn new(function: &IndexFunction, num_sets: usize) -> Self {
+        Self {
+            function: function.clone(),
+            num_sets: num_sets as u64,
+            index_bits: num_sets.trailing_zeros(),
+            prime: largest_prime_at_most(num_sets as u64),
+        }
+    }
+
+    pub(crate) fn set(&self, block_address: u64) -> usize {
+        let set = match &self.function {
+            IndexFunction::Modulo => block_address % self.num_sets,
+            IndexFunction::XorFold => {
+                if self.index_bits == 0 {
+                    return 0;
+                }
+                let mask = self.num_sets - 1;
+                let mut folded = 0;
+                let mut rest = block_address;
+                while rest != 0 {
+                    folded ^= rest & mask;
+                    rest >>= self.index_bits;
+                }
+                folded
+            }
+            IndexFunction::PrimeModulo => block_address % self.prime,
+            IndexFunction::BitSelect(bits) => {
+                bits.iter().enumerate().fold(0, |set, (idx, &bit)| {
+                    set | (((block_address >> bit) & 1) << idx)
+                })
+            }
+        };
+        set as usize
+    }
+}
+
+/// Largest prime not above `n`, or 1 when there is none.
+pub(crate) fn largest_prime_at_most(n: u64) -> u64 {
+    (2..=n)
+        .rev()
+        .find(|&candidate| is_prime(candidate))
+        .unwrap_or(1)
+}
+
+fn is_prime(n: u64) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|d| d * d <= n)
+            .all(|d| !n.is_multiple_of(d))
+}
+
+/// Set for `way` in a skewed-associative cache with `2^index_bits` sets.
+///
+/// Follows Seznec's construction: the low index bits are XORed with a
+/// per-way permutation of the next `index_bits` bits of the block address,
+/// so two blocks that collide in one way rarely collide in the others. The
+/// permutation is a rotation by `way`; past `index_bits` ways the rotated
+/// field is first passed through a Gray-code step per wrap so every way
+/// still gets a distinct bijection.
+pub(crate) fn skewed_set(block_address: u64, way: usize, index_bits: u32) -> usize {
+    if index_bits == 0 {
+        return 0;
+    }
+    let mask = (1u64 << index_bits) - 1;
+    let low = block_address & mask;
+    let mut high = (block_address >> index_bits) & mask;
+    for _ in 0..way / index_bits as usize {
+        high ^= high >> 1;
+    }
+    (low ^ rotate_left(high, way as u32 % index_bits, index_bits)) as usize
+}
+
+/// Rotates the low `bits` bits of `value`.
+fn rotate_left(value: u64, shift: u32, bits: u32) -> u64 {
+    if shift == 0 {
+        return value;
+    }
+    let mask = (1u64 << bits) - 1;
+    ((value << shift) | (value >> (bits - shift))) & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xor_folding_mixes_every_address_chunk_into_the_set() {
+        let indexer = SetIndexer::new(&IndexFunction::XorFold, 16);
+        assert_eq!(indexer.set(0x35), 0x5 ^ 0x3);
+        assert_eq!(indexer.set(0x123), 0x3 ^ 0x2 ^ 0x1);
+        assert_eq!(SetIndexer::new(&IndexFunction::XorFold, 1).set(0x123), 0);
+    }
+
+    #[test]
+    fn prime_modulo_uses_the_largest_prime_within_the_set_count() {
+        assert_eq!([1, 2, 16, 64].map(largest_prime_at_most), [1, 2, 13, 61]);
+        let indexer = SetIndexer::new(&IndexFunction::PrimeModulo, 16);
+        assert_eq!((indexer.set(13), indexer.set(20)), (0, 7));
+    }
+
+    #[test]
+    fn bit_select_places_the_chosen_bits_in_index_order() {
+        let indexer = SetIndexer::new(&IndexFunction::BitSelect(vec![4, 0]), 4);
+        assert_eq!(indexer.set(0b1_0001), 0b11);
+        assert_eq!(indexer.set(0b1_0000), 0b01);
+        assert_eq!(indexer.set(0b0_0001), 0b10);
+    }
+
+    #[test]
+    fn each_skewed_way_spreads_a_conflicting_group_differently() {
+        // The 16 blocks that share set 0 of an unskewed 16-set cache.
+        let group: Vec<u64> = (0..16).map(|high| high << 4).collect();
+        let mut placements = Vec::new();
+        for way in 0..8 {
+            let mut sets: Vec<usize> = group
+                .iter()
+                .map(|&block| skewed_set(block, way, 4))
+                .collect();
+            placements.push(sets.clone());
+            sets.sort();
+            sets.dedup();
+            assert_eq!(sets.len(), 16, "way {way} maps the group onto every set");
+        }
+        placements.sort();
+        placements.dedup();
+        assert_eq!(placements.len(), 8, "every way uses its own mapping");
+    }
+}
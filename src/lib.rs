@@ -0,0 +1,70 @@
+//! Trace-driven cache simulator.
+//!
+//! The crate models a single level of cache with LRU replacement, an optional
+//! victim buffer and MRU / multi-column way prediction. The binary in
+//! `main.rs` runs the bundled experiments; other tools can drive the same
+//! pieces directly:
+//!
+//! ```no_run
+//! use cache_simulator::{Cache, CacheConfig, TraceFile};
+//!
+//! let config = CacheConfig::builder()
+//!     .cache_size(64 * 1024)
+//!     .block_size(64)
+//!     .associativity(8)
+//!     .build()
+//!     .expect("valid config");
+//! let trace = TraceFile::load("trace/game.trace").expect("readable trace");
+//! let mut cache = Cache::new(config).expect("valid config");
+//! let stats = cache.run_trace(&trace.entries);
+//! println!("hit rate {:.2}%", stats.hit_rate() * 100.0);
+//! ```
+
+pub mod cache;
+pub mod characterize;
+pub mod coherence;
+pub mod directory;
+pub mod energy;
+pub mod error;
+pub mod experiments;
+pub mod index;
+pub mod measurement;
+pub mod mix;
+pub mod observer;
+pub mod partition;
+pub mod rng;
+pub mod sampling;
+pub mod series;
+pub mod sharing;
+pub mod synthetic;
+pub mod trace;
+pub mod translation;
+
+pub use cache::{
+    AccessOutcome, Cache, CacheConfig, CacheConfigBuilder, CacheOrganization, CacheStats,
+    ConfigError, EvictedLine, PredictionOutcome, PredictionStats, PredictionStrategy,
+    PrefetchOutcome, RehashOutcome, RehashStats, SectorStats, TrafficStats, VictimFilter,
+    VictimHitAction, VictimPolicy,
+};
+pub use characterize::{
+    CharacterizeConfig, CharacterizeError, ReuseHistogram, TraceReport, characterize,
+};
+pub use coherence::{CoherenceProtocol, CoherenceStats, CoreStats, LineState, SnoopingSystem};
+pub use directory::{DirectoryStats, DirectorySystem, SharerTracking, SystemStats};
+pub use energy::{AreaEstimate, EnergyEstimate, EnergyModel};
+pub use error::Error;
+pub use experiments::{ScenarioConfig, ScenarioResult, TraceResult, run_scenarios};
+pub use index::IndexFunction;
+pub use measurement::{Measurement, MeasurementError, Warmup};
+pub use mix::{MixConfig, MixError, MixPolicy, MixedAccess};
+pub use observer::CacheObserver;
+pub use partition::{PartitionEpoch, PartitionError, PartitionPolicy, SharedCache};
+pub use sampling::{SampledEstimate, SamplingError, SamplingMode, SliceResult, estimate};
+pub use series::{IntervalStats, TimeSeries};
+pub use sharing::{BlockSharingReport, SharingAnalysis, SharingReport};
+pub use synthetic::{Pattern, PatternError, SyntheticConfig};
+pub use trace::{AccessKind, TraceAccess, TraceError, TraceFile};
+pub use translation::{
+    FrameAllocation, PageMapping, PageSize, TlbConfig, TranslationConfig, TranslationStats,
+    Translator,
+};
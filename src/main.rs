@@ -1,36 +1,255 @@
-mod cache;
-mod experiments;
-mod trace;
-use cache::{CacheConfig, PredictionStrategy};
-use experiments::{
-    ScenarioResult, block_sizes, direct_mapped, predictor_configs, run_scenarios, set_associative,
-    victim_cache_configs,
+use cache_simulator::{
+    cache::{CacheConfig, PredictionStrategy, VictimFilter, VictimHitAction, VictimPolicy},
+    characterize::CharacterizeConfig,
+    coherence::CoherenceProtocol,
+    directory::SharerTracking,
+    energy::EnergyModel,
+    experiments::{
+        CharacterizationResult, CoherenceResult, DirectoryResult, MixResult, PartitionResult,
+        SamplingResult, ScenarioConfig, ScenarioResult, SeriesResult, SharingResult,
+        TranslatedResult, analyze_sharing, block_sizes, characterize_traces, coherence_protocols,
+        direct_mapped, index_functions, mix_policies, page_mapping_configs, partition_policies,
+        predictor_configs, prefetch_block_sizes, rehash_configs, run_coherence, run_directory,
+        run_mixed, run_partitioned, run_sampled, run_scenarios, run_series, run_translated,
+        sampling_modes, sector_configs, set_associative, sharer_tracking_configs,
+        skewed_associative, synthetic_suite, translation_configs, victim_cache_configs,
+        victim_policy_configs,
+    },
+    index::IndexFunction,
+    measurement::Measurement,
+    mix::MixPolicy,
+    partition::{PartitionPolicy, even_quotas, format_quotas},
+    sampling::SamplingMode,
+    series::TimeSeries,
+    synthetic::{Pattern, PatternError, SyntheticConfig},
+    trace::{TraceError, TraceFile},
+    translation::{FrameAllocation, PageMapping, PageSize, TranslationConfig, page_colors},
 };
 use std::{
-    fs,
+    env, fs, io,
     path::{Path, PathBuf},
+    process,
 };
-use trace::TraceFile;
+
+const USAGE: &str = "\
+usage: LRU-Cache-Simulator [command]
+
+With no command, runs every experiment on the traces in trace/.
+
+commands:
+  generate <pattern> [--accesses N] [--seed N] [--size BYTES] [--writes FRACTION]
+           [--base ADDRESS] [--out PATH]
+      Writes a synthetic trace to PATH, or to stdout. Patterns: sequential,
+      stride:256, uniform:1M, zipf:1M:0.9, chase:4096:64, loop:320K and
+      mixtures such as mix:3*zipf:1M:0.9+1*sequential.
+  analyze [--block BYTES] [--window N] [--region-bits N] [--top N] [TRACE...]
+      Characterises each trace (default: every trace in trace/) without
+      simulating: read/write mix, footprints, reuse distances, strides,
+      address regions and working sets.
+  series [--interval N] [--ways N] [--victim N] [--predict mru|multi] [--warmup N]
+         [--out PATH] [TRACE...]
+      Simulates the default cache, adjusted by the options, on each trace
+      (default: every trace in trace/) and writes CSV stats for every N
+      accesses (default 10000) to PATH, or to stdout.";
 
 fn main() {
-    run_experiments();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => run_experiments(),
+        Some("generate") => generate(&args[1..]),
+        Some("analyze") => analyze(&args[1..]),
+        Some("series") => series(&args[1..]),
+        Some("help" | "--help" | "-h") => println!("{USAGE}"),
+        Some(other) => fail(&format!("unknown command `{other}`")),
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {message}\n\n{USAGE}");
+    process::exit(2);
+}
+
+fn generate(args: &[String]) {
+    let Some((pattern, options)) = args.split_first() else {
+        fail("generate needs a pattern");
+    };
+    let pattern: Pattern = pattern
+        .parse()
+        .unwrap_or_else(|err: PatternError| fail(&err.to_string()));
+    let mut config = SyntheticConfig::new(pattern);
+    let mut out = None;
+    for pair in options.chunks(2) {
+        let [flag, value] = pair else {
+            fail(&format!("{} needs a value", pair[0]));
+        };
+        let number = || {
+            parse_number(value).unwrap_or_else(|| fail(&format!("bad number `{value}` for {flag}")))
+        };
+        match flag.as_str() {
+            "--accesses" => config.accesses = number() as usize,
+            "--seed" => config.seed = number(),
+            "--size" => config.access_size = number(),
+            "--base" => config.base_address = number(),
+            "--writes" => {
+                config.write_ratio = value
+                    .parse()
+                    .unwrap_or_else(|_| fail(&format!("bad fraction `{value}` for --writes")))
+            }
+            "--out" => out = Some(PathBuf::from(value)),
+            _ => fail(&format!("unknown option `{flag}`")),
+        }
+    }
+    let trace = config
+        .generate("synthetic")
+        .unwrap_or_else(|err| fail(&err.to_string()));
+    let written = match out {
+        Some(path) => {
+            fs::File::create(&path).and_then(|file| trace.write(io::BufWriter::new(file)))
+        }
+        None => trace.write(io::BufWriter::new(io::stdout().lock())),
+    };
+    if let Err(err) = written {
+        eprintln!("error: failed to write trace: {err}");
+        process::exit(1);
+    }
+}
+
+fn analyze(args: &[String]) {
+    let mut config = CharacterizeConfig::default();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            paths.push(PathBuf::from(arg));
+            continue;
+        }
+        let value = args
+            .next()
+            .and_then(|value| parse_number(value))
+            .unwrap_or_else(|| fail(&format!("{arg} needs a number")));
+        match arg.as_str() {
+            "--block" => config.block_size = value,
+            "--window" => config.window = value as usize,
+            "--region-bits" => config.region_bits = value.min(u32::MAX as u64) as u32,
+            "--top" => config.top = value as usize,
+            _ => fail(&format!("unknown option `{arg}`")),
+        }
+    }
+    if paths.is_empty() {
+        paths = default_trace_paths();
+    }
+    let traces = load_traces(&paths).unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        process::exit(1);
+    });
+    let results =
+        characterize_traces(&traces, &config).unwrap_or_else(|err| fail(&err.to_string()));
+    print_characterization_section(
+        &format!("Trace Characterisation ({}B blocks)", config.block_size),
+        &results,
+    );
+}
+
+fn series(args: &[String]) {
+    let mut config = CacheConfig::default();
+    let mut measurement = Measurement::default();
+    let mut interval = 10_000;
+    let mut out = None;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            paths.push(PathBuf::from(arg));
+            continue;
+        }
+        let Some(value) = args.next() else {
+            fail(&format!("{arg} needs a value"));
+        };
+        let number = || {
+            parse_number(value).unwrap_or_else(|| fail(&format!("bad number `{value}` for {arg}")))
+        };
+        match arg.as_str() {
+            "--interval" => interval = number() as usize,
+            "--ways" => config.associativity = number() as usize,
+            "--victim" => config.victim_cache_entries = number() as usize,
+            "--warmup" => measurement = Measurement::warmup(number() as usize),
+            "--predict" => {
+                config.prediction = match value.as_str() {
+                    "mru" => PredictionStrategy::Mru,
+                    "multi" => PredictionStrategy::MultiColumn,
+                    _ => fail(&format!("unknown predictor `{value}`")),
+                }
+            }
+            "--out" => out = Some(PathBuf::from(value)),
+            _ => fail(&format!("unknown option `{arg}`")),
+        }
+    }
+    if paths.is_empty() {
+        paths = default_trace_paths();
+    }
+    let traces = load_traces(&paths).unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        process::exit(1);
+    });
+    let scenario =
+        ScenarioConfig::new("series", config).unwrap_or_else(|err| fail(&err.to_string()));
+    let results = run_series(&traces, &[scenario], &measurement, interval)
+        .unwrap_or_else(|err| fail(&err.to_string()));
+    let write = |mut writer: Box<dyn io::Write>| -> io::Result<()> {
+        TimeSeries::write_csv_header(&mut writer)?;
+        for trace in &results[0].trace_results {
+            trace.series.write_csv(&trace.trace_name, &mut writer)?;
+        }
+        writer.flush()
+    };
+    let written = match out {
+        Some(path) => {
+            fs::File::create(&path).and_then(|file| write(Box::new(io::BufWriter::new(file))))
+        }
+        None => write(Box::new(io::BufWriter::new(io::stdout().lock()))),
+    };
+    if let Err(err) = written {
+        eprintln!("error: failed to write series: {err}");
+        process::exit(1);
+    }
+}
+
+/// Decimal, or hexadecimal with a `0x` prefix.
+fn parse_number(token: &str) -> Option<u64> {
+    match token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => token.parse().ok(),
+    }
 }
 
 fn run_experiments() {
     let trace_paths = default_trace_paths();
-    let traces = load_traces(&trace_paths);
+    let traces = match load_traces(&trace_paths) {
+        Ok(traces) => traces,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
 
     let base_cfg = CacheConfig::default();
+    // Every experiment counts the accesses this selects; a warm-up drops cold-start misses
+    let measurement = Measurement::default();
 
     println!("Loaded {} trace files.", traces.len());
 
     // Experiment 1: Direct-Mapped
-    let dm = run_scenarios(&traces, &[direct_mapped(&base_cfg)]);
+    let dm_scenario = direct_mapped(&base_cfg).expect("direct-mapped config");
+    let dm = run_scenarios(&traces, &[dm_scenario], &measurement).expect("direct-mapped run");
     print_section("Direct-Mapped", &dm);
 
     // Experiment 2: Set-Associative for multiple ways
-    let sa_configs = set_associative(&base_cfg, &[2, 4, 8, 16]);
-    let sa_results = run_scenarios(&traces, &sa_configs);
+    let sa_configs = set_associative(&base_cfg, &[2, 4, 8, 16]).expect("set-associative configs");
+    let sa_results =
+        run_scenarios(&traces, &sa_configs, &measurement).expect("set-associative run");
     print_section("Set-Associative Sweep", &sa_results);
 
     // Experiment 3: Block size sweep (4-way)
@@ -39,8 +258,10 @@ fn run_experiments() {
         cfg.associativity = 4;
         cfg
     };
-    let block_scenarios = block_sizes(&block_cfg, &[8, 16, 32, 64, 128, 256]);
-    let block_results = run_scenarios(&traces, &block_scenarios);
+    let block_scenarios =
+        block_sizes(&block_cfg, &[8, 16, 32, 64, 128, 256]).expect("block size configs");
+    let block_results =
+        run_scenarios(&traces, &block_scenarios, &measurement).expect("block size run");
     print_section("Block Size Sweep (4-way)", &block_results);
 
     // Experiment 4: Victim cache sizes on DM cache
@@ -49,25 +270,309 @@ fn run_experiments() {
         cfg.associativity = 1;
         cfg
     };
-    let victim_scenarios = victim_cache_configs(&victim_base, &[4, 8, 16, 32]);
-    let victim_results = run_scenarios(&traces, &victim_scenarios);
+    let victim_scenarios =
+        victim_cache_configs(&victim_base, &[4, 8, 16, 32]).expect("victim cache configs");
+    let victim_results =
+        run_scenarios(&traces, &victim_scenarios, &measurement).expect("victim cache run");
     print_section("Victim Cache on DM", &victim_results);
 
     // Experiment 5: MRU prediction
-    let mru_scenarios = predictor_configs(&base_cfg, &[2, 4, 8, 16], PredictionStrategy::Mru);
-    let mru_results = run_scenarios(&traces, &mru_scenarios);
+    let mru_scenarios =
+        predictor_configs(&base_cfg, &[2, 4, 8, 16], PredictionStrategy::Mru).expect("MRU configs");
+    let mru_results = run_scenarios(&traces, &mru_scenarios, &measurement).expect("MRU run");
     print_section("MRU Prediction", &mru_results);
 
     // Experiment 6: Multi-column prediction
     let mc_scenarios =
-        predictor_configs(&base_cfg, &[2, 4, 8, 16], PredictionStrategy::MultiColumn);
-    let mc_results = run_scenarios(&traces, &mc_scenarios);
+        predictor_configs(&base_cfg, &[2, 4, 8, 16], PredictionStrategy::MultiColumn)
+            .expect("multi-column configs");
+    let mc_results = run_scenarios(&traces, &mc_scenarios, &measurement).expect("multi-column run");
     print_section("Multi-column Prediction", &mc_results);
+
+    // Experiment 7: Victim buffers on set-associative caches, and victim policies
+    let sa_victim_base = {
+        let mut cfg = base_cfg.clone();
+        cfg.associativity = 2;
+        cfg
+    };
+    let sa_victim_scenarios =
+        victim_cache_configs(&sa_victim_base, &[8, 32]).expect("set-associative victim configs");
+    let sa_victim_results = run_scenarios(&traces, &sa_victim_scenarios, &measurement)
+        .expect("set-associative victim run");
+    print_section("Victim Cache on 2-way", &sa_victim_results);
+
+    let policy_base = {
+        let mut cfg = victim_base.clone();
+        cfg.victim_cache_entries = 16;
+        cfg
+    };
+    let policy_scenarios = victim_policy_configs(
+        &policy_base,
+        &[
+            (VictimPolicy::Lru, VictimFilter::All, VictimHitAction::Swap),
+            (VictimPolicy::Lru, VictimFilter::All, VictimHitAction::Copy),
+            (VictimPolicy::Fifo, VictimFilter::All, VictimHitAction::Copy),
+            (
+                VictimPolicy::Lru,
+                VictimFilter::DirtyOnly,
+                VictimHitAction::Swap,
+            ),
+            (
+                VictimPolicy::Lru,
+                VictimFilter::UnreferencedOnly,
+                VictimHitAction::Swap,
+            ),
+        ],
+    )
+    .expect("victim policy configs");
+    let policy_results =
+        run_scenarios(&traces, &policy_scenarios, &measurement).expect("victim policy run");
+    print_section("Victim Policies on DM", &policy_results);
+
+    // Experiment 8: Skewed-associative caches
+    let skewed_scenarios =
+        skewed_associative(&base_cfg, &[2, 4, 8]).expect("skewed-associative configs");
+    let skewed_results =
+        run_scenarios(&traces, &skewed_scenarios, &measurement).expect("skewed-associative run");
+    print_section("Skewed-Associative Sweep", &skewed_results);
+
+    // Experiment 9: Set-index functions on the DM cache
+    let index_scenarios = index_functions(
+        &victim_base,
+        &[
+            IndexFunction::Modulo,
+            IndexFunction::XorFold,
+            IndexFunction::PrimeModulo,
+            // Low 7 bits plus bits 13..19, skipping the middle of the address.
+            IndexFunction::BitSelect((0..7).chain(13..19).collect()),
+        ],
+    )
+    .expect("index function configs");
+    let index_results =
+        run_scenarios(&traces, &index_scenarios, &measurement).expect("index function run");
+    print_section("Index Functions on DM", &index_results);
+
+    // Experiment 10: Hash-rehash and column-associative caches
+    let rehash_scenarios = rehash_configs(&victim_base).expect("rehash configs");
+    let rehash_results =
+        run_scenarios(&traces, &rehash_scenarios, &measurement).expect("rehash run");
+    print_section("Column-Associative vs Hash-Rehash", &rehash_results);
+
+    // Experiment 11: Sectored 256B blocks (4-way)
+    let sector_base = {
+        let mut cfg = block_cfg.clone();
+        cfg.block_size = 256;
+        cfg
+    };
+    let sector_scenarios = sector_configs(&sector_base, &[1, 2, 4, 8]).expect("sector configs");
+    let sector_results =
+        run_scenarios(&traces, &sector_scenarios, &measurement).expect("sector run");
+    print_section("Sectored Blocks (4-way)", &sector_results);
+
+    // Experiment 12: Next-level traffic for the block size sweep (4-way)
+    let prefetch_scenarios =
+        prefetch_block_sizes(&block_cfg, &[16, 64, 256]).expect("prefetch configs");
+    let prefetch_results =
+        run_scenarios(&traces, &prefetch_scenarios, &measurement).expect("prefetch run");
+    print_traffic_section("Block Size Traffic (4-way)", &block_results);
+    print_traffic_section("Next-Line Prefetch Traffic (4-way)", &prefetch_results);
+
+    // Experiment 13: Snooping coherence across 4 private caches
+    let coherence_scenarios = coherence_protocols(
+        &base_cfg,
+        4,
+        &[CoherenceProtocol::Msi, CoherenceProtocol::Mesi],
+    )
+    .expect("coherence configs");
+    let coherence_results =
+        run_coherence(&traces, &coherence_scenarios, &measurement).expect("coherence run");
+    print_coherence_section("Snooping Coherence (4 cores)", &coherence_results);
+
+    // Experiment 14: MOESI directory at a shared 1MiB LLC behind 4 private 32KiB caches
+    let private_cfg = {
+        let mut cfg = base_cfg.clone();
+        cfg.cache_size = 32 * 1024;
+        cfg
+    };
+    let llc_cfg = {
+        let mut cfg = base_cfg.clone();
+        cfg.cache_size = 1024 * 1024;
+        cfg.associativity = 8;
+        cfg
+    };
+    let directory_scenarios = sharer_tracking_configs(
+        &private_cfg,
+        &llc_cfg,
+        4,
+        &[SharerTracking::FullMap, SharerTracking::LimitedPointer(2)],
+    )
+    .expect("directory configs");
+    let directory_results =
+        run_directory(&traces, &directory_scenarios, &measurement).expect("directory run");
+    print_directory_section(
+        "Directory Coherence (4 cores, shared LLC)",
+        &directory_results,
+    );
+
+    // Experiment 15: True and false sharing between threads at 32B blocks, with an
+    // idealised unbounded cache per thread rather than the coherence systems
+    let sharing_results = analyze_sharing(&traces, base_cfg.block_size, 5);
+    print_sharing_section("Sharing Analysis (32B blocks)", &sharing_results);
+
+    // Experiment 16: Virtual-to-physical translation in front of the default cache
+    let translation_scenarios = translation_configs(
+        &base_cfg,
+        &TranslationConfig::default(),
+        &[
+            (PageSize::Base4K, FrameAllocation::Random),
+            (
+                PageSize::Base4K,
+                FrameAllocation::PageColored {
+                    colors: page_colors(&base_cfg, PageSize::Base4K),
+                },
+            ),
+            (PageSize::Huge2M, FrameAllocation::Random),
+        ],
+    )
+    .expect("translation configs");
+    let translation_results =
+        run_translated(&traces, &translation_scenarios, &measurement).expect("translation run");
+    print_translation_section("Address Translation (4-way)", &translation_results);
+
+    // Experiment 17: OS page placement under the physically indexed DM cache. Other processes
+    // hold half of memory, so the lowest free frames skip colors and sequential allocation no
+    // longer hops bins the way bin hopping does
+    let dm_colors = page_colors(&victim_base, PageSize::Base4K);
+    let fragmented = PageMapping {
+        occupied: 0.5,
+        ..PageMapping::default()
+    };
+    let mut placement_scenarios = vec![direct_mapped(&base_cfg).expect("direct-mapped config")];
+    placement_scenarios.extend(
+        page_mapping_configs(
+            &victim_base,
+            &fragmented,
+            &[
+                FrameAllocation::Sequential,
+                FrameAllocation::Random,
+                FrameAllocation::BinHopping { colors: dm_colors },
+                FrameAllocation::PageColored { colors: dm_colors },
+            ],
+        )
+        .expect("page mapping configs"),
+    );
+    let placement_results =
+        run_scenarios(&traces, &placement_scenarios, &measurement).expect("page mapping run");
+    print_section(
+        "Page Placement on DM (half of memory in use)",
+        &placement_results,
+    );
+
+    // Experiment 18: All traces co-running on one 16-way cache, shared or way-partitioned
+    let partition_cfg = {
+        let mut cfg = base_cfg.clone();
+        cfg.associativity = 16;
+        cfg
+    };
+    // The static split is the even one UCP starts from, so UCP's gain is its repartitioning
+    let even_split = even_quotas(partition_cfg.associativity, traces.len());
+    let partition_results = partition_policies(
+        &partition_cfg,
+        traces.len(),
+        &[
+            PartitionPolicy::Shared,
+            PartitionPolicy::Static(even_split),
+            PartitionPolicy::Ucp { interval: 20_000 },
+        ],
+    )
+    .and_then(|scenarios| run_partitioned(&traces, &scenarios, &measurement));
+    match partition_results {
+        Ok(results) => print_partition_section("Shared Cache Partitioning (16-way)", &results),
+        Err(err) => eprintln!("skipping shared cache partitioning: {err}"),
+    }
+
+    // Experiment 19: All traces interleaved into the default cache, evenly and with the first
+    // trace issuing 3 accesses for every 1 from each of the others
+    let weights = (0..traces.len())
+        .map(|source| if source == 0 { 3 } else { 1 })
+        .collect();
+    let mix_results = mix_policies(
+        &base_cfg,
+        traces.len(),
+        &[MixPolicy::RoundRobin, MixPolicy::Weighted(weights)],
+    )
+    .and_then(|scenarios| run_mixed(&traces, &scenarios, &measurement));
+    match mix_results {
+        Ok(results) => print_mix_section("Workload Mixes (4-way)", &results),
+        Err(err) => eprintln!("skipping workload mixes: {err}"),
+    }
+
+    // Experiment 20: Synthetic access patterns sized against the default cache
+    let synthetic = synthetic_suite(&base_cfg, 200_000, 1).expect("synthetic traces");
+    let synthetic_configs =
+        set_associative(&base_cfg, &[1, 4, 16]).expect("set-associative configs");
+    let synthetic_results =
+        run_scenarios(&synthetic, &synthetic_configs, &measurement).expect("synthetic run");
+    print_section("Synthetic Patterns", &synthetic_results);
+
+    // Experiment 21: Hit rates of the default cache estimated from trace samples
+    let sampling_scenarios = sampling_modes(
+        &base_cfg,
+        &[
+            SamplingMode::Periodic {
+                period: 50_000,
+                sample: 5_000,
+                warmup: 10_000,
+            },
+            SamplingMode::Random {
+                intervals: 4,
+                sample: 5_000,
+                warmup: 10_000,
+                seed: 1,
+            },
+            SamplingMode::Phases {
+                interval: 5_000,
+                clusters: 4,
+                warmup: 10_000,
+                seed: 1,
+            },
+        ],
+    )
+    .expect("sampling configs");
+    // Traces shorter than a sample cannot be sampled; report and move on.
+    match run_sampled(&traces, &sampling_scenarios) {
+        Ok(results) => print_sampling_section("Sampled Simulation (4-way)", &results),
+        Err(err) => eprintln!("skipping sampled simulation: {err}"),
+    }
+
+    // Experiment 22: The set-associative sweep with cold-start misses excluded
+    let warmup_configs = set_associative(&base_cfg, &[1, 4, 16]).expect("set-associative configs");
+    let mut warmup_results = Vec::new();
+    for warmup in [
+        Measurement::default(),
+        Measurement::warmup_fraction(0.1),
+        Measurement::warmup_fraction(0.5),
+    ] {
+        let results = run_scenarios(&traces, &warmup_configs, &warmup).expect("warm-up run");
+        warmup_results.extend(results.into_iter().map(|mut result| {
+            result.label = format!("{}, {warmup}", result.label);
+            result
+        }));
+    }
+    print_section("Warm-up", &warmup_results);
+
+    // Experiment 23: Hit rate of the 4-way MRU-predicted cache over every 10000 accesses
+    let series_scenarios =
+        predictor_configs(&base_cfg, &[4], PredictionStrategy::Mru).expect("series configs");
+    let series_results =
+        run_series(&traces, &series_scenarios, &measurement, 10_000).expect("series run");
+    print_series_section("Phase Behaviour (10000-access intervals)", &series_results);
 }
 
 fn print_section(title: &str, results: &[ScenarioResult]) {
     // Format the expriments result
     println!("\n== {title} ==");
+    let energy_model = EnergyModel::default();
     for scenario in results {
         println!("  {}", scenario.label);
         for trace in &scenario.trace_results {
@@ -84,6 +589,14 @@ fn print_section(title: &str, results: &[ScenarioResult]) {
                     stats.victim_hit_ratio() * 100.0
                 ));
             }
+            if stats.victim_fills > 0 {
+                line.push_str(&format!(
+                    " contrib {:>5.2}% fills {} swaps {}",
+                    stats.victim_hit_contribution() * 100.0,
+                    stats.victim_fills,
+                    stats.victim_swaps
+                ));
+            }
             if let Some(pred) = &stats.prediction {
                 line.push_str(&format!(
                     " first {:>6.2}% non-first {:>6.2}%",
@@ -93,18 +606,360 @@ fn print_section(title: &str, results: &[ScenarioResult]) {
                 if matches!(pred.mode, PredictionStrategy::MultiColumn) {
                     line.push_str(&format!(" avg-search {:.2}", pred.avg_bit_vector_search()));
                 }
+                let energy = energy_model.estimate(&scenario.config, stats);
+                let baseline = energy_model.parallel_baseline(&scenario.config, stats);
+                line.push_str(&format!(
+                    " energy {:.1}pJ vs parallel {:.1}pJ",
+                    energy.dynamic_per_access(),
+                    baseline.dynamic_per_access()
+                ));
+            }
+            if let Some(sectors) = &stats.sectors {
+                line.push_str(&format!(
+                    " tag-miss {} sector-miss {} fetched {}KiB",
+                    sectors.tag_misses,
+                    sectors.sector_misses,
+                    stats.traffic.fill_bytes / 1024
+                ));
+            }
+            if let Some(rehash) = &stats.rehash {
+                line.push_str(&format!(
+                    " first-probe {} second-probe {} ({:.1}% of hits) swaps {}",
+                    rehash.first_probe_hits,
+                    rehash.second_probe_hits,
+                    rehash.second_probe_share() * 100.0,
+                    rehash.swaps
+                ));
+            }
+            println!("{line}");
+        }
+    }
+}
+
+fn print_traffic_section(title: &str, results: &[ScenarioResult]) {
+    println!("\n== {title} ==");
+    for scenario in results {
+        println!("  {}", scenario.label);
+        for trace in &scenario.trace_results {
+            let stats = &trace.stats;
+            let traffic = &stats.traffic;
+            let mut line = format!(
+                "    {:<14} hit {:>6.2}% fill {:>6}KiB wb {:>5}KiB",
+                trace.trace_name,
+                stats.hit_rate() * 100.0,
+                traffic.fill_bytes / 1024,
+                traffic.writeback_bytes / 1024
+            );
+            if traffic.prefetches > 0 {
+                line.push_str(&format!(
+                    " prefetch {:>6}KiB",
+                    traffic.prefetch_bytes / 1024
+                ));
+            }
+            line.push_str(&format!(
+                " {:>6.2} B/access ratio {:>5.2}",
+                stats.traffic_per_access(),
+                stats.traffic_ratio()
+            ));
+            println!("{line}");
+        }
+    }
+}
+
+fn print_coherence_section(title: &str, results: &[CoherenceResult]) {
+    println!("\n== {title} ==");
+    for scenario in results {
+        println!("  {}", scenario.label);
+        for trace in &scenario.trace_results {
+            println!("    {}", trace.trace_name);
+            // Cores that no thread mapped to have nothing to report.
+            for (core, stats) in trace.cores.iter().enumerate() {
+                if stats.cache.accesses == 0 {
+                    continue;
+                }
+                let coherence = &stats.coherence;
+                println!(
+                    "      core {core:<2} hit {:>6.2}% BusRd {} BusRdX {} BusUpgr {} \
+                     inval-in {} inval-out {} interventions {} writebacks {} coherence-miss {}",
+                    stats.cache.hit_rate() * 100.0,
+                    coherence.bus_reads,
+                    coherence.bus_read_exclusives,
+                    coherence.bus_upgrades,
+                    coherence.invalidations_received,
+                    coherence.invalidations_sent,
+                    coherence.interventions,
+                    coherence.writebacks,
+                    coherence.coherence_misses
+                );
+            }
+        }
+    }
+}
+
+fn print_directory_section(title: &str, results: &[DirectoryResult]) {
+    println!("\n== {title} ==");
+    for scenario in results {
+        println!("  {}", scenario.label);
+        for trace in &scenario.trace_results {
+            let stats = &trace.stats;
+            let directory = &stats.directory;
+            let private_hits: u64 = stats.cores.iter().map(|core| core.cache.hits).sum();
+            let accesses: u64 = stats.cores.iter().map(|core| core.cache.accesses).sum();
+            println!(
+                "    {:<14} private hit {:>6.2}% llc hit {:>6.2}% 3-hop {:>5.1}% \
+                 inval {} fan-out {:.2} broadcasts {} dir-evict {} back-inval {}",
+                trace.trace_name,
+                private_hits as f64 / accesses.max(1) as f64 * 100.0,
+                stats.llc.hit_rate() * 100.0,
+                directory.three_hop_share() * 100.0,
+                directory.invalidations,
+                directory.avg_fan_out(),
+                directory.broadcasts,
+                directory.directory_evictions,
+                directory.back_invalidations
+            );
+        }
+    }
+}
+
+fn print_sharing_section(title: &str, results: &[SharingResult]) {
+    println!("\n== {title} ==");
+    for result in results {
+        let report = &result.report;
+        println!(
+            "  {:<14} threads {} shared-blocks {} invalidations {} true {} false {} ({:.1}% false)",
+            result.trace_name,
+            report.threads,
+            report.shared_blocks,
+            report.invalidations,
+            report.true_sharing,
+            report.false_sharing,
+            report.false_sharing_ratio() * 100.0
+        );
+        for block in &report.top_blocks {
+            let threads: Vec<String> = block
+                .threads
+                .iter()
+                .map(|(thread, ranges)| {
+                    let ranges: Vec<String> = ranges
+                        .iter()
+                        .map(|range| format!("{}..{}", range.start, range.end))
+                        .collect();
+                    format!("t{thread}[{}]", ranges.join(","))
+                })
+                .collect();
+            println!(
+                "    block {:#x} false {} true {} {}",
+                block.block_address,
+                block.false_sharing,
+                block.true_sharing,
+                threads.join(" ")
+            );
+        }
+    }
+}
+
+fn print_partition_section(title: &str, results: &[PartitionResult]) {
+    const MISS_PENALTY: f64 = 100.0;
+    println!("\n== {title} ==");
+    for scenario in results {
+        print!(
+            "  {:<24} weighted speedup {:.3} ({MISS_PENALTY} cycle misses)",
+            scenario.label,
+            scenario.weighted_speedup(MISS_PENALTY)
+        );
+        if let Some(last) = scenario.history.last() {
+            print!(
+                " repartitions {} final {}",
+                scenario.history.len() - 1,
+                format_quotas(&last.quotas)
+            );
+        }
+        println!();
+        for source in &scenario.sources {
+            println!(
+                "    {:<14} hit {:>6.2}% alone {:>6.2}% speedup {:.3}",
+                source.trace_name,
+                source.shared.hit_rate() * 100.0,
+                source.alone.hit_rate() * 100.0,
+                source.speedup(MISS_PENALTY)
+            );
+        }
+    }
+}
+
+fn print_mix_section(title: &str, results: &[MixResult]) {
+    const MISS_PENALTY: f64 = 100.0;
+    println!("\n== {title} ==");
+    for scenario in results {
+        println!(
+            "  {:<24} hit {:>6.2}% weighted speedup {:.3} ({MISS_PENALTY} cycle misses)",
+            scenario.label,
+            scenario.aggregate.hit_rate() * 100.0,
+            scenario.weighted_speedup(MISS_PENALTY)
+        );
+        for source in &scenario.sources {
+            println!(
+                "    {:<14} hit {:>6.2}% alone {:>6.2}% speedup {:.3}",
+                source.trace_name,
+                source.shared.hit_rate() * 100.0,
+                source.alone.hit_rate() * 100.0,
+                source.speedup(MISS_PENALTY)
+            );
+        }
+    }
+}
+
+fn print_sampling_section(title: &str, results: &[SamplingResult]) {
+    println!("\n== {title} ==");
+    for scenario in results {
+        println!("  {}", scenario.label);
+        for trace in &scenario.trace_results {
+            let estimate = &trace.estimate;
+            let bound = match estimate.error_bound {
+                Some(bound) => format!("±{:.2}%", bound * 100.0),
+                None => "±?".to_string(),
+            };
+            println!(
+                "    {:<14} est {:>6.2}% {:<8} (cold bias {:>5.2}%) full {:>6.2}% error {:>+6.2}% slices {:>3} simulated {:>5.1}%",
+                trace.trace_name,
+                estimate.hit_rate * 100.0,
+                bound,
+                estimate.cold_start_bias * 100.0,
+                trace.full.hit_rate() * 100.0,
+                trace.error() * 100.0,
+                estimate.slices.len(),
+                estimate.simulated_fraction() * 100.0
+            );
+        }
+    }
+}
+
+fn print_series_section(title: &str, results: &[SeriesResult]) {
+    println!("\n== {title} ==");
+    for scenario in results {
+        println!("  {}", scenario.label);
+        for trace in &scenario.trace_results {
+            let rates = trace.series.hit_rates();
+            let lowest = rates.iter().copied().reduce(f64::min).unwrap_or(0.0);
+            let highest = rates.iter().copied().reduce(f64::max).unwrap_or(0.0);
+            let mut line = format!(
+                "    {:<14} hit {:>6.2}% intervals {:>3} min {:>6.2}% max {:>6.2}%",
+                trace.trace_name,
+                trace.stats.hit_rate() * 100.0,
+                rates.len(),
+                lowest * 100.0,
+                highest * 100.0
+            );
+            if let Some((start, shift)) = trace.series.largest_shift() {
+                line.push_str(&format!(
+                    " largest shift {:>+6.2}% at {start}",
+                    shift * 100.0
+                ));
             }
             println!("{line}");
         }
     }
 }
 
-fn load_traces(paths: &[PathBuf]) -> Vec<TraceFile> {
-    let mut traces = Vec::new();
-    for path in paths {
-        traces.push(TraceFile::load(path));
+fn print_characterization_section(title: &str, results: &[CharacterizationResult]) {
+    println!("\n== {title} ==");
+    for result in results {
+        let report = &result.report;
+        let share = |count: u64| {
+            if report.accesses == 0 {
+                0.0
+            } else {
+                count as f64 * 100.0 / report.accesses as f64
+            }
+        };
+        println!(
+            "  {:<14} accesses {} reads {} writes {} ({:.1}% writes)",
+            result.trace_name,
+            report.accesses,
+            report.reads,
+            report.writes,
+            report.write_ratio() * 100.0
+        );
+        let footprints: Vec<String> = report
+            .footprints
+            .iter()
+            .map(|footprint| {
+                format!(
+                    "{}B:{} ({}KiB)",
+                    footprint.granularity,
+                    footprint.unique,
+                    footprint.bytes() / 1024
+                )
+            })
+            .collect();
+        println!("    footprint {}", footprints.join(" "));
+        let reuse: Vec<String> = [1, 16, 256, 4096, 65536]
+            .iter()
+            .map(|&blocks| {
+                format!(
+                    "{blocks}:{:.1}%",
+                    report.reuse.hit_rate_within(blocks) * 100.0
+                )
+            })
+            .collect();
+        println!(
+            "    LRU hit rate by blocks {} cold {:.1}%",
+            reuse.join(" "),
+            share(report.reuse.cold)
+        );
+        let strides: Vec<String> = report
+            .top_strides
+            .iter()
+            .map(|(stride, count)| format!("{stride:+}:{:.1}%", share(*count)))
+            .collect();
+        println!(
+            "    strides {} ({} distinct)",
+            strides.join(" "),
+            report.distinct_strides
+        );
+        let regions: Vec<String> = report
+            .top_regions
+            .iter()
+            .map(|region| {
+                format!(
+                    "{:#x}:{:.1}% ({} blocks)",
+                    region.base_address,
+                    share(region.accesses),
+                    region.unique_blocks
+                )
+            })
+            .collect();
+        println!("    regions {} of {}", regions.join(" "), report.regions);
+        let (mean, largest) = report.working_set_summary();
+        println!(
+            "    working set per {} accesses: mean {:.1} max {} blocks",
+            report.window, mean, largest
+        );
+    }
+}
+
+fn print_translation_section(title: &str, results: &[TranslatedResult]) {
+    println!("\n== {title} ==");
+    for scenario in results {
+        println!("  {}", scenario.label);
+        for trace in &scenario.trace_results {
+            let tlb = &trace.translation;
+            println!(
+                "    {:<14} hit {:>6.2}% L1-TLB {:>6.2}% L2-TLB {:>6.2}% walks/1k {:>6.2} pages {}",
+                trace.trace_name,
+                trace.cache.hit_rate() * 100.0,
+                tlb.l1_hit_rate() * 100.0,
+                tlb.l2_hit_rate() * 100.0,
+                tlb.walks_per_kilo(),
+                tlb.pages_allocated
+            );
+        }
     }
-    traces
+}
+
+fn load_traces(paths: &[PathBuf]) -> Result<Vec<TraceFile>, TraceError> {
+    paths.iter().map(TraceFile::load).collect()
 }
 
 fn default_trace_paths() -> Vec<PathBuf> {
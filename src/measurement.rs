@@ -0,0 +1,144 @@
+use std::{fmt, ops::Range};
+
+/// Leading accesses that update cache state without being counted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Warmup {
+    Accesses(usize),
+    /// A share of the run, from 0 to 1.
+    Fraction(f64),
+}
+
+impl Default for Warmup {
+    fn default() -> Self {
+        Warmup::Accesses(0)
+    }
+}
+
+/// Reasons a [`Measurement`] is rejected by [`Measurement::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeasurementError {
+    /// A warm-up fraction outside 0 to 1.
+    WarmupFractionOutOfRange,
+    /// A measurement window with no positions in it.
+    EmptyWindow,
+}
+
+impl fmt::Display for MeasurementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeasurementError::WarmupFractionOutOfRange => {
+                write!(f, "the warm-up fraction must be between 0 and 1")
+            }
+            MeasurementError::EmptyWindow => write!(f, "measurement windows must be non-empty"),
+        }
+    }
+}
+
+impl std::error::Error for MeasurementError {}
+
+/// Which accesses of a run count towards its stats. Positions are indices
+/// into the access stream being replayed, mixed streams included.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Measurement {
+    pub warmup: Warmup,
+    /// Ranges of positions to count; empty counts everything after the
+    /// warm-up. Positions inside the warm-up are never counted.
+    pub windows: Vec<Range<usize>>,
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.warmup {
+            Warmup::Accesses(0) => write!(f, "no warm-up")?,
+            Warmup::Accesses(accesses) => write!(f, "{accesses}-access warm-up")?,
+            Warmup::Fraction(fraction) => write!(f, "{:.0}% warm-up", fraction * 100.0)?,
+        }
+        match self.windows.len() {
+            0 => Ok(()),
+            1 => write!(f, ", 1 window"),
+            windows => write!(f, ", {windows} windows"),
+        }
+    }
+}
+
+impl Measurement {
+    /// Counts everything after the first `accesses`.
+    pub fn warmup(accesses: usize) -> Self {
+        Self {
+            warmup: Warmup::Accesses(accesses),
+            ..Self::default()
+        }
+    }
+
+    /// Counts everything after the first `fraction` of the run.
+    pub fn warmup_fraction(fraction: f64) -> Self {
+        Self {
+            warmup: Warmup::Fraction(fraction),
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), MeasurementError> {
+        if let Warmup::Fraction(fraction) = self.warmup
+            && !(0.0..=1.0).contains(&fraction)
+        {
+            return Err(MeasurementError::WarmupFractionOutOfRange);
+        }
+        if self.windows.iter().any(Range::is_empty) {
+            return Err(MeasurementError::EmptyWindow);
+        }
+        Ok(())
+    }
+
+    /// Warm-up length of a `len`-access run.
+    pub fn warmup_accesses(&self, len: usize) -> usize {
+        match self.warmup {
+            Warmup::Accesses(accesses) => accesses.min(len),
+            Warmup::Fraction(fraction) => (len as f64 * fraction).round() as usize,
+        }
+    }
+
+    /// Whether the access at `position` of a `len`-access run is counted.
+    pub fn is_measured(&self, position: usize, len: usize) -> bool {
+        position >= self.warmup_accesses(len)
+            && (self.windows.is_empty()
+                || self.windows.iter().any(|window| window.contains(&position)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warmup_and_windows_select_positions() {
+        let measured = |measurement: &Measurement| -> Vec<usize> {
+            (0..10)
+                .filter(|&position| measurement.is_measured(position, 10))
+                .collect()
+        };
+        assert_eq!(measured(&Measurement::warmup(7)), [7, 8, 9]);
+        assert_eq!(measured(&Measurement::warmup_fraction(0.8)), [8, 9]);
+        // Windows are clipped by the warm-up.
+        let windowed = Measurement {
+            warmup: Warmup::Accesses(3),
+            windows: vec![1..5, 8..20],
+        };
+        assert_eq!(measured(&windowed), [3, 4, 8, 9]);
+        assert_eq!(Measurement::warmup(20).warmup_accesses(10), 10);
+    }
+
+    #[test]
+    fn out_of_range_fractions_and_empty_windows_are_rejected() {
+        assert_eq!(
+            Measurement::warmup_fraction(1.5).validate(),
+            Err(MeasurementError::WarmupFractionOutOfRange)
+        );
+        let empty = Measurement {
+            windows: vec![0..4, 4..4],
+            ..Measurement::default()
+        };
+        assert_eq!(empty.validate(), Err(MeasurementError::EmptyWindow));
+        assert_eq!(Measurement::warmup_fraction(1.0).validate(), Ok(()));
+    }
+}
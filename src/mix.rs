@@ -0,0 +1,297 @@
+use crate::trace::TraceAccess;
+use std::{cmp::Reverse, collections::BinaryHeap, fmt};
+
+/// Default address spaces are kept apart by placing the source id above this bit.
+pub const SOURCE_ADDRESS_SHIFT: u32 = 48;
+
+/// Order in which the sources of a mix issue their accesses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MixPolicy {
+    /// One access from each source in turn.
+    RoundRobin,
+    /// `weights[i]` consecutive accesses from source `i` in each turn.
+    Weighted(Vec<u32>),
+    /// Merged by [`TraceAccess::timestamp`], ties going to the lower source.
+    ByTimestamp,
+}
+
+impl fmt::Display for MixPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MixPolicy::RoundRobin => write!(f, "round-robin"),
+            MixPolicy::Weighted(weights) => {
+                let weights: Vec<String> = weights.iter().map(u32::to_string).collect();
+                write!(f, "weighted {}", weights.join(":"))
+            }
+            MixPolicy::ByTimestamp => write!(f, "by timestamp"),
+        }
+    }
+}
+
+/// Reasons a [`MixConfig`] is rejected by [`MixConfig::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MixError {
+    /// A mix needs at least one source.
+    ZeroSources,
+    /// Mix weights must be non-zero, one per source.
+    InvalidWeights { weights: Vec<u32>, sources: usize },
+    /// A mix needs one address offset per source.
+    OffsetCount { offsets: usize, sources: usize },
+}
+
+impl fmt::Display for MixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MixError::ZeroSources => write!(f, "a mix needs at least one source"),
+            MixError::InvalidWeights { weights, sources } => write!(
+                f,
+                "mix weights {weights:?} must be {sources} non-zero values, one per source"
+            ),
+            MixError::OffsetCount { offsets, sources } => write!(
+                f,
+                "{offsets} address offsets given for a mix of {sources} sources"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MixError {}
+
+/// How several traces are interleaved into one access stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixConfig {
+    pub policy: MixPolicy,
+    /// Added to each source's addresses; `None` gives source `i` the offset
+    /// `i << SOURCE_ADDRESS_SHIFT`, so no two sources share a block.
+    pub address_offsets: Option<Vec<u64>>,
+}
+
+impl Default for MixConfig {
+    fn default() -> Self {
+        Self {
+            policy: MixPolicy::RoundRobin,
+            address_offsets: None,
+        }
+    }
+}
+
+impl MixConfig {
+    /// Checks that weights and offsets, when given, cover exactly `sources` traces.
+    pub fn validate(&self, sources: usize) -> Result<(), MixError> {
+        if sources == 0 {
+            return Err(MixError::ZeroSources);
+        }
+        if let MixPolicy::Weighted(weights) = &self.policy
+            && (weights.len() != sources || weights.contains(&0))
+        {
+            return Err(MixError::InvalidWeights {
+                weights: weights.clone(),
+                sources,
+            });
+        }
+        if let Some(offsets) = &self.address_offsets
+            && offsets.len() != sources
+        {
+            return Err(MixError::OffsetCount {
+                offsets: offsets.len(),
+                sources,
+            });
+        }
+        Ok(())
+    }
+
+    /// Address offset applied to `source`.
+    pub fn offset(&self, source: usize) -> u64 {
+        match &self.address_offsets {
+            Some(offsets) => offsets[source],
+            None => (source as u64) << SOURCE_ADDRESS_SHIFT,
+        }
+    }
+}
+
+/// One access of a mix, already moved into its source's address space.
+#[derive(Debug, Clone, Copy)]
+pub struct MixedAccess {
+    /// Index of the trace the access came from.
+    pub source: usize,
+    pub access: TraceAccess,
+}
+
+/// Interleaves `traces` under `config`. Each source contributes every one of
+/// its accesses in its own order; sources that run out drop out of the rotation.
+pub fn mix<'a>(
+    traces: &'a [&'a [TraceAccess]],
+    config: &'a MixConfig,
+) -> Result<impl Iterator<Item = MixedAccess> + 'a, MixError> {
+    config.validate(traces.len())?;
+    let order: Box<dyn Iterator<Item = (usize, usize)> + 'a> = match &config.policy {
+        MixPolicy::RoundRobin => Box::new(turns(traces, vec![1; traces.len()])),
+        MixPolicy::Weighted(weights) => Box::new(turns(traces, weights.clone())),
+        MixPolicy::ByTimestamp => Box::new(by_timestamp(traces)),
+    };
+    Ok(order.map(move |(source, index)| {
+        let access = traces[source][index];
+        MixedAccess {
+            source,
+            access: TraceAccess {
+                address: access.address.wrapping_add(config.offset(source)),
+                ..access
+            },
+        }
+    }))
+}
+
+/// `(source, index)` pairs taking `weights[source]` accesses per turn.
+fn turns<'a>(
+    traces: &'a [&'a [TraceAccess]],
+    weights: Vec<u32>,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    let mut next = vec![0; traces.len()];
+    let mut source = 0;
+    let mut issued = 0;
+    std::iter::from_fn(move || {
+        // Every source is visited at most once before giving up.
+        for _ in 0..=traces.len() {
+            if next[source] < traces[source].len() && issued < weights[source] {
+                let index = next[source];
+                next[source] += 1;
+                issued += 1;
+                return Some((source, index));
+            }
+            source = (source + 1) % traces.len();
+            issued = 0;
+        }
+        None
+    })
+}
+
+/// `(source, index)` pairs in timestamp order.
+fn by_timestamp<'a>(traces: &'a [&'a [TraceAccess]]) -> impl Iterator<Item = (usize, usize)> + 'a {
+    let mut heads: BinaryHeap<Reverse<(u64, usize, usize)>> = traces
+        .iter()
+        .enumerate()
+        .filter_map(|(source, trace)| {
+            trace
+                .first()
+                .map(|first| Reverse((first.timestamp, source, 0)))
+        })
+        .collect();
+    std::iter::from_fn(move || {
+        let Reverse((_, source, index)) = heads.pop()?;
+        if let Some(next) = traces[source].get(index + 1) {
+            heads.push(Reverse((next.timestamp, source, index + 1)));
+        }
+        Some((source, index))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::AccessKind;
+
+    fn trace(timestamps: &[u64]) -> Vec<TraceAccess> {
+        timestamps
+            .iter()
+            .enumerate()
+            .map(|(index, &timestamp)| TraceAccess {
+                kind: AccessKind::Read,
+                address: index as u64,
+                thread: 0,
+                size: 4,
+                timestamp,
+            })
+            .collect()
+    }
+
+    /// `(source, address)` of each mixed access, offsets left at zero.
+    fn order(traces: &[&[TraceAccess]], policy: MixPolicy) -> Vec<(usize, u64)> {
+        let config = MixConfig {
+            policy,
+            address_offsets: Some(vec![0; traces.len()]),
+        };
+        mix(traces, &config)
+            .unwrap()
+            .map(|mixed| (mixed.source, mixed.access.address))
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_alternates_and_drains_longer_sources() {
+        let (a, b) = (trace(&[0, 1, 2, 3]), trace(&[0, 1]));
+        assert_eq!(
+            order(&[&a, &b], MixPolicy::RoundRobin),
+            [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2), (0, 3)]
+        );
+    }
+
+    #[test]
+    fn weighted_turns_issue_runs_per_source() {
+        let (a, b, c) = (trace(&[0; 5]), trace(&[0; 2]), trace(&[]));
+        assert_eq!(
+            order(&[&a, &b, &c], MixPolicy::Weighted(vec![3, 1, 2])),
+            [(0, 0), (0, 1), (0, 2), (1, 0), (0, 3), (0, 4), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn by_timestamp_merges_with_ties_to_the_lower_source() {
+        let (a, b) = (trace(&[0, 5, 9]), trace(&[3, 5, 20]));
+        assert_eq!(
+            order(&[&a, &b], MixPolicy::ByTimestamp),
+            [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn sources_get_disjoint_address_spaces_unless_offsets_are_given() {
+        let (a, b) = (trace(&[0]), trace(&[0]));
+        let traces: [&[TraceAccess]; 2] = [&a, &b];
+        let addresses = |config: &MixConfig| -> Vec<u64> {
+            mix(&traces, config)
+                .unwrap()
+                .map(|mixed| mixed.access.address)
+                .collect()
+        };
+        assert_eq!(
+            addresses(&MixConfig::default()),
+            [0, 1 << SOURCE_ADDRESS_SHIFT]
+        );
+        let shifted = MixConfig {
+            address_offsets: Some(vec![0x100, 0x200]),
+            ..MixConfig::default()
+        };
+        assert_eq!(addresses(&shifted), [0x100, 0x200]);
+    }
+
+    #[test]
+    fn mixes_must_match_their_sources() {
+        assert_eq!(MixConfig::default().validate(0), Err(MixError::ZeroSources));
+        for weights in [vec![1], vec![1, 0]] {
+            let config = MixConfig {
+                policy: MixPolicy::Weighted(weights.clone()),
+                ..MixConfig::default()
+            };
+            assert_eq!(
+                config.validate(2),
+                Err(MixError::InvalidWeights {
+                    weights,
+                    sources: 2
+                })
+            );
+        }
+        let config = MixConfig {
+            address_offsets: Some(vec![0; 3]),
+            ..MixConfig::default()
+        };
+        assert_eq!(
+            config.validate(2),
+            Err(MixError::OffsetCount {
+                offsets: 3,
+                sources: 2
+            })
+        );
+        let empty: [&[TraceAccess]; 0] = [];
+        assert!(mix(&empty, &MixConfig::default()).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use crate::cache::{AccessOutcome, EvictedLine, PredictionOutcome};
+use std::{cell::RefCell, rc::Rc};
+
+/// Instrumentation hooks invoked by [`crate::cache::Cache`] after each access.
+///
+/// Every method has an empty default, so an observer only implements the
+/// events it cares about. `tick` is the cache's access counter; it keeps
+/// counting across [`crate::cache::Cache::reset_stats`], so it can be used to
+/// measure lifetimes and reuse intervals.
+pub trait CacheObserver {
+    /// A main-array hit.
+    fn on_hit(&mut self, _tick: u64, _outcome: &AccessOutcome) {}
+
+    /// The block was in neither the main array nor the victim buffer.
+    fn on_miss(&mut self, _tick: u64, _outcome: &AccessOutcome) {}
+
+    /// The block was recovered from the victim buffer.
+    fn on_victim_hit(&mut self, _tick: u64, _outcome: &AccessOutcome) {}
+
+    /// A block was placed into `way` of `set_index`, from memory, the victim
+    /// buffer or the other slot of a hash-rehash pair.
+    fn on_fill(&mut self, _tick: u64, _set_index: usize, _way: usize, _block_address: u64) {}
+
+    /// A line was displaced from the main array.
+    fn on_eviction(&mut self, _tick: u64, _set_index: usize, _line: &EvictedLine) {}
+
+    /// A main-array line was dropped by [`crate::cache::Cache::invalidate`].
+    fn on_invalidation(&mut self, _tick: u64, _set_index: usize, _line: &EvictedLine) {}
+
+    /// The predictor's verdict for a main-array hit.
+    fn on_prediction(
+        &mut self,
+        _tick: u64,
+        _outcome: &AccessOutcome,
+        _prediction: &PredictionOutcome,
+    ) {
+    }
+}
+
+/// Lets a caller keep a handle on an observer after handing it to a cache.
+impl<T: CacheObserver> CacheObserver for Rc<RefCell<T>> {
+    fn on_hit(&mut self, tick: u64, outcome: &AccessOutcome) {
+        self.borrow_mut().on_hit(tick, outcome);
+    }
+
+    fn on_miss(&mut self, tick: u64, outcome: &AccessOutcome) {
+        self.borrow_mut().on_miss(tick, outcome);
+    }
+
+    fn on_victim_hit(&mut self, tick: u64, outcome: &AccessOutcome) {
+        self.borrow_mut().on_victim_hit(tick, outcome);
+    }
+
+    fn on_fill(&mut self, tick: u64, set_index: usize, way: usize, block_address: u64) {
+        self.borrow_mut()
+            .on_fill(tick, set_index, way, block_address);
+    }
+
+    fn on_eviction(&mut self, tick: u64, set_index: usize, line: &EvictedLine) {
+        self.borrow_mut().on_eviction(tick, set_index, line);
+    }
+
+    fn on_invalidation(&mut self, tick: u64, set_index: usize, line: &EvictedLine) {
+        self.borrow_mut().on_invalidation(tick, set_index, line);
+    }
+
+    fn on_prediction(
+        &mut self,
+        tick: u64,
+        outcome: &AccessOutcome,
+        prediction: &PredictionOutcome,
+    ) {
+        self.borrow_mut().on_prediction(tick, outcome, prediction);
+    }
+}
+
+/// Fans one access out to every attached observer in attach order.
+pub(crate) fn notify(observers: &mut [Box<dyn CacheObserver>], tick: u64, outcome: &AccessOutcome) {
+    for observer in observers.iter_mut() {
+        if let Some(prediction) = &outcome.prediction {
+            observer.on_prediction(tick, outcome, prediction);
+        }
+        if let Some(line) = &outcome.evicted {
+            observer.on_eviction(tick, line.set_index, line);
+        }
+        if outcome.from_victim && outcome.hit {
+            observer.on_victim_hit(tick, outcome);
+        } else if outcome.hit {
+            observer.on_hit(tick, outcome);
+        } else {
+            observer.on_miss(tick, outcome);
+        }
+        // A sector miss on a resident line fetches data but places no block,
+        // unless a second probe found the line and moved it to the primary set.
+        let second_probe = outcome.rehash.is_some_and(|rehash| rehash.second_probe);
+        if outcome.from_victim || second_probe || !(outcome.hit || outcome.sector_miss) {
+            observer.on_fill(tick, outcome.set_index, outcome.way, outcome.block_address);
+        }
+        if let Some(rehash) = &outcome.rehash
+            && let Some(moved) = rehash.moved_block
+        {
+            observer.on_fill(tick, rehash.rehash_set, 0, moved);
+        }
+        if let Some(prefetch) = &outcome.prefetch {
+            if let Some(line) = &prefetch.evicted {
+                observer.on_eviction(tick, line.set_index, line);
+            }
+            observer.on_fill(
+                tick,
+                prefetch.set_index,
+                prefetch.way,
+                prefetch.block_address,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::{Cache, CacheConfig},
+        trace::{AccessKind, TraceAccess},
+    };
+
+    /// Every event as `(name, tick, block)`.
+    #[derive(Default)]
+    struct EventLog(Vec<(&'static str, u64, u64)>);
+
+    impl CacheObserver for EventLog {
+        fn on_hit(&mut self, tick: u64, outcome: &AccessOutcome) {
+            self.0.push(("hit", tick, outcome.block_address));
+        }
+
+        fn on_miss(&mut self, tick: u64, outcome: &AccessOutcome) {
+            self.0.push(("miss", tick, outcome.block_address));
+        }
+
+        fn on_victim_hit(&mut self, tick: u64, outcome: &AccessOutcome) {
+            self.0.push(("victim-hit", tick, outcome.block_address));
+        }
+
+        fn on_fill(&mut self, tick: u64, _set_index: usize, _way: usize, block_address: u64) {
+            self.0.push(("fill", tick, block_address));
+        }
+
+        fn on_eviction(&mut self, tick: u64, _set_index: usize, line: &EvictedLine) {
+            self.0.push(("evict", tick, line.block_address));
+        }
+    }
+
+    fn read(block_address: u64) -> TraceAccess {
+        TraceAccess {
+            kind: AccessKind::Read,
+            address: block_address * 32,
+            thread: 0,
+            size: 4,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn hits_misses_fills_and_evictions_are_reported_in_order() {
+        // Direct-mapped with four sets, so blocks 0 and 4 conflict.
+        let config = CacheConfig::builder()
+            .cache_size(4 * 32)
+            .associativity(1)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        let log = Rc::new(RefCell::new(EventLog::default()));
+        cache.attach_observer(Rc::clone(&log));
+        cache.run_trace(&[0, 0, 4].map(read));
+        assert_eq!(
+            log.borrow().0,
+            [
+                ("miss", 1, 0),
+                ("fill", 1, 0),
+                ("hit", 2, 0),
+                ("evict", 3, 0),
+                ("miss", 3, 4),
+                ("fill", 3, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn victim_hits_refill_the_main_array() {
+        let config = CacheConfig::builder()
+            .cache_size(4 * 32)
+            .associativity(1)
+            .victim_cache_entries(2)
+            .build()
+            .unwrap();
+        let mut cache = Cache::new(config).unwrap();
+        let log = Rc::new(RefCell::new(EventLog::default()));
+        cache.attach_observer(Rc::clone(&log));
+        cache.run_trace(&[0, 4, 0].map(read));
+        let events: Vec<_> = log.borrow().0[5..]
+            .iter()
+            .map(|&(name, _, block)| (name, block))
+            .collect();
+        assert_eq!(events, [("evict", 4), ("victim-hit", 0), ("fill", 0)]);
+    }
+}
@@ -0,0 +1,469 @@
+use crate::{
+    cache::{AccessOutcome, Cache, CacheConfig, CacheStats, ConfigError},
+    error::Error,
+    measurement::Measurement,
+    mix::{self, MixConfig},
+    trace::TraceAccess,
+};
+use std::fmt;
+
+/// Sets one utility monitor samples: one in every this many.
+const UMON_SET_STRIDE: usize = 32;
+
+/// How the ways of a shared cache are divided between its sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionPolicy {
+    /// Every source competes for the whole set under LRU.
+    Shared,
+    /// Fixed way quotas, indexed by source.
+    Static(Vec<usize>),
+    /// Utility-based cache partitioning (Qureshi and Patt): shadow tags
+    /// measure how many hits each source would get from every way count, and
+    /// the ways are reassigned every `interval` accesses.
+    Ucp { interval: u64 },
+}
+
+impl fmt::Display for PartitionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionPolicy::Shared => write!(f, "shared LRU"),
+            PartitionPolicy::Static(quotas) => write!(f, "static {}", format_quotas(quotas)),
+            PartitionPolicy::Ucp { interval } => write!(f, "UCP every {interval}"),
+        }
+    }
+}
+
+/// Reasons a [`SharedCache`] cannot be set up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionError {
+    /// A shared cache needs at least one source.
+    ZeroSources,
+    /// Utility-based repartitioning needs a non-zero interval.
+    ZeroInterval,
+}
+
+impl fmt::Display for PartitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionError::ZeroSources => write!(f, "a shared cache needs at least one source"),
+            PartitionError::ZeroInterval => {
+                write!(f, "the repartitioning interval must be non-zero")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PartitionError {}
+
+/// Quotas as `6/5/5`.
+pub fn format_quotas(quotas: &[usize]) -> String {
+    quotas
+        .iter()
+        .map(|ways| ways.to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `ways` divided as evenly as possible between `sources`, earlier sources
+/// taking the remainder: the split UCP starts from.
+pub fn even_quotas(ways: usize, sources: usize) -> Vec<usize> {
+    (0..sources)
+        .map(|source| ways / sources + usize::from(source < ways % sources))
+        .collect()
+}
+
+/// Quotas in force from `access` (counted across all sources) onwards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionEpoch {
+    pub access: u64,
+    pub quotas: Vec<usize>,
+}
+
+/// Shadow tags for one source over a sample of sets, each kept as a full
+/// LRU stack as if the source had the whole cache.
+struct UtilityMonitor {
+    stacks: Vec<Vec<u64>>,
+    ways: usize,
+    /// Hits at each LRU stack position.
+    hits: Vec<u64>,
+}
+
+impl UtilityMonitor {
+    fn new(sets: usize, ways: usize) -> Self {
+        Self {
+            stacks: vec![Vec::with_capacity(ways); sets.div_ceil(UMON_SET_STRIDE)],
+            ways,
+            hits: vec![0; ways],
+        }
+    }
+
+    fn observe(&mut self, set_index: usize, block_address: u64) {
+        if !set_index.is_multiple_of(UMON_SET_STRIDE) {
+            return;
+        }
+        let stack = &mut self.stacks[set_index / UMON_SET_STRIDE];
+        match stack.iter().position(|&block| block == block_address) {
+            Some(depth) => {
+                self.hits[depth] += 1;
+                stack.remove(depth);
+            }
+            None => stack.truncate(self.ways - 1),
+        }
+        stack.insert(0, block_address);
+    }
+
+    /// Hits the source would have had with `ways` ways.
+    fn utility(&self, ways: usize) -> u64 {
+        self.hits[..ways].iter().sum()
+    }
+
+    /// Halves the counters so older behaviour fades.
+    fn decay(&mut self) {
+        for hits in &mut self.hits {
+            *hits /= 2;
+        }
+    }
+}
+
+/// Greedy lookahead allocation: every source starts with one way, and each
+/// round the source with the best hits-per-way over any further block of
+/// ways takes that block.
+fn lookahead_partition(monitors: &[UtilityMonitor], ways: usize) -> Vec<usize> {
+    // `SharedCache::new` only starts UCP from valid quotas, at least one way
+    // per source, so there is always a way for every monitor.
+    debug_assert!(
+        ways >= monitors.len(),
+        "{ways} ways for {} sources",
+        monitors.len()
+    );
+    let mut quotas = vec![1; monitors.len()];
+    let mut balance = ways - monitors.len();
+    while balance > 0 {
+        let mut best: Option<(f64, usize, usize)> = None;
+        for (source, monitor) in monitors.iter().enumerate() {
+            let base = monitor.utility(quotas[source]);
+            for extra in 1..=balance {
+                let gain = monitor.utility(quotas[source] + extra) - base;
+                let per_way = gain as f64 / extra as f64;
+                if best.is_none_or(|(best_per_way, _, _)| per_way > best_per_way) {
+                    best = Some((per_way, source, extra));
+                }
+            }
+        }
+        let (_, source, extra) = best.unwrap();
+        quotas[source] += extra;
+        balance -= extra;
+    }
+    quotas
+}
+
+/// One cache shared by several interleaved sources, optionally way-partitioned.
+pub struct SharedCache {
+    cache: Cache,
+    policy: PartitionPolicy,
+    monitors: Vec<UtilityMonitor>,
+    sources: Vec<CacheStats>,
+    history: Vec<PartitionEpoch>,
+    accesses: u64,
+}
+
+impl SharedCache {
+    pub fn new(
+        config: CacheConfig,
+        sources: usize,
+        policy: PartitionPolicy,
+    ) -> Result<Self, Error> {
+        if sources == 0 {
+            return Err(PartitionError::ZeroSources.into());
+        }
+        let mut config = config;
+        config.way_quotas = match &policy {
+            PartitionPolicy::Shared => None,
+            PartitionPolicy::Static(quotas) => Some(quotas.clone()),
+            PartitionPolicy::Ucp { interval } => {
+                if *interval == 0 {
+                    return Err(PartitionError::ZeroInterval.into());
+                }
+                // Start from an even split; the first epoch corrects it.
+                Some(even_quotas(config.associativity, sources))
+            }
+        };
+        if let Some(quotas) = &config.way_quotas
+            && quotas.len() != sources
+        {
+            return Err(ConfigError::InvalidWayQuotas {
+                quotas: quotas.clone(),
+                ways: config.associativity,
+            }
+            .into());
+        }
+        let monitors = match policy {
+            PartitionPolicy::Ucp { .. } => (0..sources)
+                .map(|_| UtilityMonitor::new(config.num_sets(), config.associativity))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let history = config
+            .way_quotas
+            .iter()
+            .map(|quotas| PartitionEpoch {
+                access: 0,
+                quotas: quotas.clone(),
+            })
+            .collect();
+        let stats = CacheStats::new(&config);
+        Ok(Self {
+            cache: Cache::new(config)?,
+            policy,
+            monitors,
+            sources: vec![stats; sources],
+            history,
+            accesses: 0,
+        })
+    }
+
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
+    pub fn policy(&self) -> &PartitionPolicy {
+        &self.policy
+    }
+
+    /// Per-source stats, indexed by source.
+    pub fn source_stats(&self) -> &[CacheStats] {
+        &self.sources
+    }
+
+    /// Each change of quotas, starting with the initial ones; empty when unpartitioned.
+    pub fn history(&self) -> &[PartitionEpoch] {
+        &self.history
+    }
+
+    /// Services one access from `source`. The address is used as given, so
+    /// sources that must not share blocks need address ranges of their own.
+    pub fn access(
+        &mut self,
+        source: usize,
+        access: &TraceAccess,
+    ) -> Result<AccessOutcome, ConfigError> {
+        self.service(source, access, true)
+    }
+
+    /// [`SharedCache::access`] without counting it, as during warm-up. The
+    /// utility monitors still learn from it.
+    pub fn warm(
+        &mut self,
+        source: usize,
+        access: &TraceAccess,
+    ) -> Result<AccessOutcome, ConfigError> {
+        self.service(source, access, false)
+    }
+
+    /// Replays `traces` interleaved by `mix`; source `i` replays `traces[i]`.
+    pub fn run(&mut self, traces: &[&[TraceAccess]], mix: &MixConfig) -> Result<(), Error> {
+        self.run_measured(traces, mix, &Measurement::default())
+    }
+
+    /// [`SharedCache::run`], counting only the accesses of the mixed stream
+    /// that `measurement` selects.
+    pub fn run_measured(
+        &mut self,
+        traces: &[&[TraceAccess]],
+        mix: &MixConfig,
+        measurement: &Measurement,
+    ) -> Result<(), Error> {
+        measurement.validate()?;
+        let len = traces.iter().map(|trace| trace.len()).sum();
+        for (position, mixed) in mix::mix(traces, mix)?.enumerate() {
+            self.service(
+                mixed.source,
+                &mixed.access,
+                measurement.is_measured(position, len),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn service(
+        &mut self,
+        source: usize,
+        access: &TraceAccess,
+        measured: bool,
+    ) -> Result<AccessOutcome, ConfigError> {
+        if source >= self.sources.len() {
+            return Err(ConfigError::UnknownSource {
+                source,
+                sources: self.sources.len(),
+            });
+        }
+        self.cache.set_measuring(measured);
+        let outcome = self.cache.access_from(source as u32, access);
+        self.cache.set_measuring(true);
+        let outcome = outcome?;
+        if measured {
+            self.sources[source].record(&outcome);
+        }
+        if let Some(monitor) = self.monitors.get_mut(source) {
+            monitor.observe(outcome.set_index, outcome.block_address);
+        }
+        self.accesses += 1;
+        if let PartitionPolicy::Ucp { interval } = self.policy
+            && self.accesses.is_multiple_of(interval)
+        {
+            self.repartition();
+        }
+        Ok(outcome)
+    }
+
+    fn repartition(&mut self) {
+        let quotas = lookahead_partition(&self.monitors, self.cache.config().associativity);
+        for monitor in &mut self.monitors {
+            monitor.decay();
+        }
+        if self.cache.config().way_quotas.as_ref() == Some(&quotas) {
+            return;
+        }
+        self.cache
+            .set_way_quotas(quotas.clone())
+            .expect("lookahead quotas cover every way");
+        self.history.push(PartitionEpoch {
+            access: self.accesses,
+            quotas,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::AccessKind;
+
+    fn monitor(hits: Vec<u64>) -> UtilityMonitor {
+        UtilityMonitor {
+            stacks: Vec::new(),
+            ways: hits.len(),
+            hits,
+        }
+    }
+
+    #[test]
+    fn monitors_count_hits_by_stack_depth_in_sampled_sets() {
+        let mut monitor = UtilityMonitor::new(64, 4);
+        for block in [1, 2, 3, 1, 3] {
+            monitor.observe(0, block);
+            // Set 1 is not sampled.
+            monitor.observe(1, block);
+        }
+        assert_eq!(monitor.hits, [0, 1, 1, 0]);
+        assert_eq!(monitor.utility(2), 1);
+        assert_eq!(monitor.utility(3), 2);
+    }
+
+    #[test]
+    fn lookahead_sees_past_a_utility_cliff() {
+        // Source 0 gains nothing until its working set fits in four ways;
+        // source 1 gains a little less from each extra way.
+        let monitors = [
+            monitor(vec![10, 0, 0, 30, 0, 0, 0, 0]),
+            monitor(vec![10, 8, 6, 4, 2, 1, 0, 0]),
+        ];
+        assert_eq!(lookahead_partition(&monitors, 8), [4, 4]);
+        // With no cliff, the steadier source takes every spare way.
+        let monitors = [
+            monitor(vec![10, 0, 0, 0, 0, 0, 0, 0]),
+            monitor(vec![10, 8, 6, 4, 2, 1, 1, 1]),
+        ];
+        assert_eq!(lookahead_partition(&monitors, 8), [1, 7]);
+    }
+
+    #[test]
+    fn accesses_from_unknown_sources_are_rejected() {
+        let mut shared = SharedCache::new(
+            CacheConfig::default(),
+            2,
+            PartitionPolicy::Static(vec![2, 2]),
+        )
+        .unwrap();
+        let access = TraceAccess {
+            kind: AccessKind::Read,
+            address: 0,
+            thread: 0,
+            size: 4,
+            timestamp: 0,
+        };
+        assert!(shared.access(1, &access).is_ok());
+        assert_eq!(
+            shared.access(2, &access).unwrap_err(),
+            ConfigError::UnknownSource {
+                source: 2,
+                sources: 2
+            }
+        );
+    }
+
+    fn reads(blocks: impl IntoIterator<Item = u64>) -> Vec<TraceAccess> {
+        blocks
+            .into_iter()
+            .map(|block| TraceAccess {
+                kind: AccessKind::Read,
+                address: block * 32,
+                thread: 0,
+                size: 4,
+                timestamp: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn even_quotas_hand_the_remainder_to_earlier_sources() {
+        assert_eq!(even_quotas(16, 3), [6, 5, 5]);
+        assert_eq!(even_quotas(16, 4), [4, 4, 4, 4]);
+    }
+
+    #[test]
+    fn static_quotas_shield_a_source_from_a_streaming_neighbour() {
+        let config = CacheConfig::builder()
+            .cache_size(4 * 32)
+            .block_size(32)
+            .associativity(4)
+            .build()
+            .unwrap();
+        // Source 0 cycles through three blocks; source 1 never reuses one.
+        let looping = reads((0..30).map(|step| step % 3));
+        let streaming = reads(100..130);
+        let hits = |policy| {
+            let mut shared = SharedCache::new(config.clone(), 2, policy).unwrap();
+            shared
+                .run(&[&looping, &streaming], &MixConfig::default())
+                .unwrap();
+            shared.source_stats()[0].hits
+        };
+        assert_eq!(hits(PartitionPolicy::Shared), 0);
+        assert_eq!(hits(PartitionPolicy::Static(vec![3, 1])), 27);
+    }
+
+    #[test]
+    fn shared_caches_need_sources_an_interval_and_one_quota_each() {
+        let config = CacheConfig::default();
+        let rejected = |sources, policy| SharedCache::new(config.clone(), sources, policy).err();
+        assert_eq!(
+            rejected(0, PartitionPolicy::Shared),
+            Some(PartitionError::ZeroSources.into())
+        );
+        assert_eq!(
+            rejected(2, PartitionPolicy::Ucp { interval: 0 }),
+            Some(PartitionError::ZeroInterval.into())
+        );
+        assert_eq!(
+            rejected(3, PartitionPolicy::Static(vec![2, 2])),
+            Some(
+                ConfigError::InvalidWayQuotas {
+                    quotas: vec![2, 2],
+                    ways: 4,
+                }
+                .into()
+            )
+        );
+    }
+}
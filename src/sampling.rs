@@ -0,0 +1,591 @@
+use crate::{
+    cache::{Cache, CacheConfig, CacheStats},
+    error::Error,
+    rng::SplitMix64,
+    trace::TraceAccess,
+};
+use std::{collections::HashSet, fmt, ops::Range};
+
+/// Buckets in an interval's address-footprint signature.
+const SIGNATURE_BUCKETS: usize = 64;
+
+/// Pages are the unit an interval's signature is built from.
+const SIGNATURE_PAGE_SHIFT: u32 = 12;
+
+/// k-means rounds when clustering signatures.
+const KMEANS_ROUNDS: usize = 20;
+
+/// How slices of a trace are chosen. Every slice is simulated on a fresh
+/// cache after replaying the `warmup` accesses before it uncounted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingMode {
+    /// The last `sample` accesses of every `period`, each standing for its
+    /// period. A trailing partial period keeps its last `sample` accesses, or
+    /// all of them, and stands for its own length.
+    Periodic {
+        period: usize,
+        sample: usize,
+        warmup: usize,
+    },
+    /// `intervals` slices of `sample` accesses at random offsets.
+    Random {
+        intervals: usize,
+        sample: usize,
+        warmup: usize,
+        seed: u64,
+    },
+    /// SimPoint-style: the trace is cut into `interval`-access intervals,
+    /// each signed by the pages it touches, the signatures are grouped into
+    /// up to `clusters` phases with k-means, and the interval nearest each
+    /// phase's centre stands for the whole phase.
+    Phases {
+        interval: usize,
+        clusters: usize,
+        warmup: usize,
+        seed: u64,
+    },
+}
+
+impl fmt::Display for SamplingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SamplingMode::Periodic { period, sample, .. } => {
+                write!(f, "periodic {sample}/{period}")
+            }
+            SamplingMode::Random {
+                intervals, sample, ..
+            } => write!(f, "random {intervals}x{sample}"),
+            SamplingMode::Phases {
+                interval, clusters, ..
+            } => write!(f, "phases {clusters}x{interval}"),
+        }
+    }
+}
+
+/// Reasons a [`SamplingMode`] cannot pick slices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingError {
+    /// The mode's parameters cannot describe any slice.
+    InvalidMode(&'static str),
+    /// The trace is shorter than one sample, or empty in phase mode.
+    TraceTooShort,
+}
+
+impl fmt::Display for SamplingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SamplingError::InvalidMode(reason) => write!(f, "invalid sampling: {reason}"),
+            SamplingError::TraceTooShort => {
+                write!(f, "invalid sampling: the trace is shorter than one sample")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SamplingError {}
+
+impl SamplingMode {
+    pub fn validate(&self) -> Result<(), SamplingError> {
+        let invalid = |reason| Err(SamplingError::InvalidMode(reason));
+        match *self {
+            SamplingMode::Periodic { period, sample, .. } => {
+                if sample == 0 || sample > period {
+                    return invalid("the sample must be non-empty and fit in the period");
+                }
+            }
+            SamplingMode::Random {
+                intervals, sample, ..
+            } => {
+                if intervals == 0 || sample == 0 {
+                    return invalid("random sampling needs at least one non-empty interval");
+                }
+            }
+            SamplingMode::Phases {
+                interval, clusters, ..
+            } => {
+                if interval == 0 || clusters == 0 {
+                    return invalid("phase sampling needs non-empty intervals and a cluster");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn warmup(&self) -> usize {
+        match *self {
+            SamplingMode::Periodic { warmup, .. }
+            | SamplingMode::Random { warmup, .. }
+            | SamplingMode::Phases { warmup, .. } => warmup,
+        }
+    }
+
+    /// Measured ranges of a `len`-access trace and the share of the trace
+    /// each stands for.
+    fn slices(&self, trace: &[TraceAccess]) -> Result<Vec<(Range<usize>, f64)>, SamplingError> {
+        self.validate()?;
+        let len = trace.len();
+        let too_short = Err(SamplingError::TraceTooShort);
+        match *self {
+            SamplingMode::Periodic { period, sample, .. } => {
+                if len < sample {
+                    return too_short;
+                }
+                Ok((0..len.div_ceil(period))
+                    .map(|index| {
+                        let start = index * period;
+                        let end = (start + period).min(len);
+                        let slice = (end - sample).max(start)..end;
+                        (slice, (end - start) as f64 / len as f64)
+                    })
+                    .collect())
+            }
+            SamplingMode::Random {
+                intervals,
+                sample,
+                seed,
+                ..
+            } => {
+                if len < sample {
+                    return too_short;
+                }
+                let mut rng = SplitMix64::new(seed);
+                let mut starts: Vec<usize> = (0..intervals)
+                    .map(|_| rng.below((len - sample + 1) as u64) as usize)
+                    .collect();
+                starts.sort_unstable();
+                Ok(starts
+                    .into_iter()
+                    .map(|start| (start..start + sample, 1.0 / intervals as f64))
+                    .collect())
+            }
+            SamplingMode::Phases {
+                interval,
+                clusters,
+                seed,
+                ..
+            } => {
+                if len == 0 {
+                    return too_short;
+                }
+                Ok(phase_slices(trace, interval, clusters, seed))
+            }
+        }
+    }
+}
+
+/// Share of an interval's accesses falling in each signature bucket.
+fn signature(accesses: &[TraceAccess]) -> Vec<f64> {
+    let mut buckets = vec![0.0; SIGNATURE_BUCKETS];
+    for access in accesses {
+        let page = access.address >> SIGNATURE_PAGE_SHIFT;
+        // Fibonacci hashing spreads neighbouring pages over the buckets.
+        let bucket = (page.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 58) as usize;
+        buckets[bucket] += 1.0;
+    }
+    for share in &mut buckets {
+        *share /= accesses.len() as f64;
+    }
+    buckets
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// One representative interval per k-means cluster of interval signatures,
+/// weighted by the accesses its cluster covers.
+fn phase_slices(
+    trace: &[TraceAccess],
+    interval: usize,
+    clusters: usize,
+    seed: u64,
+) -> Vec<(Range<usize>, f64)> {
+    let ranges: Vec<Range<usize>> = (0..trace.len())
+        .step_by(interval)
+        .map(|start| start..(start + interval).min(trace.len()))
+        .collect();
+    let signatures: Vec<Vec<f64>> = ranges
+        .iter()
+        .map(|range| signature(&trace[range.clone()]))
+        .collect();
+
+    // k-means++ seeding: each further centre is drawn with probability
+    // proportional to its squared distance from the nearest centre so far.
+    let mut rng = SplitMix64::new(seed);
+    let mut centres = vec![signatures[rng.below(signatures.len() as u64) as usize].clone()];
+    while centres.len() < clusters.min(signatures.len()) {
+        let nearest: Vec<f64> = signatures
+            .iter()
+            .map(|signature| {
+                centres
+                    .iter()
+                    .map(|centre| distance(signature, centre))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .collect();
+        let total: f64 = nearest.iter().sum();
+        if total == 0.0 {
+            break;
+        }
+        let mut draw = rng.next_f64() * total;
+        let chosen = nearest
+            .iter()
+            .position(|&weight| {
+                draw -= weight;
+                draw < 0.0
+            })
+            .unwrap_or(nearest.len() - 1);
+        centres.push(signatures[chosen].clone());
+    }
+
+    let nearest_centre = |signature: &[f64], centres: &[Vec<f64>]| {
+        (0..centres.len())
+            .min_by(|&a, &b| {
+                distance(signature, &centres[a]).total_cmp(&distance(signature, &centres[b]))
+            })
+            .unwrap()
+    };
+    let mut assignment = vec![0; signatures.len()];
+    for _ in 0..KMEANS_ROUNDS {
+        let next: Vec<usize> = signatures
+            .iter()
+            .map(|signature| nearest_centre(signature, &centres))
+            .collect();
+        let settled = next == assignment;
+        assignment = next;
+        for (cluster, centre) in centres.iter_mut().enumerate() {
+            let members: Vec<&Vec<f64>> = signatures
+                .iter()
+                .zip(&assignment)
+                .filter(|(_, assigned)| **assigned == cluster)
+                .map(|(signature, _)| signature)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            for (bucket, value) in centre.iter_mut().enumerate() {
+                *value =
+                    members.iter().map(|member| member[bucket]).sum::<f64>() / members.len() as f64;
+            }
+        }
+        if settled {
+            break;
+        }
+    }
+
+    (0..centres.len())
+        .filter_map(|cluster| {
+            let members: Vec<usize> = (0..signatures.len())
+                .filter(|&index| assignment[index] == cluster)
+                .collect();
+            let representative = *members.iter().min_by(|&&a, &&b| {
+                distance(&signatures[a], &centres[cluster])
+                    .total_cmp(&distance(&signatures[b], &centres[cluster]))
+            })?;
+            let covered: usize = members.iter().map(|&index| ranges[index].len()).sum();
+            Some((
+                ranges[representative].clone(),
+                covered as f64 / trace.len() as f64,
+            ))
+        })
+        .collect()
+}
+
+/// One simulated slice.
+#[derive(Debug, Clone)]
+pub struct SliceResult {
+    /// Measured accesses; the warm-up before them is not included.
+    pub range: Range<usize>,
+    /// Accesses replayed uncounted before the slice.
+    pub warmup: usize,
+    /// Slice accesses to blocks neither the warm-up nor the slice had touched
+    /// before. They miss on the fresh cache, but might have hit in a full run.
+    pub cold_accesses: u64,
+    /// Share of the trace the slice stands for.
+    pub weight: f64,
+    pub stats: CacheStats,
+}
+
+/// Full-trace hit rate estimated from weighted slices.
+#[derive(Debug, Clone)]
+pub struct SampledEstimate {
+    pub slices: Vec<SliceResult>,
+    pub hit_rate: f64,
+    /// Half-width of a 95% interval around `hit_rate`: the spread of the
+    /// slice hit rates plus `cold_start_bias`. `None` with fewer than two
+    /// weighted slices.
+    pub error_bound: Option<f64>,
+    /// Most the fresh caches can have lowered `hit_rate`: the weighted share
+    /// of slice accesses that were cold after the warm-up. The true hit rate
+    /// lies below `hit_rate` by at most the statistical error, and above it by
+    /// at most that error plus this bias. A warm-up of a few cache capacities
+    /// keeps it small.
+    pub cold_start_bias: f64,
+    pub total_accesses: usize,
+}
+
+impl SampledEstimate {
+    /// Accesses simulated, warm-up included.
+    pub fn simulated_accesses(&self) -> usize {
+        self.slices
+            .iter()
+            .map(|slice| slice.warmup + slice.range.len())
+            .sum()
+    }
+
+    /// Simulated accesses as a share of the trace.
+    pub fn simulated_fraction(&self) -> f64 {
+        if self.total_accesses == 0 {
+            0.0
+        } else {
+            self.simulated_accesses() as f64 / self.total_accesses as f64
+        }
+    }
+}
+
+/// Estimates the hit rate `config` would reach on all of `trace` by
+/// simulating only the slices `mode` picks. The slices start on fresh caches,
+/// so the estimate leans low; see [`SampledEstimate::cold_start_bias`].
+pub fn estimate(
+    trace: &[TraceAccess],
+    config: &CacheConfig,
+    mode: &SamplingMode,
+) -> Result<SampledEstimate, Error> {
+    config.validate()?;
+    let slices = mode
+        .slices(trace)?
+        .into_iter()
+        .map(|(range, weight)| {
+            let warmup = mode.warmup().min(range.start);
+            let mut cache = Cache::new(config.clone())?;
+            for access in &trace[range.start - warmup..range.start] {
+                cache.access(access);
+            }
+            let stats = cache.run_trace(&trace[range.clone()]);
+            let block_shift = config.block_size.trailing_zeros();
+            let mut touched: HashSet<u64> = trace[range.start - warmup..range.start]
+                .iter()
+                .map(|access| access.address >> block_shift)
+                .collect();
+            let cold_accesses = trace[range.clone()]
+                .iter()
+                .filter(|access| touched.insert(access.address >> block_shift))
+                .count() as u64;
+            Ok(SliceResult {
+                range,
+                warmup,
+                cold_accesses,
+                weight,
+                stats,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let total_weight: f64 = slices.iter().map(|slice| slice.weight).sum();
+    let weights: Vec<f64> = slices
+        .iter()
+        .map(|slice| slice.weight / total_weight)
+        .collect();
+    let rates: Vec<f64> = slices.iter().map(|slice| slice.stats.hit_rate()).collect();
+    let cold_start_bias: f64 = weights
+        .iter()
+        .zip(&slices)
+        .map(|(w, slice)| w * slice.cold_accesses as f64 / slice.range.len() as f64)
+        .sum();
+    let hit_rate: f64 = weights.iter().zip(&rates).map(|(w, rate)| w * rate).sum();
+    // Weighted variance of the slice rates, scaled by the effective number
+    // of independent slices; equal weights give the usual s^2 / n.
+    let concentration: f64 = weights.iter().map(|w| w * w).sum();
+    let error_bound = (concentration < 1.0 - 1e-12).then(|| {
+        let variance = weights
+            .iter()
+            .zip(&rates)
+            .map(|(w, rate)| w * (rate - hit_rate).powi(2))
+            .sum::<f64>()
+            / (1.0 - concentration);
+        1.96 * (variance * concentration).sqrt() + cold_start_bias
+    });
+    Ok(SampledEstimate {
+        slices,
+        hit_rate,
+        error_bound,
+        cold_start_bias,
+        total_accesses: trace.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::AccessKind;
+
+    fn read(address: u64) -> TraceAccess {
+        TraceAccess {
+            kind: AccessKind::Read,
+            address,
+            thread: 0,
+            size: 4,
+            timestamp: 0,
+        }
+    }
+
+    /// `len` reads cycling over `blocks` 32-byte blocks starting at `base`.
+    fn looping(base: u64, blocks: u64, len: usize) -> Vec<TraceAccess> {
+        (0..len as u64)
+            .map(|index| read(base + index % blocks * 32))
+            .collect()
+    }
+
+    #[test]
+    fn periodic_slices_end_each_period_and_share_the_weight() {
+        let trace = looping(0, 8, 100);
+        let mode = SamplingMode::Periodic {
+            period: 20,
+            sample: 5,
+            warmup: 10,
+        };
+        let estimate = estimate(&trace, &CacheConfig::default(), &mode).unwrap();
+        let ranges: Vec<_> = estimate
+            .slices
+            .iter()
+            .map(|slice| slice.range.clone())
+            .collect();
+        assert_eq!(ranges, [15..20, 35..40, 55..60, 75..80, 95..100]);
+        assert!(estimate.slices.iter().all(|slice| slice.weight == 0.2));
+        assert_eq!(estimate.simulated_accesses(), 75);
+        // Ten accesses warm all eight blocks, so every slice only hits.
+        assert_eq!(estimate.hit_rate, 1.0);
+        assert_eq!(estimate.error_bound, Some(0.0));
+    }
+
+    #[test]
+    fn phase_sampling_picks_one_interval_per_phase() {
+        // Two phases on disjoint pages, ten 10-access intervals each.
+        let mut trace = looping(0, 4, 100);
+        trace.extend(looping(1 << 20, 4, 100));
+        let mode = SamplingMode::Phases {
+            interval: 10,
+            clusters: 2,
+            warmup: 0,
+            seed: 3,
+        };
+        let mut slices = mode.slices(&trace).unwrap();
+        slices.sort_by_key(|(range, _)| range.start);
+        assert_eq!(slices.len(), 2);
+        assert!(slices[0].0.end <= 100 && slices[1].0.start >= 100);
+        assert!(
+            slices
+                .iter()
+                .all(|(range, weight)| range.len() == 10 && *weight == 0.5)
+        );
+    }
+
+    #[test]
+    fn modes_that_cannot_pick_a_slice_are_rejected() {
+        let trace = looping(0, 4, 10);
+        let periodic = |period, sample| SamplingMode::Periodic {
+            period,
+            sample,
+            warmup: 0,
+        };
+        assert!(matches!(
+            periodic(4, 5).slices(&trace),
+            Err(SamplingError::InvalidMode(_))
+        ));
+        assert_eq!(
+            periodic(20, 12).slices(&trace),
+            Err(SamplingError::TraceTooShort)
+        );
+        let random = SamplingMode::Random {
+            intervals: 0,
+            sample: 5,
+            warmup: 0,
+            seed: 1,
+        };
+        assert!(matches!(
+            random.slices(&trace),
+            Err(SamplingError::InvalidMode(_))
+        ));
+        let phases = SamplingMode::Phases {
+            interval: 10,
+            clusters: 0,
+            warmup: 0,
+            seed: 1,
+        };
+        assert!(matches!(
+            phases.slices(&trace),
+            Err(SamplingError::InvalidMode(_))
+        ));
+        let phases = SamplingMode::Phases {
+            interval: 10,
+            clusters: 1,
+            warmup: 0,
+            seed: 1,
+        };
+        assert_eq!(phases.slices(&[]), Err(SamplingError::TraceTooShort));
+    }
+
+    #[test]
+    fn a_trailing_partial_period_stands_for_its_own_length() {
+        let trace = looping(0, 8, 50);
+        let periodic = |sample| SamplingMode::Periodic {
+            period: 20,
+            sample,
+            warmup: 0,
+        };
+        assert_eq!(
+            periodic(5).slices(&trace).unwrap(),
+            [(15..20, 0.4), (35..40, 0.4), (45..50, 0.2)]
+        );
+        // A remainder shorter than the sample is measured whole.
+        assert_eq!(
+            periodic(15).slices(&trace).unwrap(),
+            [(5..20, 0.4), (25..40, 0.4), (40..50, 0.2)]
+        );
+        // A trace within one period is a single partial period.
+        assert_eq!(periodic(5).slices(&trace[..12]).unwrap(), [(7..12, 1.0)]);
+    }
+
+    #[test]
+    fn random_slices_are_sorted_seeded_and_inside_the_trace() {
+        let trace = looping(0, 8, 1000);
+        let mode = SamplingMode::Random {
+            intervals: 6,
+            sample: 50,
+            warmup: 0,
+            seed: 9,
+        };
+        let slices = mode.slices(&trace).unwrap();
+        assert_eq!(slices, mode.slices(&trace).unwrap());
+        assert_eq!(slices.len(), 6);
+        assert!(
+            slices
+                .windows(2)
+                .all(|pair| pair[0].0.start <= pair[1].0.start)
+        );
+        assert!(
+            slices.iter().all(|(range, weight)| range.len() == 50
+                && range.end <= 1000
+                && *weight == 1.0 / 6.0)
+        );
+    }
+
+    #[test]
+    fn cold_slices_widen_the_bound_to_cover_the_full_run() {
+        let trace = looping(0, 8, 100);
+        let mode = SamplingMode::Periodic {
+            period: 20,
+            sample: 10,
+            warmup: 0,
+        };
+        let config = CacheConfig::default();
+        let estimate = estimate(&trace, &config, &mode).unwrap();
+        // Each unwarmed slice misses on its first touch of all eight blocks.
+        assert!(estimate.slices.iter().all(|slice| slice.cold_accesses == 8));
+        assert!((estimate.hit_rate - 0.2).abs() < 1e-12);
+        assert!((estimate.cold_start_bias - 0.8).abs() < 1e-12);
+        let full = Cache::new(config).unwrap().run_trace(&trace).hit_rate();
+        let bound = estimate.error_bound.unwrap();
+        assert!(full > estimate.hit_rate && full <= estimate.hit_rate + bound);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::cache::CacheStats;
+use std::io::{self, Write};
+
+/// Counters for one interval of a run.
+#[derive(Debug, Clone)]
+pub struct IntervalStats {
+    /// Position of the interval's first access in the trace.
+    pub start: usize,
+    pub stats: CacheStats,
+}
+
+/// Per-interval stats of a run, in trace order. Only measured accesses are
+/// counted, so intervals inside a warm-up are empty.
+#[derive(Debug, Clone)]
+pub struct TimeSeries {
+    /// Accesses per interval; the last one may be shorter.
+    pub interval: usize,
+    pub intervals: Vec<IntervalStats>,
+}
+
+impl TimeSeries {
+    pub fn hit_rates(&self) -> Vec<f64> {
+        self.intervals
+            .iter()
+            .map(|interval| interval.stats.hit_rate())
+            .collect()
+    }
+
+    /// The biggest change in hit rate between neighbouring measured intervals,
+    /// as the start of the later interval and the change.
+    pub fn largest_shift(&self) -> Option<(usize, f64)> {
+        let measured: Vec<&IntervalStats> = self
+            .intervals
+            .iter()
+            .filter(|interval| interval.stats.accesses > 0)
+            .collect();
+        measured
+            .windows(2)
+            .map(|pair| {
+                (
+                    pair[1].start,
+                    pair[1].stats.hit_rate() - pair[0].stats.hit_rate(),
+                )
+            })
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+    }
+
+    /// Column names matching [`TimeSeries::write_csv`].
+    pub fn write_csv_header(mut writer: impl Write) -> io::Result<()> {
+        writeln!(
+            writer,
+            "label,start,accesses,hits,misses,hit_rate,victim_hits,first_hit_rate,prediction_accuracy"
+        )
+    }
+
+    /// One CSV row per interval, each starting with `label` as a quoted
+    /// field. The prediction columns are left empty without a predictor.
+    pub fn write_csv(&self, label: &str, mut writer: impl Write) -> io::Result<()> {
+        let label = format!("\"{}\"", label.replace('"', "\"\""));
+        for interval in &self.intervals {
+            let stats = &interval.stats;
+            let (first_hit_rate, accuracy) = match &stats.prediction {
+                Some(prediction) => (
+                    format!("{:.6}", prediction.first_hit_rate()),
+                    format!("{:.6}", prediction.accuracy()),
+                ),
+                None => (String::new(), String::new()),
+            };
+            writeln!(
+                writer,
+                "{label},{},{},{},{},{:.6},{},{first_hit_rate},{accuracy}",
+                interval.start,
+                stats.accesses,
+                stats.hits,
+                stats.misses,
+                stats.hit_rate(),
+                stats.victim_hits
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheConfig;
+
+    #[test]
+    fn labels_are_quoted_csv_fields() {
+        let series = TimeSeries {
+            interval: 10,
+            intervals: vec![IntervalStats {
+                start: 0,
+                stats: CacheStats::new(&CacheConfig::default()),
+            }],
+        };
+        let mut csv = Vec::new();
+        series.write_csv("a,\"b\"", &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "\"a,\"\"b\"\"\",0,0,0,0,0.000000,0,,\n"
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use crate::trace::{AccessKind, TraceAccess};
+use std::{collections::HashMap, ops::Range};
+
+/// Bytes of one block, one bit per byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ByteMask(Vec<u64>);
+
+impl ByteMask {
+    fn empty(block_size: u64) -> Self {
+        Self(vec![0; block_size.div_ceil(64) as usize])
+    }
+
+    fn set_range(&mut self, bytes: Range<u64>) {
+        for byte in bytes {
+            self.0[(byte / 64) as usize] |= 1 << (byte % 64);
+        }
+    }
+
+    fn union(&mut self, other: &ByteMask) {
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word |= other;
+        }
+    }
+
+    fn overlaps(&self, other: &ByteMask) -> bool {
+        self.0.iter().zip(&other.0).any(|(a, b)| a & b != 0)
+    }
+
+    /// Runs of set bytes, in order.
+    fn ranges(&self) -> Vec<Range<u32>> {
+        let mut ranges = Vec::new();
+        let mut start = None;
+        let bits = self.0.len() as u32 * 64;
+        for byte in 0..=bits {
+            let set = byte < bits && (self.0[(byte / 64) as usize] >> (byte % 64)) & 1 != 0;
+            match (set, start) {
+                (true, None) => start = Some(byte),
+                (false, Some(first)) => {
+                    ranges.push(first..byte);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        ranges
+    }
+}
+
+/// One thread's valid copy of a block and the bytes it has touched since
+/// fetching it.
+#[derive(Debug, Clone)]
+struct HeldCopy {
+    thread: u32,
+    touched: ByteMask,
+}
+
+#[derive(Debug, Clone, Default)]
+struct BlockSharing {
+    copies: Vec<HeldCopy>,
+    /// Every byte each thread has touched in the block, across copies.
+    footprint: Vec<(u32, ByteMask)>,
+    true_sharing: u64,
+    false_sharing: u64,
+}
+
+/// Invalidation counts for one block and the bytes each thread touched.
+#[derive(Debug, Clone)]
+pub struct BlockSharingReport {
+    pub block_address: u64,
+    pub true_sharing: u64,
+    pub false_sharing: u64,
+    /// Byte offsets within the block touched by each thread, by thread id.
+    pub threads: Vec<(u32, Vec<Range<u32>>)>,
+}
+
+/// Whole-trace totals plus the blocks with the most false sharing.
+#[derive(Debug, Clone, Default)]
+pub struct SharingReport {
+    pub accesses: u64,
+    pub threads: usize,
+    /// Blocks touched by more than one thread.
+    pub shared_blocks: usize,
+    pub invalidations: u64,
+    pub true_sharing: u64,
+    pub false_sharing: u64,
+    /// Sorted by false-sharing invalidations, then by block address.
+    pub top_blocks: Vec<BlockSharingReport>,
+}
+
+impl SharingReport {
+    /// Fraction of invalidations caused by false sharing.
+    pub fn false_sharing_ratio(&self) -> f64 {
+        if self.invalidations == 0 {
+            0.0
+        } else {
+            self.false_sharing as f64 / self.invalidations as f64
+        }
+    }
+}
+
+/// Classifies the invalidations a multi-threaded trace causes as true or
+/// false sharing.
+///
+/// This is an idealised model, separate from [`crate::coherence`] and
+/// [`crate::directory`]: every thread gets an unbounded private cache kept
+/// coherent by invalidation, so each invalidation counted is caused by
+/// sharing rather than capacity, conflicts or the core a thread runs on.
+/// When a write invalidates another thread's copy, the invalidation is true
+/// sharing if the written bytes overlap the bytes that thread touched while
+/// it held the copy, and false sharing otherwise.
+pub struct SharingAnalysis {
+    block_size: u64,
+    blocks: HashMap<u64, BlockSharing>,
+    accesses: u64,
+    invalidations: u64,
+    true_sharing: u64,
+    false_sharing: u64,
+}
+
+impl SharingAnalysis {
+    /// `block_size` is the coherence granularity in bytes.
+    pub fn new(block_size: usize) -> Self {
+        Self {
+            block_size: block_size.max(1) as u64,
+            blocks: HashMap::new(),
+            accesses: 0,
+            invalidations: 0,
+            true_sharing: 0,
+            false_sharing: 0,
+        }
+    }
+
+    pub fn run_trace(&mut self, trace: &[TraceAccess]) {
+        for access in trace {
+            self.access(access);
+        }
+    }
+
+    /// Applies one access, splitting it across every block it spans.
+    pub fn access(&mut self, access: &TraceAccess) {
+        self.accesses += 1;
+        let end = access.address.saturating_add(access.size.max(1));
+        let mut address = access.address;
+        while address < end {
+            let block_address = address / self.block_size;
+            let block_end = ((block_address + 1) * self.block_size).min(end);
+            let offset = address % self.block_size;
+            let mut bytes = ByteMask::empty(self.block_size);
+            bytes.set_range(offset..offset + (block_end - address));
+            self.touch(block_address, access.thread, access.kind, bytes);
+            address = block_end;
+        }
+    }
+
+    fn touch(&mut self, block_address: u64, thread: u32, kind: AccessKind, bytes: ByteMask) {
+        let block = self.blocks.entry(block_address).or_default();
+        if kind == AccessKind::Write {
+            let mut invalidated = Vec::new();
+            block.copies.retain(|copy| {
+                if copy.thread == thread {
+                    return true;
+                }
+                invalidated.push(copy.touched.overlaps(&bytes));
+                false
+            });
+            for true_sharing in invalidated {
+                self.invalidations += 1;
+                if true_sharing {
+                    block.true_sharing += 1;
+                    self.true_sharing += 1;
+                } else {
+                    block.false_sharing += 1;
+                    self.false_sharing += 1;
+                }
+            }
+        }
+
+        match block.copies.iter_mut().find(|copy| copy.thread == thread) {
+            Some(copy) => copy.touched.union(&bytes),
+            None => block.copies.push(HeldCopy {
+                thread,
+                touched: bytes.clone(),
+            }),
+        }
+        match block
+            .footprint
+            .iter_mut()
+            .find(|(owner, _)| *owner == thread)
+        {
+            Some((_, footprint)) => footprint.union(&bytes),
+            None => block.footprint.push((thread, bytes)),
+        }
+    }
+
+    /// Totals so far plus the `top` blocks with the most false sharing.
+    pub fn report(&self, top: usize) -> SharingReport {
+        let mut threads: Vec<u32> = self
+            .blocks
+            .values()
+            .flat_map(|block| block.footprint.iter().map(|(thread, _)| *thread))
+            .collect();
+        threads.sort_unstable();
+        threads.dedup();
+
+        let mut offenders: Vec<(&u64, &BlockSharing)> = self
+            .blocks
+            .iter()
+            .filter(|(_, block)| block.false_sharing > 0)
+            .collect();
+        offenders.sort_by(|(a_addr, a), (b_addr, b)| {
+            b.false_sharing
+                .cmp(&a.false_sharing)
+                .then(a_addr.cmp(b_addr))
+        });
+        let top_blocks = offenders
+            .into_iter()
+            .take(top)
+            .map(|(&block_address, block)| {
+                let mut threads: Vec<(u32, Vec<Range<u32>>)> = block
+                    .footprint
+                    .iter()
+                    .map(|(thread, bytes)| (*thread, bytes.ranges()))
+                    .collect();
+                threads.sort_by_key(|(thread, _)| *thread);
+                BlockSharingReport {
+                    block_address,
+                    true_sharing: block.true_sharing,
+                    false_sharing: block.false_sharing,
+                    threads,
+                }
+            })
+            .collect();
+
+        SharingReport {
+            accesses: self.accesses,
+            threads: threads.len(),
+            shared_blocks: self
+                .blocks
+                .values()
+                .filter(|block| block.footprint.len() > 1)
+                .count(),
+            invalidations: self.invalidations,
+            true_sharing: self.true_sharing,
+            false_sharing: self.false_sharing,
+            top_blocks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access(kind: AccessKind, thread: u32, address: u64, size: u64) -> TraceAccess {
+        TraceAccess {
+            kind,
+            address,
+            thread,
+            size,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn invalidations_are_true_sharing_only_when_bytes_overlap() {
+        let mut analysis = SharingAnalysis::new(32);
+        analysis.run_trace(&[
+            access(AccessKind::Write, 0, 0, 4),
+            access(AccessKind::Read, 1, 16, 4),
+            // Thread 1 only touched bytes 16..20, so this is false sharing.
+            access(AccessKind::Write, 0, 0, 4),
+            access(AccessKind::Read, 1, 0, 4),
+            access(AccessKind::Write, 0, 2, 1),
+        ]);
+        let report = analysis.report(5);
+        assert_eq!((report.threads, report.shared_blocks), (2, 1));
+        assert_eq!(
+            (
+                report.invalidations,
+                report.true_sharing,
+                report.false_sharing
+            ),
+            (2, 1, 1)
+        );
+        assert_eq!(report.false_sharing_ratio(), 0.5);
+        let block = &report.top_blocks[0];
+        assert_eq!(block.block_address, 0);
+        assert_eq!(
+            format!("{:?}", block.threads),
+            "[(0, [0..4]), (1, [0..4, 16..20])]"
+        );
+    }
+
+    #[test]
+    fn accesses_are_split_across_the_blocks_they_span() {
+        let mut analysis = SharingAnalysis::new(32);
+        analysis.run_trace(&[
+            access(AccessKind::Read, 1, 30, 1),
+            access(AccessKind::Read, 1, 32, 1),
+            access(AccessKind::Write, 0, 28, 8),
+        ]);
+        let report = analysis.report(5);
+        assert_eq!(report.accesses, 3);
+        assert_eq!((report.invalidations, report.true_sharing), (2, 2));
+        assert_eq!(report.shared_blocks, 2);
+        assert!(report.top_blocks.is_empty());
+    }
+}
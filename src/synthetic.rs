@@ -0,0 +1,593 @@
+use crate::{
+    rng::SplitMix64,
+    trace::{AccessKind, TraceAccess, TraceFile},
+};
+use std::{fmt, str::FromStr};
+
+/// Components of a [`Pattern::Mixture`] are placed this far apart.
+pub const MIXTURE_REGION_SHIFT: u32 = 32;
+
+/// Zipf sampling keeps one cumulative weight per element.
+const MAX_ZIPF_ELEMENTS: u64 = 1 << 24;
+
+/// A synthetic trace pattern, as given, and why it cannot be generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    pub pattern: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} pattern: {}", self.pattern, self.reason)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// Address pattern of a synthetic trace. Sizes are in bytes and addresses
+/// are offsets from [`SyntheticConfig::base_address`], in steps of the access size.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// Consecutive elements, never revisited.
+    Sequential,
+    /// One element every `stride` bytes, never revisited.
+    Strided { stride: u64 },
+    /// Uniformly random elements of a `working_set`-byte region.
+    Uniform { working_set: u64 },
+    /// Elements of a `working_set`-byte region with Zipf popularity of
+    /// `exponent`; the lowest addresses are the hottest.
+    Zipf { working_set: u64, exponent: f64 },
+    /// Follows one random cycle through `nodes` nodes of `node_size` bytes,
+    /// as a linked-list traversal does.
+    PointerChase { nodes: u64, node_size: u64 },
+    /// Sweeps a `footprint`-byte region sequentially, over and over; a
+    /// footprint just larger than the cache defeats LRU.
+    Loop { footprint: u64 },
+    /// Each access comes from a component picked by weight. Component `i`
+    /// lives `i << MIXTURE_REGION_SHIFT` bytes above the base address.
+    Mixture(Vec<(u32, Pattern)>),
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Sequential => write!(f, "sequential"),
+            Pattern::Strided { stride } => write!(f, "stride {}", Bytes(*stride)),
+            Pattern::Uniform { working_set } => write!(f, "uniform {}", Bytes(*working_set)),
+            Pattern::Zipf {
+                working_set,
+                exponent,
+            } => write!(f, "zipf({exponent}) {}", Bytes(*working_set)),
+            Pattern::PointerChase { nodes, node_size } => {
+                write!(f, "chase {nodes}x{}", Bytes(*node_size))
+            }
+            Pattern::Loop { footprint } => write!(f, "loop {}", Bytes(*footprint)),
+            Pattern::Mixture(components) => {
+                let parts: Vec<String> = components
+                    .iter()
+                    .map(|(weight, pattern)| format!("{weight}x {pattern}"))
+                    .collect();
+                write!(f, "mix[{}]", parts.join(", "))
+            }
+        }
+    }
+}
+
+/// Byte count shown in KiB or MiB when it divides evenly.
+struct Bytes(u64);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            bytes if bytes >= 1 << 20 && bytes.is_multiple_of(1 << 20) => {
+                write!(f, "{}MiB", bytes >> 20)
+            }
+            bytes if bytes >= 1 << 10 && bytes.is_multiple_of(1 << 10) => {
+                write!(f, "{}KiB", bytes >> 10)
+            }
+            bytes => write!(f, "{bytes}B"),
+        }
+    }
+}
+
+impl Pattern {
+    fn validate(&self, access_size: u64) -> Result<(), PatternError> {
+        let invalid = |reason| {
+            Err(PatternError {
+                pattern: self.to_string(),
+                reason,
+            })
+        };
+        match self {
+            Pattern::Sequential => Ok(()),
+            Pattern::Strided { stride } if *stride == 0 => invalid("the stride must be non-zero"),
+            Pattern::Uniform { working_set }
+            | Pattern::Zipf { working_set, .. }
+            | Pattern::Loop {
+                footprint: working_set,
+            } if *working_set < access_size => invalid("the region must hold at least one access"),
+            Pattern::Zipf { exponent, .. } if !(*exponent >= 0.0 && exponent.is_finite()) => {
+                invalid("the exponent must be finite and non-negative")
+            }
+            Pattern::Zipf { working_set, .. } if working_set / access_size > MAX_ZIPF_ELEMENTS => {
+                invalid("at most 2^24 elements are supported")
+            }
+            Pattern::PointerChase { nodes, node_size } if *nodes == 0 || *node_size == 0 => {
+                invalid("the list needs at least one non-empty node")
+            }
+            Pattern::PointerChase { nodes, .. } if *nodes > u32::MAX as u64 => {
+                invalid("at most 2^32 - 1 nodes are supported")
+            }
+            Pattern::Mixture(components) => {
+                if components.is_empty() || components.iter().all(|(weight, _)| *weight == 0) {
+                    return invalid("a mixture needs a component with non-zero weight");
+                }
+                components
+                    .iter()
+                    .try_for_each(|(_, pattern)| pattern.validate(access_size))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A seeded, parameterised synthetic trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticConfig {
+    pub pattern: Pattern,
+    pub accesses: usize,
+    pub base_address: u64,
+    /// Bytes per access; element addresses are aligned to it.
+    pub access_size: u64,
+    /// Fraction of accesses that are writes.
+    pub write_ratio: f64,
+    pub seed: u64,
+}
+
+impl SyntheticConfig {
+    /// `pattern` with 100k 4-byte accesses, a quarter of them writes.
+    pub fn new(pattern: Pattern) -> Self {
+        Self {
+            pattern,
+            accesses: 100_000,
+            base_address: 0x1000_0000,
+            access_size: 4,
+            write_ratio: 0.25,
+            seed: 1,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), PatternError> {
+        if self.access_size == 0 {
+            return Err(PatternError {
+                pattern: self.pattern.to_string(),
+                reason: "the access size must be non-zero",
+            });
+        }
+        if !(0.0..=1.0).contains(&self.write_ratio) {
+            return Err(PatternError {
+                pattern: self.pattern.to_string(),
+                reason: "the write ratio must be between 0 and 1",
+            });
+        }
+        self.pattern.validate(self.access_size)
+    }
+
+    /// Generates the trace, named `name`. The same config always produces
+    /// the same trace.
+    pub fn generate(&self, name: impl Into<String>) -> Result<TraceFile, PatternError> {
+        self.validate()?;
+        let mut rng = SplitMix64::new(self.seed);
+        let mut state = PatternState::new(&self.pattern, self.access_size, &mut rng);
+        let entries = (0..self.accesses)
+            .map(|index| {
+                let offset = state.next(self.access_size, &mut rng);
+                let kind = if rng.next_f64() < self.write_ratio {
+                    AccessKind::Write
+                } else {
+                    AccessKind::Read
+                };
+                TraceAccess {
+                    kind,
+                    address: self.base_address.wrapping_add(offset),
+                    thread: 0,
+                    size: self.access_size,
+                    timestamp: index as u64,
+                }
+            })
+            .collect();
+        Ok(TraceFile {
+            name: name.into(),
+            entries,
+        })
+    }
+}
+
+/// Where a pattern is up to.
+enum PatternState {
+    Stream {
+        step: u64,
+        position: u64,
+    },
+    Uniform {
+        elements: u64,
+    },
+    /// Cumulative popularity of each element.
+    Zipf {
+        cdf: Vec<f64>,
+    },
+    Chase {
+        next: Vec<u32>,
+        current: u32,
+        node_size: u64,
+    },
+    Loop {
+        elements: u64,
+        position: u64,
+    },
+    Mixture {
+        total: u64,
+        components: Vec<(u64, PatternState)>,
+    },
+}
+
+impl PatternState {
+    fn new(pattern: &Pattern, access_size: u64, rng: &mut SplitMix64) -> Self {
+        match pattern {
+            Pattern::Sequential => PatternState::Stream {
+                step: access_size,
+                position: 0,
+            },
+            Pattern::Strided { stride } => PatternState::Stream {
+                step: *stride,
+                position: 0,
+            },
+            Pattern::Uniform { working_set } => PatternState::Uniform {
+                elements: working_set / access_size,
+            },
+            Pattern::Zipf {
+                working_set,
+                exponent,
+            } => {
+                let elements = working_set / access_size;
+                let mut total = 0.0;
+                let mut cdf: Vec<f64> = (1..=elements)
+                    .map(|rank| {
+                        total += 1.0 / (rank as f64).powf(*exponent);
+                        total
+                    })
+                    .collect();
+                for weight in &mut cdf {
+                    *weight /= total;
+                }
+                PatternState::Zipf { cdf }
+            }
+            Pattern::PointerChase { nodes, node_size } => {
+                // Sattolo's shuffle yields a single cycle through every node.
+                let mut next: Vec<u32> = (0..*nodes as u32).collect();
+                for i in (1..next.len()).rev() {
+                    let j = rng.below(i as u64) as usize;
+                    next.swap(i, j);
+                }
+                PatternState::Chase {
+                    next,
+                    current: 0,
+                    node_size: *node_size,
+                }
+            }
+            Pattern::Loop { footprint } => PatternState::Loop {
+                elements: footprint / access_size,
+                position: 0,
+            },
+            Pattern::Mixture(components) => PatternState::Mixture {
+                total: components.iter().map(|(weight, _)| *weight as u64).sum(),
+                components: components
+                    .iter()
+                    .map(|(weight, pattern)| {
+                        (*weight as u64, PatternState::new(pattern, access_size, rng))
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    /// Offset of the next access from the base address.
+    fn next(&mut self, access_size: u64, rng: &mut SplitMix64) -> u64 {
+        match self {
+            PatternState::Stream { step, position } => {
+                let offset = *position;
+                *position = position.wrapping_add(*step);
+                offset
+            }
+            PatternState::Uniform { elements } => rng.below(*elements) * access_size,
+            PatternState::Zipf { cdf } => {
+                let draw = rng.next_f64();
+                let element = cdf.partition_point(|&weight| weight <= draw);
+                element.min(cdf.len() - 1) as u64 * access_size
+            }
+            PatternState::Chase {
+                next,
+                current,
+                node_size,
+            } => {
+                let offset = *current as u64 * *node_size;
+                *current = next[*current as usize];
+                offset
+            }
+            PatternState::Loop { elements, position } => {
+                let offset = *position * access_size;
+                *position = (*position + 1) % *elements;
+                offset
+            }
+            PatternState::Mixture { total, components } => {
+                let mut draw = rng.below(*total);
+                for (index, (weight, state)) in components.iter_mut().enumerate() {
+                    if draw < *weight {
+                        let region = (index as u64) << MIXTURE_REGION_SHIFT;
+                        return region + state.next(access_size, rng);
+                    }
+                    draw -= *weight;
+                }
+                unreachable!("the draw is below the total weight")
+            }
+        }
+    }
+}
+
+/// Parses the compact spelling used on the command line: `sequential`,
+/// `stride:256`, `uniform:1M`, `zipf:1M:0.9`, `chase:4096:64`, `loop:320K`,
+/// or `mix:3*zipf:1M:0.9+1*sequential`. Sizes take an optional `K`, `M` or
+/// `G` suffix; mixture weights default to 1.
+impl FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| PatternError {
+            pattern: spec.to_string(),
+            reason,
+        };
+        if let Some(components) = spec.strip_prefix("mix:") {
+            return components
+                .split('+')
+                .map(|component| {
+                    let (weight, pattern) = match component.split_once('*') {
+                        Some((weight, pattern)) => (
+                            weight
+                                .parse()
+                                .map_err(|_| invalid("mixture weights are whole numbers"))?,
+                            pattern,
+                        ),
+                        None => (1, component),
+                    };
+                    Ok((weight, pattern.parse()?))
+                })
+                .collect::<Result<_, _>>()
+                .map(Pattern::Mixture);
+        }
+        let mut parts = spec.split(':');
+        let kind = parts.next().unwrap_or_default();
+        let args: Vec<&str> = parts.collect();
+        let size = |index: usize| {
+            args.get(index)
+                .and_then(|arg| parse_size(arg))
+                .ok_or_else(|| invalid("expected a size such as 4096, 64K or 1M"))
+        };
+        let pattern = match (kind, args.len()) {
+            ("sequential", 0) => Pattern::Sequential,
+            ("stride", 1) => Pattern::Strided { stride: size(0)? },
+            ("uniform", 1) => Pattern::Uniform {
+                working_set: size(0)?,
+            },
+            ("zipf", 2) => Pattern::Zipf {
+                working_set: size(0)?,
+                exponent: args[1]
+                    .parse()
+                    .map_err(|_| invalid("expected a Zipf exponent such as 0.9"))?,
+            },
+            ("chase", 2) => Pattern::PointerChase {
+                nodes: size(0)?,
+                node_size: size(1)?,
+            },
+            ("loop", 1) => Pattern::Loop {
+                footprint: size(0)?,
+            },
+            _ => return Err(invalid("unknown pattern or wrong number of arguments")),
+        };
+        Ok(pattern)
+    }
+}
+
+/// `4096`, `64K`, `1M` or `2G`.
+fn parse_size(token: &str) -> Option<u64> {
+    let (digits, scale) = match token.as_bytes().last()? {
+        b'K' | b'k' => (&token[..token.len() - 1], 1 << 10),
+        b'M' | b'm' => (&token[..token.len() - 1], 1 << 20),
+        b'G' | b'g' => (&token[..token.len() - 1], 1 << 30),
+        _ => (token, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(pattern: Pattern, accesses: usize) -> Vec<u64> {
+        let config = SyntheticConfig {
+            accesses,
+            base_address: 0,
+            ..SyntheticConfig::new(pattern)
+        };
+        config
+            .generate("test")
+            .unwrap()
+            .entries
+            .iter()
+            .map(|access| access.address)
+            .collect()
+    }
+
+    #[test]
+    fn streams_and_loops_step_through_their_region() {
+        assert_eq!(offsets(Pattern::Sequential, 4), [0, 4, 8, 12]);
+        assert_eq!(offsets(Pattern::Strided { stride: 256 }, 3), [0, 256, 512]);
+        assert_eq!(
+            offsets(Pattern::Loop { footprint: 12 }, 7),
+            [0, 4, 8, 0, 4, 8, 0]
+        );
+    }
+
+    #[test]
+    fn pointer_chases_visit_every_node_once_per_cycle() {
+        let nodes = 64;
+        let visits = offsets(
+            Pattern::PointerChase {
+                nodes,
+                node_size: 32,
+            },
+            2 * nodes as usize,
+        );
+        let mut first_cycle = visits[..nodes as usize].to_vec();
+        first_cycle.sort_unstable();
+        assert_eq!(
+            first_cycle,
+            (0..nodes).map(|node| node * 32).collect::<Vec<_>>()
+        );
+        assert_eq!(visits[..nodes as usize], visits[nodes as usize..]);
+    }
+
+    #[test]
+    fn random_patterns_stay_in_their_working_set_and_zipf_favours_low_addresses() {
+        let working_set = 4096;
+        let uniform = offsets(Pattern::Uniform { working_set }, 10_000);
+        let zipf = offsets(
+            Pattern::Zipf {
+                working_set,
+                exponent: 1.0,
+            },
+            10_000,
+        );
+        for trace in [&uniform, &zipf] {
+            assert!(
+                trace
+                    .iter()
+                    .all(|&offset| offset < working_set && offset % 4 == 0)
+            );
+        }
+        let hottest = |trace: &[u64]| trace.iter().filter(|&&offset| offset < 64).count();
+        assert!(hottest(&zipf) > 5 * hottest(&uniform));
+    }
+
+    #[test]
+    fn mixtures_place_components_in_separate_regions_by_weight() {
+        let trace = offsets(
+            Pattern::Mixture(vec![(3, Pattern::Sequential), (1, Pattern::Sequential)]),
+            8_000,
+        );
+        let second = trace
+            .iter()
+            .filter(|&&offset| offset >> MIXTURE_REGION_SHIFT == 1)
+            .count();
+        assert!((1_600..2_400).contains(&second), "{second}");
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_trace() {
+        let config = SyntheticConfig::new(Pattern::Uniform {
+            working_set: 1 << 20,
+        });
+        let trace = config.generate("a").unwrap().entries;
+        assert_eq!(trace.len(), config.accesses);
+        assert_eq!(config.generate("b").unwrap().entries, trace);
+        let reseeded = SyntheticConfig { seed: 2, ..config };
+        assert_ne!(reseeded.generate("c").unwrap().entries, trace);
+    }
+
+    #[test]
+    fn specs_parse_into_patterns() {
+        assert_eq!("sequential".parse(), Ok(Pattern::Sequential));
+        assert_eq!("stride:256".parse(), Ok(Pattern::Strided { stride: 256 }));
+        assert_eq!(
+            "chase:4K:64".parse(),
+            Ok(Pattern::PointerChase {
+                nodes: 4096,
+                node_size: 64
+            })
+        );
+        assert_eq!(
+            "mix:3*zipf:1M:0.9+loop:320K".parse(),
+            Ok(Pattern::Mixture(vec![
+                (
+                    3,
+                    Pattern::Zipf {
+                        working_set: 1 << 20,
+                        exponent: 0.9
+                    }
+                ),
+                (
+                    1,
+                    Pattern::Loop {
+                        footprint: 320 << 10
+                    }
+                ),
+            ]))
+        );
+        for spec in [
+            "stride",
+            "uniform:lots",
+            "zipf:1M:hot",
+            "mix:x*sequential",
+            "spiral:1K",
+        ] {
+            let err = spec.parse::<Pattern>().unwrap_err();
+            assert_eq!(err.pattern, spec);
+        }
+    }
+
+    #[test]
+    fn unusable_parameters_are_rejected() {
+        let rejected = |config: SyntheticConfig| config.generate("bad").unwrap_err().reason;
+        let config = |pattern| SyntheticConfig::new(pattern);
+        assert_eq!(
+            rejected(config(Pattern::Strided { stride: 0 })),
+            "the stride must be non-zero"
+        );
+        assert_eq!(
+            rejected(config(Pattern::Loop { footprint: 2 })),
+            "the region must hold at least one access"
+        );
+        assert_eq!(
+            rejected(config(Pattern::Zipf {
+                working_set: 4096,
+                exponent: f64::NAN
+            })),
+            "the exponent must be finite and non-negative"
+        );
+        assert_eq!(
+            rejected(config(Pattern::PointerChase {
+                nodes: 0,
+                node_size: 64
+            })),
+            "the list needs at least one non-empty node"
+        );
+        assert_eq!(
+            rejected(config(Pattern::Mixture(vec![(0, Pattern::Sequential)]))),
+            "a mixture needs a component with non-zero weight"
+        );
+        assert_eq!(
+            rejected(SyntheticConfig {
+                access_size: 0,
+                ..config(Pattern::Sequential)
+            }),
+            "the access size must be non-zero"
+        );
+        assert_eq!(
+            rejected(SyntheticConfig {
+                write_ratio: 1.5,
+                ..config(Pattern::Sequential)
+            }),
+            "the write ratio must be between 0 and 1"
+        );
+    }
+}
@@ -1,10 +1,14 @@
 use std::{
     fmt,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{self, BufRead, BufReader, Write},
     path::Path,
 };
 
+/// Bytes assumed per access when the trace does not record a size.
+pub const DEFAULT_ACCESS_SIZE: u64 = 4;
+
+/// Direction of a memory reference.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AccessKind {
     Read,
@@ -20,12 +24,46 @@ impl fmt::Display for AccessKind {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// One memory reference from a trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TraceAccess {
     pub kind: AccessKind,
     pub address: u64,
+    /// Issuing thread; 0 for single-threaded traces.
+    pub thread: u32,
+    /// Bytes read or written, starting at `address`.
+    pub size: u64,
+    /// Issue time in the trace's own units; used to interleave traces.
+    pub timestamp: u64,
+}
+
+/// Why a trace could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceError {
+    /// The trace could not be opened or read.
+    Io { trace: String, message: String },
+    /// Line `line` (from 1) is not `<op> <address>`.
+    Malformed {
+        trace: String,
+        line: usize,
+        text: String,
+    },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Io { trace, message } => write!(f, "cannot read {trace}: {message}"),
+            TraceError::Malformed { trace, line, text } => {
+                write!(f, "{trace}:{line}: malformed trace line `{text}`")
+            }
+        }
+    }
 }
 
+impl std::error::Error for TraceError {}
+
+/// A named sequence of accesses, usually loaded from a `*.trace` file.
 #[derive(Debug, Clone)]
 pub struct TraceFile {
     pub name: String,
@@ -33,57 +71,202 @@ pub struct TraceFile {
 }
 
 impl TraceFile {
-    pub fn load(path: impl AsRef<Path>) -> Self {
+    /// Reads a trace file; the trace is named after the file name.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TraceError> {
         let path = path.as_ref();
-        let file = File::open(path).expect("trace file missing");
-        let reader = BufReader::new(file);
+        let file = File::open(path).map_err(|err| io_error(&path.display().to_string(), &err))?;
+        let name = path
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        Self::from_reader(name, BufReader::new(file))
+    }
+
+    /// Parses `<op> <address> [thread] [size] [timestamp]` lines; blank lines
+    /// and `#` comments are skipped. Addresses are hexadecimal, with or
+    /// without `0x`, or carry a `0b` or `0o` prefix. The thread id, size and
+    /// timestamp are decimal and default to 0, [`DEFAULT_ACCESS_SIZE`] and the
+    /// access's position in the trace. Anything from the first token that is
+    /// not a decimal number onwards is ignored.
+    pub fn from_reader(name: impl Into<String>, reader: impl BufRead) -> Result<Self, TraceError> {
+        let name = name.into();
         let mut entries = Vec::new();
-        for line in reader.lines() {
-            let line = line.expect("failed to read trace line");
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|err| io_error(&name, &err))?;
             let trimmed = line.trim();
             if trimmed.is_empty() || trimmed.starts_with('#') {
                 continue;
             }
             let mut parts = trimmed.split_whitespace();
-            let op = parts.next().unwrap();
-            let addr = parts.next().unwrap();
+            let op = parts.next().unwrap_or_default();
+            let Some(address) = parts.next().and_then(parse_address) else {
+                return Err(TraceError::Malformed {
+                    trace: name,
+                    line: index + 1,
+                    text: trimmed.to_string(),
+                });
+            };
             let kind = match op.to_ascii_lowercase().chars().next().unwrap_or('r') {
                 'r' => AccessKind::Read,
                 'w' => AccessKind::Write,
                 _ => AccessKind::Read,
             };
-            let address = parse_address(addr);
-            entries.push(TraceAccess { kind, address });
+            // The optional columns end at the first token that is not a
+            // number, so a trailing comment or extra column is ignored.
+            let mut optional = parts.map_while(|token| token.parse::<u64>().ok()).fuse();
+            let thread = optional
+                .next()
+                .map_or(0, |thread| u32::try_from(thread).unwrap_or(u32::MAX));
+            let size = optional.next().unwrap_or(DEFAULT_ACCESS_SIZE);
+            let timestamp = optional.next().unwrap_or(entries.len() as u64);
+            entries.push(TraceAccess {
+                kind,
+                address,
+                thread,
+                size,
+                timestamp,
+            });
         }
-        Self {
-            name: path
-                .file_name()
-                .map(|f| f.to_string_lossy().into_owned())
-                .unwrap_or_else(|| path.display().to_string()),
-            entries,
+        Ok(Self { name, entries })
+    }
+
+    /// Writes the trace in the format [`TraceFile::from_reader`] reads,
+    /// with every field spelled out.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        for access in &self.entries {
+            writeln!(
+                writer,
+                "{} {:#x} {} {} {}",
+                access.kind, access.address, access.thread, access.size, access.timestamp
+            )?;
         }
+        Ok(())
+    }
+}
+
+fn io_error(trace: &str, err: &io::Error) -> TraceError {
+    TraceError::Io {
+        trace: trace.to_string(),
+        message: err.to_string(),
     }
 }
 
-fn parse_address(token: &str) -> u64 {
+fn parse_address(token: &str) -> Option<u64> {
     let token = token.trim();
     if let Some(hex) = token
         .strip_prefix("0x")
         .or_else(|| token.strip_prefix("0X"))
     {
-        return u64::from_str_radix(hex, 16).unwrap();
+        return u64::from_str_radix(hex, 16).ok();
     }
     if let Some(bin) = token
         .strip_prefix("0b")
         .or_else(|| token.strip_prefix("0B"))
     {
-        return u64::from_str_radix(bin, 2).unwrap();
+        return u64::from_str_radix(bin, 2).ok();
     }
     if let Some(oct) = token
         .strip_prefix("0o")
         .or_else(|| token.strip_prefix("0O"))
     {
-        return u64::from_str_radix(oct, 8).unwrap();
+        return u64::from_str_radix(oct, 8).ok();
+    }
+    u64::from_str_radix(token, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<TraceAccess> {
+        TraceFile::from_reader("test", text.as_bytes())
+            .unwrap()
+            .entries
+    }
+
+    #[test]
+    fn addresses_accept_every_radix_prefix() {
+        let entries = parse("R 0x1f\nW 0b101\nr 0o17\nw ff\n");
+        let fields: Vec<_> = entries
+            .iter()
+            .map(|access| (access.kind, access.address))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (AccessKind::Read, 0x1f),
+                (AccessKind::Write, 0b101),
+                (AccessKind::Read, 0o17),
+                (AccessKind::Write, 0xff),
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_lines_and_comments_are_skipped() {
+        let entries = parse("# header\n\n  R 0x10\n   # indented comment\nW 0x20\n");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].address, 0x20);
+    }
+
+    #[test]
+    fn optional_columns_default_when_absent() {
+        let entries = parse("R 0x10 3\nW 20 1 8\nR 30\nW 40 2 4 100\n");
+        let fields: Vec<_> = entries
+            .iter()
+            .map(|access| (access.address, access.thread, access.size, access.timestamp))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (0x10, 3, DEFAULT_ACCESS_SIZE, 0),
+                (0x20, 1, 8, 1),
+                (0x30, 0, DEFAULT_ACCESS_SIZE, 2),
+                (0x40, 2, 4, 100),
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_comments_and_columns_are_ignored() {
+        let entries = parse("R 0x10 # stack\nW 0x20 2 pc=0x400 9\n");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            (entries[0].thread, entries[0].size),
+            (0, DEFAULT_ACCESS_SIZE)
+        );
+        assert_eq!(
+            (entries[1].thread, entries[1].size),
+            (2, DEFAULT_ACCESS_SIZE)
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_reported_with_their_line_number() {
+        for (text, line) in [("R 0x10\n\nW\n", 3), ("R 0x10\nR zz\n", 2)] {
+            match TraceFile::from_reader("bad", text.as_bytes()) {
+                Err(TraceError::Malformed {
+                    trace, line: at, ..
+                }) => assert_eq!((trace.as_str(), at), ("bad", line), "{text:?}"),
+                other => panic!("{text:?} parsed as {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn written_traces_read_back_unchanged() {
+        let trace = TraceFile {
+            name: "round-trip".to_string(),
+            entries: parse("R 0x10\nW 0x20 3 8 100\n"),
+        };
+        let mut text = Vec::new();
+        trace.write(&mut text).unwrap();
+        assert_eq!(parse(std::str::from_utf8(&text).unwrap()), trace.entries);
+    }
+
+    #[test]
+    fn missing_files_are_an_error() {
+        let err = TraceFile::load("no/such/dir/missing.trace").unwrap_err();
+        assert!(matches!(err, TraceError::Io { .. }), "{err}");
     }
-    u64::from_str_radix(token, 16).unwrap_or_else(|_| u64::from_str_radix(token, 10).unwrap())
 }
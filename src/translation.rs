@@ -0,0 +1,823 @@
+use crate::{
+    cache::{CacheConfig, ConfigError},
+    rng::SplitMix64,
+    trace::TraceAccess,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    ops::Range,
+};
+
+/// Translation granule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSize {
+    Base4K,
+    Huge2M,
+}
+
+impl PageSize {
+    pub fn bytes(self) -> u64 {
+        match self {
+            PageSize::Base4K => 4 * 1024,
+            PageSize::Huge2M => 2 * 1024 * 1024,
+        }
+    }
+}
+
+impl fmt::Display for PageSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageSize::Base4K => write!(f, "4K"),
+            PageSize::Huge2M => write!(f, "2M"),
+        }
+    }
+}
+
+/// How a free frame is picked for a page on its first touch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameAllocation {
+    /// The lowest free frame, so pages fill memory in first-touch order.
+    Sequential,
+    /// Any free frame, uniformly at random.
+    Random,
+    /// A random free frame of the color after the previous allocation's, so
+    /// pages touched one after another land in different cache bins.
+    BinHopping { colors: u64 },
+    /// A random free frame of the same color as the page, so that pages with
+    /// consecutive virtual numbers land in different cache bins. `colors`
+    /// usually comes from [`page_colors`].
+    PageColored { colors: u64 },
+}
+
+impl fmt::Display for FrameAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameAllocation::Sequential => write!(f, "sequential"),
+            FrameAllocation::Random => write!(f, "random"),
+            FrameAllocation::BinHopping { colors } => write!(f, "bin-hopping({colors})"),
+            FrameAllocation::PageColored { colors } => write!(f, "colored({colors})"),
+        }
+    }
+}
+
+/// Number of page colors `cache` has for `page`: how many pages fit in one way.
+pub fn page_colors(cache: &CacheConfig, page: PageSize) -> u64 {
+    let way_bytes = (cache.cache_size / cache.associativity.max(1)) as u64;
+    (way_bytes / page.bytes()).max(1)
+}
+
+impl FrameAllocation {
+    fn colors(self) -> Option<u64> {
+        match self {
+            FrameAllocation::BinHopping { colors } | FrameAllocation::PageColored { colors } => {
+                Some(colors)
+            }
+            FrameAllocation::Sequential | FrameAllocation::Random => None,
+        }
+    }
+}
+
+/// An OS-style page mapping: pages get frames on first touch and keep them
+/// until physical memory runs out, when the page mapped longest ago gives
+/// its frame up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageMapping {
+    pub page_size: PageSize,
+    pub allocation: FrameAllocation,
+    /// Bytes of physical memory frames are allocated from.
+    pub physical_memory: u64,
+    /// Share of frames, in `[0, 1)`, already held by other processes when the
+    /// trace starts, picked at random. It fragments the free memory, so the
+    /// lowest free frames no longer run through the colors in order.
+    pub occupied: f64,
+    /// Seed for frame allocation and for the frames other processes hold.
+    pub seed: u64,
+}
+
+impl Default for PageMapping {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::Base4K,
+            allocation: FrameAllocation::Random,
+            physical_memory: 4 * 1024 * 1024 * 1024,
+            occupied: 0.0,
+            seed: 1,
+        }
+    }
+}
+
+impl PageMapping {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let frames = self.physical_memory / self.page_size.bytes();
+        if frames == 0 {
+            return Err(ConfigError::PhysicalMemoryTooSmall {
+                bytes: self.physical_memory,
+                page: self.page_size.bytes(),
+            });
+        }
+        if let Some(colors) = self.allocation.colors()
+            && (colors == 0 || colors > frames)
+        {
+            return Err(ConfigError::InvalidPageColors { colors, frames });
+        }
+        if !(0.0..1.0).contains(&self.occupied) {
+            return Err(ConfigError::InvalidOccupiedMemory);
+        }
+        Ok(())
+    }
+}
+
+/// The frames other processes hold: a fixed pseudo-random subset, decided per
+/// frame so that it costs nothing to store.
+#[derive(Debug, Clone, Copy)]
+struct Occupancy {
+    seed: u64,
+    /// A frame is held when its hash falls below this.
+    threshold: u64,
+}
+
+impl Occupancy {
+    fn new(mapping: &PageMapping) -> Self {
+        Self {
+            seed: mapping.seed,
+            threshold: (mapping.occupied * u64::MAX as f64) as u64,
+        }
+    }
+
+    fn holds(self, frame: u64) -> bool {
+        self.threshold > 0
+            && SplitMix64::new(self.seed ^ frame.wrapping_mul(0xd6e8_feb8_6659_fd93)).next_u64()
+                < self.threshold
+    }
+}
+
+/// Frames not yet handed out, `base`, `base + stride`, ... , drawn without
+/// replacement by a lazy Fisher-Yates shuffle: slot `i` holds frame index `i`
+/// unless an earlier draw moved another index into it.
+#[derive(Debug, Clone)]
+struct FramePool {
+    base: u64,
+    stride: u64,
+    len: u64,
+    moved: HashMap<u64, u64>,
+}
+
+impl FramePool {
+    fn new(base: u64, stride: u64, len: u64) -> Self {
+        Self {
+            base,
+            stride,
+            len,
+            moved: HashMap::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes and returns a uniformly random free frame.
+    fn take_random(&mut self, rng: &mut SplitMix64) -> Option<u64> {
+        if self.is_empty() {
+            return None;
+        }
+        let slot = rng.below(self.len);
+        let last = self.len - 1;
+        let index = self.moved.remove(&slot).unwrap_or(slot);
+        if slot != last {
+            let tail = self.moved.remove(&last).unwrap_or(last);
+            self.moved.insert(slot, tail);
+        }
+        self.len = last;
+        Some(self.base + self.stride * index)
+    }
+
+    /// [`FramePool::take_random`], dropping frames other processes hold as
+    /// they come up; each is drawn at most once.
+    fn take_free(&mut self, rng: &mut SplitMix64, occupancy: Occupancy) -> Option<u64> {
+        loop {
+            let frame = self.take_random(rng)?;
+            if !occupancy.holds(frame) {
+                return Some(frame);
+            }
+        }
+    }
+}
+
+/// Frames handed out so far, and the allocator that picks new ones.
+#[derive(Debug, Clone)]
+pub(crate) struct PageTable {
+    allocation: FrameAllocation,
+    page_bytes: u64,
+    frames: u64,
+    entries: HashMap<u64, u64>,
+    /// Mapped pages, oldest first; the front one gives up its frame once
+    /// memory runs out.
+    mapped: VecDeque<u64>,
+    /// Free frames for the random and color-aware policies: one pool for
+    /// random allocation, one per color otherwise.
+    pools: Vec<FramePool>,
+    occupancy: Occupancy,
+    /// Lowest frame that may still be free, for sequential allocation.
+    next_frame: u64,
+    /// Color of the last frame allocated, for bin hopping.
+    last_color: Option<u64>,
+    /// Page that lost its frame to the last allocation, and the frame.
+    reclaimed: Option<(u64, u64)>,
+    rng: SplitMix64,
+}
+
+impl PageTable {
+    /// `mapping` must already be validated.
+    pub(crate) fn new(mapping: &PageMapping) -> Self {
+        let page_bytes = mapping.page_size.bytes();
+        let frames = mapping.physical_memory / page_bytes;
+        let pools = match mapping.allocation {
+            FrameAllocation::Sequential => Vec::new(),
+            FrameAllocation::Random => vec![FramePool::new(0, 1, frames)],
+            FrameAllocation::BinHopping { colors } | FrameAllocation::PageColored { colors } => {
+                let pool = |color| FramePool::new(color, colors, (frames - color).div_ceil(colors));
+                (0..colors).map(pool).collect()
+            }
+        };
+        Self {
+            allocation: mapping.allocation,
+            page_bytes,
+            frames,
+            entries: HashMap::new(),
+            mapped: VecDeque::new(),
+            pools,
+            occupancy: Occupancy::new(mapping),
+            next_frame: 0,
+            last_color: None,
+            reclaimed: None,
+            rng: SplitMix64::new(mapping.seed),
+        }
+    }
+
+    pub(crate) fn page_bytes(&self) -> u64 {
+        self.page_bytes
+    }
+
+    /// Pages mapped so far.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Physical address for `virtual_address`, mapping its page if needed.
+    pub(crate) fn translate(&mut self, virtual_address: u64) -> u64 {
+        let frame = self.frame(virtual_address / self.page_bytes);
+        frame * self.page_bytes + virtual_address % self.page_bytes
+    }
+
+    /// Frame for `page`, allocated on its first touch.
+    pub(crate) fn frame(&mut self, page: u64) -> u64 {
+        if let Some(&frame) = self.entries.get(&page) {
+            return frame;
+        }
+        let frame = match self.allocate(page) {
+            Some(frame) => frame,
+            None => self.reclaim(),
+        };
+        self.entries.insert(page, frame);
+        self.mapped.push_back(page);
+        frame
+    }
+
+    /// Takes the frame a page lost since the last call, with the page that
+    /// held it. Anything keyed by physical address must forget that frame.
+    pub(crate) fn take_reclaimed(&mut self) -> Option<(u64, u64)> {
+        self.reclaimed.take()
+    }
+
+    /// A free frame for `page`, or `None` once physical memory is full.
+    fn allocate(&mut self, page: u64) -> Option<u64> {
+        let frame = match self.allocation {
+            FrameAllocation::Sequential => {
+                while self.next_frame < self.frames && self.occupancy.holds(self.next_frame) {
+                    self.next_frame += 1;
+                }
+                let frame = (self.next_frame < self.frames).then_some(self.next_frame)?;
+                self.next_frame += 1;
+                frame
+            }
+            FrameAllocation::Random => self.pools[0].take_free(&mut self.rng, self.occupancy)?,
+            FrameAllocation::BinHopping { colors } => {
+                let color = self.last_color.map_or(0, |last| (last + 1) % colors);
+                self.colored_free(color)?
+            }
+            FrameAllocation::PageColored { colors } => self.colored_free(page % colors)?,
+        };
+        if let Some(colors) = self.allocation.colors() {
+            self.last_color = Some(frame % colors);
+        }
+        Some(frame)
+    }
+
+    /// A random free frame of `color`, or of the next color with one free
+    /// once it is full, as an OS would fall back.
+    fn colored_free(&mut self, color: u64) -> Option<u64> {
+        let colors = self.pools.len();
+        (0..colors)
+            .map(|step| (color as usize + step) % colors)
+            .find_map(|color| self.pools[color].take_free(&mut self.rng, self.occupancy))
+    }
+
+    /// Unmaps the page mapped longest ago and hands its frame over, as an
+    /// OS paging it out would. With no page of its own to give up, the
+    /// trace takes frame 0 from the other processes.
+    fn reclaim(&mut self) -> u64 {
+        let Some(victim) = self.mapped.pop_front() else {
+            return 0;
+        };
+        let frame = self
+            .entries
+            .remove(&victim)
+            .expect("mapped pages have a frame");
+        self.reclaimed = Some((victim, frame));
+        frame
+    }
+}
+
+/// A set-associative, LRU translation buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlbConfig {
+    pub entries: usize,
+    pub associativity: usize,
+}
+
+impl TlbConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.entries == 0
+            || self.associativity == 0
+            || !self.entries.is_multiple_of(self.associativity)
+        {
+            return Err(ConfigError::InvalidTlb {
+                entries: self.entries,
+                ways: self.associativity,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Virtual-to-physical translation in front of a cache.
+#[derive(Debug, Clone)]
+pub struct TranslationConfig {
+    pub page_size: PageSize,
+    pub allocation: FrameAllocation,
+    /// Bytes of physical memory frames are allocated from.
+    pub physical_memory: u64,
+    /// Share of frames already held by other processes (see [`PageMapping::occupied`]).
+    pub occupied: f64,
+    pub l1_tlb: TlbConfig,
+    pub l2_tlb: Option<TlbConfig>,
+    /// Seed for frame allocation.
+    pub seed: u64,
+}
+
+impl Default for TranslationConfig {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::Base4K,
+            allocation: FrameAllocation::Random,
+            physical_memory: 4 * 1024 * 1024 * 1024,
+            occupied: 0.0,
+            l1_tlb: TlbConfig {
+                entries: 64,
+                associativity: 4,
+            },
+            l2_tlb: Some(TlbConfig {
+                entries: 1536,
+                associativity: 12,
+            }),
+            seed: 1,
+        }
+    }
+}
+
+impl TranslationConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.l1_tlb.validate()?;
+        if let Some(l2) = &self.l2_tlb {
+            l2.validate()?;
+        }
+        self.mapping().validate()
+    }
+
+    /// The page-table half of this configuration.
+    pub fn mapping(&self) -> PageMapping {
+        PageMapping {
+            page_size: self.page_size,
+            allocation: self.allocation,
+            physical_memory: self.physical_memory,
+            occupied: self.occupied,
+            seed: self.seed,
+        }
+    }
+}
+
+/// TLB and page-table counters.
+#[derive(Debug, Clone, Default)]
+pub struct TranslationStats {
+    pub translations: u64,
+    pub l1_hits: u64,
+    pub l2_hits: u64,
+    /// Translations that missed every TLB and walked the page table.
+    pub walks: u64,
+    /// Pages given a frame on their first touch.
+    pub pages_allocated: u64,
+    /// Pages that gave up their frame because physical memory was full.
+    pub pages_reclaimed: u64,
+}
+
+impl TranslationStats {
+    pub fn l1_hit_rate(&self) -> f64 {
+        if self.translations == 0 {
+            0.0
+        } else {
+            self.l1_hits as f64 / self.translations as f64
+        }
+    }
+
+    /// Hit rate of the second-level TLB over the lookups that reached it.
+    pub fn l2_hit_rate(&self) -> f64 {
+        let lookups = self.translations - self.l1_hits;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.l2_hits as f64 / lookups as f64
+        }
+    }
+
+    /// Page walks per thousand translations.
+    pub fn walks_per_kilo(&self) -> f64 {
+        if self.translations == 0 {
+            0.0
+        } else {
+            self.walks as f64 * 1000.0 / self.translations as f64
+        }
+    }
+}
+
+struct TlbEntry {
+    page: u64,
+    frame: u64,
+    stamp: u64,
+}
+
+struct Tlb {
+    sets: Vec<Vec<TlbEntry>>,
+    ways: usize,
+}
+
+impl Tlb {
+    fn new(config: &TlbConfig) -> Self {
+        let sets = config.entries / config.associativity;
+        Self {
+            sets: (0..sets)
+                .map(|_| Vec::with_capacity(config.associativity))
+                .collect(),
+            ways: config.associativity,
+        }
+    }
+
+    fn set_of(&self, page: u64) -> usize {
+        (page % self.sets.len() as u64) as usize
+    }
+
+    /// Returns the cached frame for `page` and refreshes its LRU stamp.
+    fn lookup(&mut self, page: u64, stamp: u64) -> Option<u64> {
+        let set = self.set_of(page);
+        let entry = self.sets[set].iter_mut().find(|entry| entry.page == page)?;
+        entry.stamp = stamp;
+        Some(entry.frame)
+    }
+
+    fn invalidate(&mut self, page: u64) {
+        let set = self.set_of(page);
+        self.sets[set].retain(|entry| entry.page != page);
+    }
+
+    fn fill(&mut self, page: u64, frame: u64, stamp: u64) {
+        let set_index = self.set_of(page);
+        let ways = self.ways;
+        let set = &mut self.sets[set_index];
+        let entry = TlbEntry { page, frame, stamp };
+        if set.len() < ways {
+            set.push(entry);
+        } else if let Some(lru) = set.iter_mut().min_by_key(|entry| entry.stamp) {
+            *lru = entry;
+        }
+    }
+}
+
+/// Translates virtual trace addresses through TLBs and a page table that
+/// allocates frames on first touch.
+pub struct Translator {
+    config: TranslationConfig,
+    l1: Tlb,
+    l2: Option<Tlb>,
+    page_table: PageTable,
+    stamp: u64,
+    stats: TranslationStats,
+    /// Frame reassigned by a translation and not yet taken by the caller.
+    reclaimed: Option<u64>,
+}
+
+impl Translator {
+    pub fn new(config: TranslationConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(Self {
+            l1: Tlb::new(&config.l1_tlb),
+            l2: config.l2_tlb.as_ref().map(Tlb::new),
+            page_table: PageTable::new(&config.mapping()),
+            stamp: 0,
+            stats: TranslationStats::default(),
+            reclaimed: None,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &TranslationConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> &TranslationStats {
+        &self.stats
+    }
+
+    /// Clears the counters while keeping the TLBs and page table.
+    pub fn reset_stats(&mut self) {
+        self.stats = TranslationStats::default();
+    }
+
+    /// Physical address for `virtual_address`.
+    pub fn translate(&mut self, virtual_address: u64) -> u64 {
+        self.stamp += 1;
+        self.stats.translations += 1;
+        let page_bytes = self.page_table.page_bytes();
+        let page = virtual_address / page_bytes;
+        let frame = match self.l1.lookup(page, self.stamp) {
+            Some(frame) => {
+                self.stats.l1_hits += 1;
+                frame
+            }
+            None => {
+                let frame = match self.l2.as_mut().and_then(|l2| l2.lookup(page, self.stamp)) {
+                    Some(frame) => {
+                        self.stats.l2_hits += 1;
+                        frame
+                    }
+                    None => {
+                        self.stats.walks += 1;
+                        let mapped = self.page_table.len();
+                        let frame = self.page_table.frame(page);
+                        let reclaimed = self.page_table.take_reclaimed();
+                        if self.page_table.len() > mapped || reclaimed.is_some() {
+                            self.stats.pages_allocated += 1;
+                        }
+                        if let Some((victim, lost_frame)) = reclaimed {
+                            self.stats.pages_reclaimed += 1;
+                            self.l1.invalidate(victim);
+                            if let Some(l2) = self.l2.as_mut() {
+                                l2.invalidate(victim);
+                            }
+                            self.reclaimed = Some(lost_frame);
+                        }
+                        if let Some(l2) = self.l2.as_mut() {
+                            l2.fill(page, frame, self.stamp);
+                        }
+                        frame
+                    }
+                };
+                self.l1.fill(page, frame, self.stamp);
+                frame
+            }
+        };
+        frame * page_bytes + virtual_address % page_bytes
+    }
+
+    /// Physical addresses of a frame that was taken from one page and given to
+    /// another since the last call. A cache fed by this translator still holds
+    /// the old page's lines there and should invalidate them.
+    pub fn take_reclaimed(&mut self) -> Option<Range<u64>> {
+        let base = self.reclaimed.take()? * self.page_table.page_bytes();
+        Some(base..base + self.page_table.page_bytes())
+    }
+
+    /// `access` with its address translated.
+    pub fn translate_access(&mut self, access: &TraceAccess) -> TraceAccess {
+        TraceAccess {
+            address: self.translate(access.address),
+            ..*access
+        }
+    }
+
+    /// [`Translator::translate_access`] without counting it, as during warm-up.
+    pub fn warm_access(&mut self, access: &TraceAccess) -> TraceAccess {
+        let stats = self.stats.clone();
+        let physical = self.translate_access(access);
+        self.stats = stats;
+        physical
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache::Cache, trace::AccessKind};
+
+    fn config(allocation: FrameAllocation, frames: u64) -> TranslationConfig {
+        TranslationConfig {
+            allocation,
+            physical_memory: frames * PageSize::Base4K.bytes(),
+            seed: 7,
+            ..TranslationConfig::default()
+        }
+    }
+
+    #[test]
+    fn every_policy_hands_out_each_frame_once() {
+        for allocation in [
+            FrameAllocation::Sequential,
+            FrameAllocation::Random,
+            FrameAllocation::BinHopping { colors: 4 },
+            FrameAllocation::PageColored { colors: 4 },
+        ] {
+            let mut table = PageTable::new(&config(allocation, 10).mapping());
+            let mut frames: Vec<u64> = (0..10).map(|page| table.frame(page * 3)).collect();
+            frames.sort();
+            assert_eq!(frames, (0..10).collect::<Vec<_>>(), "{allocation}");
+            assert_eq!(table.take_reclaimed(), None);
+        }
+    }
+
+    #[test]
+    fn page_coloring_matches_frame_and_page_colors_while_they_last() {
+        let mut table =
+            PageTable::new(&config(FrameAllocation::PageColored { colors: 4 }, 16).mapping());
+        for page in 0..16 {
+            assert_eq!(table.frame(page) % 4, page % 4);
+        }
+    }
+
+    #[test]
+    fn full_memory_reclaims_the_oldest_page() {
+        let mut table = PageTable::new(&config(FrameAllocation::Random, 2).mapping());
+        let first = table.frame(10);
+        table.frame(11);
+        assert_eq!(table.frame(12), first);
+        assert_eq!(table.take_reclaimed(), Some((10, first)));
+        assert_eq!(table.len(), 2);
+        // Page 10 faults back in and takes page 11's frame.
+        table.frame(10);
+        assert_eq!(table.take_reclaimed().map(|(page, _)| page), Some(11));
+    }
+
+    #[test]
+    fn translator_drops_tlb_entries_of_reclaimed_pages() {
+        let mut translator = Translator::new(config(FrameAllocation::Random, 2)).unwrap();
+        let page = PageSize::Base4K.bytes();
+        let first = translator.translate(0);
+        let second = translator.translate(page);
+        assert_eq!(translator.take_reclaimed(), None);
+        // A third page takes page 0's frame, whose TLB entry must go.
+        assert_eq!(translator.translate(2 * page + 8), first + 8);
+        assert_eq!(translator.take_reclaimed(), Some(first..first + page));
+        assert_eq!(translator.translate(0), second);
+        let stats = translator.stats();
+        assert_eq!((stats.pages_allocated, stats.pages_reclaimed), (4, 2));
+    }
+
+    #[test]
+    fn warm_translations_fill_the_tlbs_without_counting() {
+        let mut translator = Translator::new(config(FrameAllocation::Sequential, 16)).unwrap();
+        let access = TraceAccess {
+            kind: AccessKind::Read,
+            address: 0x40,
+            thread: 0,
+            size: 4,
+            timestamp: 0,
+        };
+        let warmed = translator.warm_access(&access);
+        assert_eq!(translator.stats().translations, 0);
+        assert_eq!(translator.translate_access(&access), warmed);
+        let stats = translator.stats();
+        assert_eq!((stats.translations, stats.l1_hits, stats.walks), (1, 1, 0));
+    }
+
+    #[test]
+    fn reclaiming_a_dirty_page_writes_its_lines_back() {
+        let mut translator = Translator::new(config(FrameAllocation::Random, 1)).unwrap();
+        let mut cache = Cache::new(CacheConfig::default()).unwrap();
+        let page = PageSize::Base4K.bytes();
+        for (kind, address) in [
+            (AccessKind::Write, 0),
+            (AccessKind::Write, 64),
+            (AccessKind::Read, 128),
+            (AccessKind::Read, page),
+        ] {
+            let access = TraceAccess {
+                kind,
+                address,
+                thread: 0,
+                size: 4,
+                timestamp: 0,
+            };
+            let physical = translator.translate_access(&access);
+            if let Some(frame) = translator.take_reclaimed() {
+                assert_eq!(cache.invalidate_range(frame), 3);
+            }
+            cache.access(&physical);
+        }
+        let block = cache.config().block_size as u64;
+        assert_eq!(cache.stats().traffic.writeback_bytes, 2 * block);
+        // Only the new page's block is left.
+        assert_eq!(cache.stats().traffic.fill_bytes, 4 * block);
+        assert_eq!(cache.invalidate_range(0..page), 1);
+        assert_eq!(cache.stats().traffic.writeback_bytes, 2 * block);
+    }
+
+    #[test]
+    fn configs_need_valid_tlbs_and_room_for_a_page() {
+        let tlb = |entries, associativity| TlbConfig {
+            entries,
+            associativity,
+        };
+        for l1_tlb in [tlb(0, 4), tlb(64, 0), tlb(64, 3)] {
+            let invalid = TranslationConfig {
+                l1_tlb,
+                ..TranslationConfig::default()
+            };
+            assert_eq!(
+                invalid.validate(),
+                Err(ConfigError::InvalidTlb {
+                    entries: l1_tlb.entries,
+                    ways: l1_tlb.associativity,
+                })
+            );
+        }
+        let invalid = TranslationConfig {
+            l2_tlb: Some(tlb(1536, 10)),
+            ..TranslationConfig::default()
+        };
+        assert!(matches!(
+            invalid.validate(),
+            Err(ConfigError::InvalidTlb { .. })
+        ));
+        let invalid = TranslationConfig {
+            page_size: PageSize::Huge2M,
+            physical_memory: 1024 * 1024,
+            ..TranslationConfig::default()
+        };
+        assert_eq!(
+            invalid.validate(),
+            Err(ConfigError::PhysicalMemoryTooSmall {
+                bytes: 1024 * 1024,
+                page: 2 * 1024 * 1024,
+            })
+        );
+        for colors in [0, 5] {
+            assert_eq!(
+                config(FrameAllocation::PageColored { colors }, 4).validate(),
+                Err(ConfigError::InvalidPageColors { colors, frames: 4 })
+            );
+        }
+    }
+
+    #[test]
+    fn bin_hopping_keeps_cycling_colors_in_fragmented_memory() {
+        let fragmented = |allocation| PageMapping {
+            occupied: 0.5,
+            ..config(allocation, 4096).mapping()
+        };
+        let occupancy = Occupancy::new(&fragmented(FrameAllocation::Sequential));
+        let colors_of = |allocation| {
+            let mut table = PageTable::new(&fragmented(allocation));
+            (0..64)
+                .map(|page| {
+                    let frame = table.frame(page);
+                    assert!(!occupancy.holds(frame), "frame {frame} is held elsewhere");
+                    frame % 8
+                })
+                .collect::<Vec<_>>()
+        };
+        let hopping = colors_of(FrameAllocation::BinHopping { colors: 8 });
+        assert_eq!(hopping, (0..64).map(|page| page % 8).collect::<Vec<_>>());
+        // The lowest free frames skip whichever colors other processes hold.
+        assert_ne!(colors_of(FrameAllocation::Sequential), hopping);
+    }
+
+    #[test]
+    fn occupied_share_must_lie_below_one() {
+        for occupied in [-0.1, 1.0, f64::NAN] {
+            let invalid = TranslationConfig {
+                occupied,
+                ..TranslationConfig::default()
+            };
+            assert_eq!(invalid.validate(), Err(ConfigError::InvalidOccupiedMemory));
+        }
+    }
+}